@@ -2,6 +2,7 @@
 
 use crate::application::repositories::knowledge_repository::KnowledgeRepository;
 use crate::domain::table::Table;
+use crate::domain::two_db_error::TwoDbError;
 
 /// Controller for handling knowledge-related operations
 pub struct KnowledgeController<T: KnowledgeRepository> {
@@ -15,42 +16,42 @@ impl<T: KnowledgeRepository> KnowledgeController<T> {
     }
 
     /// Get knowledge about a table
-    pub fn get_table_knowledge(&self, database_name: &str, table_name: &str) -> Result<Table, String> {
+    pub fn get_table_knowledge(&self, database_name: &str, table_name: &str) -> Result<Table, TwoDbError> {
         self.knowledge_repository.get_table_knowledge(database_name, table_name)
     }
 
     /// Update knowledge about a table
-    pub fn update_table_knowledge(&self, table: &Table) -> Result<(), String> {
+    pub fn update_table_knowledge(&self, table: &Table) -> Result<(), TwoDbError> {
         self.knowledge_repository.update_table_knowledge(table)
     }
 
     /// Update knowledge about table row count
-    pub fn update_row_count(&self, table: &mut Table) -> Result<(), String> {
+    pub fn update_row_count(&self, table: &mut Table) -> Result<(), TwoDbError> {
         self.knowledge_repository.update_row_count(table)
     }
 
     /// Update knowledge about table self-referencing status
-    pub fn update_self_referencing(&self, table: &mut Table, database_name: &str) -> Result<(), String> {
+    pub fn update_self_referencing(&self, table: &mut Table, database_name: &str) -> Result<(), TwoDbError> {
         self.knowledge_repository.update_self_referencing(table, database_name)
     }
 
     /// Update knowledge about table export status
-    pub fn update_export_status(&self, table_name: &str, is_exported: bool) -> Result<(), String> {
-        self.knowledge_repository.update_export_status(table_name, is_exported)
+    pub fn update_export_status(&self, table_name: &str, database_name: &str, is_exported: bool) -> Result<(), TwoDbError> {
+        self.knowledge_repository.update_export_status(table_name, database_name, is_exported)
     }
 
     /// Get all tables that have been exported
-    pub fn get_exported_tables(&self) -> Result<Vec<Table>, String> {
+    pub fn get_exported_tables(&self) -> Result<Vec<Table>, TwoDbError> {
         self.knowledge_repository.get_exported_tables()
     }
 
     /// Get all tables that have not been exported
-    pub fn get_non_exported_tables(&self) -> Result<Vec<Table>, String> {
+    pub fn get_non_exported_tables(&self) -> Result<Vec<Table>, TwoDbError> {
         self.knowledge_repository.get_non_exported_tables()
     }
 
     /// Update knowledge about empty tables
-    pub fn update_empty_tables_knowledge(&self, database_name: &str) -> Result<(), String> {
+    pub fn update_empty_tables_knowledge(&self, database_name: &str) -> Result<(), TwoDbError> {
         // This is where we would call the update_empty_tables_knowledge use case
         // In a real implementation, we would create an UpdateEmptyTablesKnowledgeUseCase and call it
         
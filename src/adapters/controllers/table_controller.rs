@@ -3,6 +3,8 @@
 use crate::application::repositories::table_repository::TableRepository;
 use crate::domain::table::Table;
 use crate::domain::two_column::TwoColumn;
+use crate::domain::two_value::TwoValue;
+use crate::domain::two_db_error::TwoDbError;
 
 /// Controller for handling table-related operations
 pub struct TableController<T: TableRepository> {
@@ -16,62 +18,62 @@ impl<T: TableRepository> TableController<T> {
     }
 
     /// Get a table by its name
-    pub fn get_table(&self, database_name: &str, table_name: &str) -> Result<Table, String> {
+    pub fn get_table(&self, database_name: &str, table_name: &str) -> Result<Table, TwoDbError> {
         self.table_repository.get_table_by_name(database_name, table_name)
     }
 
     /// Get all tables in a database
-    pub fn get_all_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
+    pub fn get_all_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
         self.table_repository.get_all_tables(database_name)
     }
 
     /// Get all empty tables in a database
-    pub fn get_empty_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
+    pub fn get_empty_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
         self.table_repository.get_empty_tables(database_name)
     }
 
     /// Get all tables without foreign keys
-    pub fn get_clean_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
+    pub fn get_clean_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
         self.table_repository.get_clean_tables(database_name)
     }
 
     /// Get all self-referencing tables
-    pub fn get_self_referencing_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
+    pub fn get_self_referencing_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
         self.table_repository.get_self_referencing_tables(database_name)
     }
 
     /// Update a table in the database
-    pub fn update_table(&self, table: &Table) -> Result<(), String> {
+    pub fn update_table(&self, table: &Table) -> Result<(), TwoDbError> {
         self.table_repository.update_table(table)
     }
 
     /// Insert a new table into the database
-    pub fn insert_table(&self, table: &Table) -> Result<(), String> {
+    pub fn insert_table(&self, table: &Table) -> Result<(), TwoDbError> {
         self.table_repository.insert_table(table)
     }
 
     /// Check if a table exists
-    pub fn table_exists(&self, table: &Table) -> Result<bool, String> {
+    pub fn table_exists(&self, table: &Table) -> Result<bool, TwoDbError> {
         self.table_repository.table_exists(table)
     }
 
     /// Get columns of a table
-    pub fn get_columns(&self, database_name: &str, table_name: &str) -> Result<Vec<TwoColumn>, String> {
+    pub fn get_columns(&self, database_name: &str, table_name: &str) -> Result<Vec<TwoColumn>, TwoDbError> {
         self.table_repository.get_columns(database_name, table_name)
     }
 
     /// Get rows of a table
-    pub fn get_rows(&self, database_name: &str, table_name: &str) -> Result<Vec<Vec<String>>, String> {
+    pub fn get_rows(&self, database_name: &str, table_name: &str) -> Result<Vec<Vec<TwoValue>>, TwoDbError> {
         self.table_repository.get_rows(database_name, table_name)
     }
 
     /// Execute a query on a database
-    pub fn execute_query(&self, database_name: &str, query: &str) -> Result<(), String> {
-        self.table_repository.execute_query(database_name, query)
+    pub fn execute_query(&self, database_name: &str, query: &str, params: &[TwoValue]) -> Result<(), TwoDbError> {
+        self.table_repository.execute_query(database_name, query, params)
     }
 
     /// Move data from one table to another
-    pub fn move_table_data(&self, source_db: &str, source_table: &str, target_db: &str, target_table: &str) -> Result<(), String> {
+    pub fn move_table_data(&self, source_db: &str, source_table: &str, target_db: &str, target_table: &str) -> Result<(), TwoDbError> {
         // This is where we would call the move_table_data use case
         // In a real implementation, we would create a MoveTableDataUseCase and call it
         
@@ -1,248 +1,168 @@
 /*! This file contains the PostgresTableGateway implementation. */
 
+use std::collections::HashMap;
+use postgres::Client;
+use postgres::types::ToSql;
 use crate::application::repositories::table_repository::TableRepository;
-use crate::domain::table::{Table, TableType, ExportComplexityType};
+use crate::core::config::ConnectionConfig;
+use crate::core::database::{pg_connect_with, PgClient};
+use crate::core::get_knowledge::build_column;
+use crate::core::postgresql_queries::{
+    query_get_base_tables, query_get_clean_tables, query_get_columns_by_table, query_get_empty_tables,
+    query_get_self_references_tables, query_table_exists,
+};
+use crate::core::table::{build_base_simple_table, build_self_references_table};
+use crate::domain::table::Table;
 use crate::domain::two_column::TwoColumn;
-use std::error::Error;
+use crate::domain::two_db_error::TwoDbError;
+use crate::domain::two_value::TwoValue;
 
 /// PostgreSQL implementation of the TableRepository trait
 pub struct PostgresTableGateway {
-    connection_string: String,
+    /// Connection profiles, the database given to each method is the name of its profile
+    config: ConnectionConfig,
 }
 
 impl PostgresTableGateway {
-    /// Create a new PostgresTableGateway with the given connection string
-    pub fn new(connection_string: String) -> Self {
-        Self { connection_string }
+    /// Create a new PostgresTableGateway with the given connection profiles
+    pub fn new(config: ConnectionConfig) -> Self {
+        Self { config }
     }
 
-    /// Helper method to establish a connection to the database
-    fn connect(&self) -> Result<(), Box<dyn Error>> {
-        // In a real implementation, this would establish a connection to PostgreSQL
-        // using the connection_string
-        Ok(())
+    /// Create a new PostgresTableGateway from the config file, or the `POSTGRES_*` variables
+    pub fn from_config() -> Result<Self, TwoDbError> {
+        ConnectionConfig::load().map(Self::new)
+    }
+
+    /// Helper method to borrow a connection to a database from its pool
+    fn connect(&self, database_name: &str) -> Result<PgClient, TwoDbError> {
+        pg_connect_with(database_name, || self.config.profile(database_name).cloned())
+    }
+
+    fn count_rows(client: &mut Client, table_name: &str) -> Result<i64, TwoDbError> {
+        let query = format!("SELECT COUNT(*) FROM {}", table_name);
+        client.query_one(&query, &[])
+            .map(|row| row.get(0))
+            .map_err(|e| TwoDbError::transfer(table_name, &e))
+    }
+
+    /// Self-referencing column of each self-referencing table
+    fn get_self_referencing_columns(client: &mut Client, database_name: &str) -> Result<HashMap<String, String>, TwoDbError> {
+        let rows = client.query(query_get_self_references_tables(), &[])
+            .map_err(|e| TwoDbError::catalog(database_name, e))?;
+        Ok(rows.iter().map(|row| (row.get("table_name"), row.get("column_name"))).collect())
+    }
+
+    /// Build tables with their row count and self-referencing column from a list of names
+    fn build_tables(&self, client: &mut Client, database_name: &str, table_names: Vec<String>) -> Result<Vec<Table>, TwoDbError> {
+        let mut self_referencing_columns = Self::get_self_referencing_columns(client, database_name)?;
+        table_names.into_iter().map(|table_name| {
+            let mut table = match self_referencing_columns.remove(&table_name) {
+                Some(column) => {
+                    let mut table = build_self_references_table(table_name.clone(), database_name.to_string());
+                    table.self_referencing_column = column;
+                    table
+                }
+                None => build_base_simple_table(table_name.clone(), database_name.to_string()),
+            };
+            table.row_count = Self::count_rows(client, &table_name)?;
+            Ok(table)
+        }).collect()
+    }
+
+    fn query_table_names(client: &mut Client, database_name: &str, query: &str) -> Result<Vec<String>, TwoDbError> {
+        let rows = client.query(query, &[])
+            .map_err(|e| TwoDbError::catalog(database_name, e))?;
+        Ok(rows.iter().map(|row| row.get("table_name")).collect())
     }
 }
 
 impl TableRepository for PostgresTableGateway {
-    fn get_table_by_name(&self, database_name: &str, table_name: &str) -> Result<Table, String> {
-        // In a real implementation, this would query PostgreSQL for the table
-        // and convert the result to a Table entity
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching a table from PostgreSQL
-                let table = Table {
-                    id: 1,
-                    name: table_name.to_string(),
-                    table_type: TableType::BaseTable,
-                    export_complexity_type: ExportComplexityType::SIMPLE,
-                    database: database_name.to_string(),
-                    export_order: 0,
-                    is_self_referencing: false,
-                    self_referencing_column: String::new(),
-                    row_count: 0,
-                    is_exported: false,
-                };
-                Ok(table)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
+    fn get_table_by_name(&self, database_name: &str, table_name: &str) -> Result<Table, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let exists: bool = client.query_one(query_table_exists(), &[&table_name])
+            .map(|row| row.get(0))
+            .map_err(|e| TwoDbError::catalog(database_name, e))?;
+        if !exists {
+            return Err(TwoDbError::catalog(database_name, format!("Table {} does not exist", table_name)));
         }
+
+        let mut tables = self.build_tables(&mut client, database_name, vec![table_name.to_string()])?;
+        Ok(tables.remove(0))
     }
-    
-    fn get_all_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
-        // In a real implementation, this would query PostgreSQL for all tables
-        // and convert the results to Table entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching all tables from PostgreSQL
-                let tables = vec![
-                    Table {
-                        id: 1,
-                        name: "table1".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: database_name.to_string(),
-                        export_order: 0,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 0,
-                        is_exported: false,
-                    },
-                    Table {
-                        id: 2,
-                        name: "table2".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: database_name.to_string(),
-                        export_order: 0,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 0,
-                        is_exported: false,
-                    },
-                ];
-                Ok(tables)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn get_all_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let table_names = Self::query_table_names(&mut client, database_name, query_get_base_tables())?;
+        self.build_tables(&mut client, database_name, table_names)
     }
-    
-    fn get_empty_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
-        // In a real implementation, this would query PostgreSQL for empty tables
-        // and convert the results to Table entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching empty tables from PostgreSQL
-                let tables = vec![
-                    Table {
-                        id: 1,
-                        name: "empty_table1".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: database_name.to_string(),
-                        export_order: 0,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 0,
-                        is_exported: false,
-                    },
-                ];
-                Ok(tables)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn get_empty_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let table_names = Self::query_table_names(&mut client, database_name, query_get_empty_tables())?;
+        Ok(table_names.into_iter()
+            .map(|table_name| build_base_simple_table(table_name, database_name.to_string()))
+            .collect())
     }
-    
-    fn get_clean_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
-        // In a real implementation, this would query PostgreSQL for tables without foreign keys
-        // and convert the results to Table entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching clean tables from PostgreSQL
-                let tables = vec![
-                    Table {
-                        id: 1,
-                        name: "clean_table1".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: database_name.to_string(),
-                        export_order: 0,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 0,
-                        is_exported: false,
-                    },
-                ];
-                Ok(tables)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn get_clean_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let table_names = Self::query_table_names(&mut client, database_name, query_get_clean_tables())?;
+        self.build_tables(&mut client, database_name, table_names)
     }
-    
-    fn get_self_referencing_tables(&self, database_name: &str) -> Result<Vec<Table>, String> {
-        // In a real implementation, this would query PostgreSQL for self-referencing tables
-        // and convert the results to Table entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching self-referencing tables from PostgreSQL
-                let tables = vec![
-                    Table {
-                        id: 1,
-                        name: "self_ref_table1".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::COMPLEX,
-                        database: database_name.to_string(),
-                        export_order: 0,
-                        is_self_referencing: true,
-                        self_referencing_column: "parent_id".to_string(),
-                        row_count: 10,
-                        is_exported: false,
-                    },
-                ];
-                Ok(tables)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn get_self_referencing_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let mut table_names = Self::get_self_referencing_columns(&mut client, database_name)?
+            .into_keys()
+            .collect::<Vec<_>>();
+        table_names.sort();
+        self.build_tables(&mut client, database_name, table_names)
     }
-    
-    fn update_table(&self, table: &Table) -> Result<(), String> {
-        // In a real implementation, this would update the table in PostgreSQL
-        match self.connect() {
-            Ok(_) => {
-                // Simulate updating a table in PostgreSQL
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn update_table(&self, table: &Table) -> Result<(), TwoDbError> {
+        Err(TwoDbError::Knowledge(format!("Table {} is not stored in PostgreSQL, update it in the knowledge repository", table.name)))
     }
-    
-    fn insert_table(&self, table: &Table) -> Result<(), String> {
-        // In a real implementation, this would insert a new table into PostgreSQL
-        match self.connect() {
-            Ok(_) => {
-                // Simulate inserting a table into PostgreSQL
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn insert_table(&self, table: &Table) -> Result<(), TwoDbError> {
+        Err(TwoDbError::Knowledge(format!("Table {} is not stored in PostgreSQL, insert it in the knowledge repository", table.name)))
     }
-    
-    fn table_exists(&self, table: &Table) -> Result<bool, String> {
-        // In a real implementation, this would check if a table exists in PostgreSQL
-        match self.connect() {
-            Ok(_) => {
-                // Simulate checking if a table exists in PostgreSQL
-                Ok(true)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn table_exists(&self, table: &Table) -> Result<bool, TwoDbError> {
+        let mut client = self.connect(&table.database)?;
+        client.query_one(query_table_exists(), &[&table.name])
+            .map(|row| row.get(0))
+            .map_err(|e| TwoDbError::catalog(&table.database, e))
     }
-    
-    fn get_columns(&self, database_name: &str, table_name: &str) -> Result<Vec<TwoColumn>, String> {
-        // In a real implementation, this would query PostgreSQL for the columns of a table
-        // and convert the results to TwoColumn entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching columns from PostgreSQL
-                let columns = vec![
-                    TwoColumn {
-                        name: "id".to_string(),
-                        data_type: "integer".to_string(),
-                    },
-                    TwoColumn {
-                        name: "name".to_string(),
-                        data_type: "varchar".to_string(),
-                    },
-                ];
-                Ok(columns)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn get_columns(&self, database_name: &str, table_name: &str) -> Result<Vec<TwoColumn>, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let rows = client.query(query_get_columns_by_table(), &[&table_name])
+            .map_err(|e| TwoDbError::catalog(database_name, e))?;
+        Ok(rows.iter().map(build_column).collect())
     }
-    
-    fn get_rows(&self, database_name: &str, table_name: &str) -> Result<Vec<Vec<String>>, String> {
-        // In a real implementation, this would query PostgreSQL for the rows of a table
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching rows from PostgreSQL
-                let rows = vec![
-                    vec!["1".to_string(), "Row 1".to_string()],
-                    vec!["2".to_string(), "Row 2".to_string()],
-                ];
-                Ok(rows)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn get_rows(&self, database_name: &str, table_name: &str) -> Result<Vec<Vec<TwoValue>>, TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let query = format!("SELECT * FROM {}", table_name);
+        let rows = client.query(&query, &[])
+            .map_err(|e| TwoDbError::transfer(table_name, &e))?;
+        rows.iter().map(|row| {
+            (0..row.len())
+                .map(|i| row.try_get::<_, TwoValue>(i)
+                    .map_err(|e| TwoDbError::Transfer {
+                        table: table_name.to_string(),
+                        message: format!("Failed to read column {}: {}", row.columns()[i].name(), e),
+                    }))
+                .collect()
+        }).collect()
     }
-    
-    fn execute_query(&self, database_name: &str, query: &str) -> Result<(), String> {
-        // In a real implementation, this would execute a query on PostgreSQL
-        match self.connect() {
-            Ok(_) => {
-                // Simulate executing a query on PostgreSQL
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    fn execute_query(&self, database_name: &str, query: &str, params: &[TwoValue]) -> Result<(), TwoDbError> {
+        let mut client = self.connect(database_name)?;
+        let params = params.iter().map(|v| v as &(dyn ToSql + Sync)).collect::<Vec<_>>();
+        client.execute(query, &params)
+            .map(|_| ())
+            .map_err(|e| TwoDbError::transfer("", &e))
     }
-}
\ No newline at end of file
+}
@@ -1,173 +1,849 @@
 /*! This file contains the SqliteKnowledgeGateway implementation. */
 
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use rusqlite::{Connection, OptionalExtension, Params, Row, params};
+use rusqlite::types::Type;
 use crate::application::repositories::knowledge_repository::KnowledgeRepository;
-use crate::domain::table::{Table, TableType, ExportComplexityType};
-use std::error::Error;
+use crate::core::SQLITE_DATABASE_PATH;
+use crate::core::sqlite_queries::{
+    query_get_running_migration_run, query_update_export_order, query_update_row_count, query_upsert_table_checkpoint,
+};
+use crate::domain::mapping_rule::{MappingRule, MappingRuleKind};
+use crate::domain::migration_run::{MigrationRun, MigrationRunStatus, TableCheckpoint};
+use crate::domain::row_filter::RowFilter;
+use crate::domain::schema_diff::{DifferenceKind, SchemaDiff, SchemaDifference, SchemaObject};
+use crate::domain::table::{Table, TableType, ExportComplexityType, SelfReferencingStrategy};
+use crate::domain::table_sync::TableSync;
+use crate::domain::transform_rule::{TransformKind, TransformRule};
+use crate::domain::two_db_error::TwoDbError;
+
+const SELECT_PART: &str = "SELECT
+            id,
+            name,
+            table_type,
+            export_complexity_type,
+            database,
+            export_order,
+            is_self_referencing,
+            self_referencing_column,
+            row_count,
+            is_exported,
+            self_referencing_strategy
+        FROM tables ";
+
+/// Columns added after the first release of the `tables` table
+const ADDED_COLUMNS: [(&str, &str); 1] = [
+    ("self_referencing_strategy", "TEXT NOT NULL DEFAULT ''"),
+];
+
+const SELECT_MAPPING_RULES: &str = "SELECT
+            id,
+            database,
+            table_name,
+            kind,
+            column_name,
+            target_name,
+            value
+        FROM mapping_rules ";
+
+const SELECT_TRANSFORM_RULES: &str = "SELECT
+            id,
+            database,
+            table_name,
+            column_name,
+            kind,
+            value,
+            replacement
+        FROM transform_rules ";
+
+const SELECT_ROW_FILTERS: &str = "SELECT
+            id,
+            database,
+            table_name,
+            condition,
+            is_seed
+        FROM row_filters ";
+
+const SELECT_SCHEMA_DIFFERENCES: &str = "SELECT
+            table_name,
+            object_type,
+            object_name,
+            difference,
+            source_value,
+            target_value
+        FROM schema_differences ";
+
+const SELECT_TABLE_SYNCS: &str = "SELECT
+            id,
+            database,
+            table_name,
+            change_column,
+            watermark,
+            synced_at
+        FROM table_syncs ";
+
+/// Knowledge bases whose tables were checked by this process
+static SCHEMA_READY: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
 
 /// SQLite implementation of the KnowledgeRepository trait
 pub struct SqliteKnowledgeGateway {
     db_path: String,
 }
 
+impl Default for SqliteKnowledgeGateway {
+    /// Knowledge base of the application, `twodb.db` in the working directory
+    fn default() -> Self {
+        Self::new(SQLITE_DATABASE_PATH.to_string())
+    }
+}
+
 impl SqliteKnowledgeGateway {
     /// Create a new SqliteKnowledgeGateway with the given database path
     pub fn new(db_path: String) -> Self {
         Self { db_path }
     }
 
-    /// Helper method to establish a connection to the database
-    fn connect(&self) -> Result<(), Box<dyn Error>> {
-        // In a real implementation, this would establish a connection to SQLite
-        // using the db_path
-        Ok(())
+    /// Helper method to open the database, with every table up to date
+    ///
+    /// The tables are checked on the first connection of the process only
+    fn connect(&self) -> Result<Connection, TwoDbError> {
+        let conn = Connection::open(&self.db_path)
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to open {}: {}", self.db_path, e)))?;
+        let mut ready = SCHEMA_READY.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner);
+        if !ready.contains(&self.db_path) {
+            Self::ensure_schema(&conn)
+                .map_err(|e| TwoDbError::Knowledge(format!("Failed to create the tables of {}: {}", self.db_path, e)))?;
+            ready.insert(self.db_path.clone());
+        }
+        Ok(conn)
     }
-}
 
-impl KnowledgeRepository for SqliteKnowledgeGateway {
-    fn get_table_knowledge(&self, database_name: &str, table_name: &str) -> Result<Table, String> {
-        // In a real implementation, this would query SQLite for knowledge about the table
-        // and convert the result to a Table entity
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching table knowledge from SQLite
-                let table = Table {
-                    id: 1,
-                    name: table_name.to_string(),
-                    table_type: TableType::BaseTable,
-                    export_complexity_type: ExportComplexityType::SIMPLE,
-                    database: database_name.to_string(),
-                    export_order: 0,
-                    is_self_referencing: false,
-                    self_referencing_column: String::new(),
-                    row_count: 0,
-                    is_exported: false,
-                };
-                Ok(table)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+    /// Create the tables of the knowledge base that do not exist yet
+    pub fn ensure_schema(conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tables (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                table_type TEXT NOT NULL,
+                export_complexity_type TEXT NOT NULL,
+                database TEXT NOT NULL,
+                export_order INTEGER NOT NULL,
+                is_self_referencing BOOLEAN NOT NULL,
+                self_referencing_column TEXT,
+                row_count INTEGER NOT NULL DEFAULT 0,
+                is_exported BOOLEAN NOT NULL DEFAULT FALSE,
+                self_referencing_strategy TEXT NOT NULL DEFAULT ''
+            );
+            CREATE TABLE IF NOT EXISTS migration_runs (
+                id INTEGER PRIMARY KEY,
+                source_database TEXT NOT NULL,
+                target_database TEXT NOT NULL,
+                status TEXT NOT NULL,
+                started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                finished_at TEXT
+            );
+            CREATE TABLE IF NOT EXISTS table_checkpoints (
+                id INTEGER PRIMARY KEY,
+                run_id INTEGER REFERENCES migration_runs (id),
+                table_name TEXT NOT NULL,
+                database TEXT NOT NULL,
+                primary_key_column TEXT NOT NULL,
+                last_primary_key TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (table_name, database)
+            );
+            CREATE TABLE IF NOT EXISTS suppressed_triggers (
+                id INTEGER PRIMARY KEY,
+                run_id INTEGER REFERENCES migration_runs (id),
+                table_name TEXT NOT NULL,
+                database TEXT NOT NULL,
+                trigger_name TEXT NOT NULL,
+                trigger_mode TEXT NOT NULL,
+                suppressed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE IF NOT EXISTS mapping_rules (
+                id INTEGER PRIMARY KEY,
+                database TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                column_name TEXT NOT NULL DEFAULT '',
+                target_name TEXT NOT NULL DEFAULT '',
+                value TEXT NOT NULL DEFAULT ''
+            );
+            CREATE TABLE IF NOT EXISTS transform_rules (
+                id INTEGER PRIMARY KEY,
+                database TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                value TEXT NOT NULL DEFAULT '',
+                replacement TEXT NOT NULL DEFAULT ''
+            );
+            CREATE TABLE IF NOT EXISTS row_filters (
+                id INTEGER PRIMARY KEY,
+                database TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                condition TEXT NOT NULL,
+                is_seed BOOLEAN NOT NULL DEFAULT FALSE
+            );
+            CREATE TABLE IF NOT EXISTS table_syncs (
+                id INTEGER PRIMARY KEY,
+                database TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                change_column TEXT NOT NULL DEFAULT '',
+                watermark TEXT NOT NULL DEFAULT '',
+                synced_at TEXT,
+                UNIQUE (database, table_name)
+            );
+            CREATE TABLE IF NOT EXISTS schema_differences (
+                id INTEGER PRIMARY KEY,
+                source_database TEXT NOT NULL,
+                target_database TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                object_type TEXT NOT NULL,
+                object_name TEXT NOT NULL,
+                difference TEXT NOT NULL,
+                source_value TEXT,
+                target_value TEXT,
+                compared_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        )?;
+        Self::add_missing_columns(conn)
     }
-    
-    fn update_table_knowledge(&self, table: &Table) -> Result<(), String> {
-        // In a real implementation, this would update knowledge about the table in SQLite
-        match self.connect() {
-            Ok(_) => {
-                // Simulate updating table knowledge in SQLite
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
+
+    /// Bring a `tables` table created by an older version up to date
+    fn add_missing_columns(conn: &Connection) -> rusqlite::Result<()> {
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('tables')")?;
+        let existing_columns = stmt.query_map(params![], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        for (name, definition) in ADDED_COLUMNS {
+            if existing_columns.iter().any(|c| c == name) {
+                continue;
+            }
+            conn.execute(
+                &format!("ALTER TABLE tables ADD COLUMN {} {}", name, definition),
+                params![],
+            )?;
         }
+        Ok(())
     }
-    
-    fn update_row_count(&self, table: &mut Table) -> Result<(), String> {
-        // In a real implementation, this would update the row count of the table in SQLite
-        match self.connect() {
-            Ok(_) => {
-                // Simulate updating row count in SQLite
-                table.row_count = 10; // Just a placeholder value
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    /// Rows of `select` matching `condition`, e.g. `WHERE database = ?1`, each built by `build_row`
+    ///
+    /// `what` names the rows in the error message
+    fn query_rows<T>(
+        &self,
+        select: &str,
+        condition: &str,
+        params: impl Params,
+        build_row: fn(&Row<'_>) -> rusqlite::Result<T>,
+        what: &str,
+    ) -> Result<Vec<T>, TwoDbError> {
+        let conn = self.connect()?;
+        let query = String::from(select) + " " + condition;
+        let mut stmt = conn.prepare(&query)
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to prepare query {}: {}", query, e)))?;
+        let rows = stmt.query_map(params, build_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<T>>>())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to query {}: {}", what, e)))?;
+        Ok(rows)
     }
-    
-    fn update_self_referencing(&self, table: &mut Table, database_name: &str) -> Result<(), String> {
-        // In a real implementation, this would update the self-referencing status of the table in SQLite
-        match self.connect() {
-            Ok(_) => {
-                // Simulate updating self-referencing status in SQLite
-                table.is_self_referencing = true; // Just a placeholder value
-                table.self_referencing_column = "parent_id".to_string(); // Just a placeholder value
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    /// Run a statement that returns no rows, `what` tells what failed in the error message
+    fn execute(&self, sql: &str, params: impl Params, what: impl FnOnce() -> String) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(sql, params)
+            .map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to {}: {}", what(), e)))
     }
-    
-    fn update_export_status(&self, table_name: &str, is_exported: bool) -> Result<(), String> {
-        // In a real implementation, this would update the export status of the table in SQLite
-        match self.connect() {
-            Ok(_) => {
-                // Simulate updating export status in SQLite
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
-        }
+
+    /// Build a Table from a row of `SELECT_PART`
+    fn build_table(row: &Row<'_>) -> rusqlite::Result<Table> {
+        Ok(Table {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            table_type: TableType::from_name(&row.get::<_, String>(2)?),
+            export_complexity_type: ExportComplexityType::from_name(&row.get::<_, String>(3)?),
+            database: row.get(4)?,
+            export_order: row.get(5)?,
+            is_self_referencing: row.get(6)?,
+            self_referencing_column: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+            row_count: row.get(8)?,
+            is_exported: row.get(9)?,
+            self_referencing_strategy: SelfReferencingStrategy::from_name(&row.get::<_, String>(10)?),
+        })
+    }
+
+    fn query_tables(&self, condition: &str, params: impl Params) -> Result<Vec<Table>, TwoDbError> {
+        self.query_rows(SELECT_PART, condition, params, Self::build_table, "tables")
+    }
+
+    /// Build a MappingRule from a row of `SELECT_MAPPING_RULES`
+    fn build_mapping_rule(row: &Row<'_>) -> rusqlite::Result<MappingRule> {
+        Ok(MappingRule {
+            id: row.get(0)?,
+            database: row.get(1)?,
+            table_name: row.get(2)?,
+            kind: MappingRuleKind::from_name(&row.get::<_, String>(3)?),
+            column_name: row.get(4)?,
+            target_name: row.get(5)?,
+            value: row.get(6)?,
+        })
+    }
+
+    fn query_mapping_rules(&self, condition: &str, params: impl Params) -> Result<Vec<MappingRule>, TwoDbError> {
+        self.query_rows(SELECT_MAPPING_RULES, condition, params, Self::build_mapping_rule, "mapping rules")
+    }
+
+    /// Rules of every table of the source database, in the order they were added
+    pub fn get_mapping_rules_of_database(&self, database_name: &str) -> Result<Vec<MappingRule>, TwoDbError> {
+        self.query_mapping_rules("WHERE database = ?1 ORDER BY table_name, id", [database_name])
+    }
+
+    pub fn insert_mapping_rule(&self, rule: &MappingRule) -> Result<(), TwoDbError> {
+        self.execute(
+            "INSERT INTO mapping_rules (database, table_name, kind, column_name, target_name, value)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                rule.database,
+                rule.table_name,
+                rule.kind.name(),
+                rule.column_name,
+                rule.target_name,
+                rule.value,
+            ],
+            || format!("insert mapping rule of table {}", rule.table_name),
+        )
+    }
+
+    pub fn delete_mapping_rule(&self, id: i64) -> Result<(), TwoDbError> {
+        self.execute("DELETE FROM mapping_rules WHERE id = ?1", params![id], || format!("delete mapping rule {}", id))
+    }
+
+    /// Build a TransformRule from a row of `SELECT_TRANSFORM_RULES`
+    fn build_transform_rule(row: &Row<'_>) -> rusqlite::Result<TransformRule> {
+        let kind_name = row.get::<_, String>(4)?;
+        let kind = TransformKind::from_name(&kind_name).ok_or_else(|| {
+            let message = format!("Unknown transform kind: {}", kind_name);
+            rusqlite::Error::FromSqlConversionFailure(4, Type::Text, message.into())
+        })?;
+        Ok(TransformRule {
+            id: row.get(0)?,
+            database: row.get(1)?,
+            table_name: row.get(2)?,
+            column_name: row.get(3)?,
+            kind,
+            value: row.get(5)?,
+            replacement: row.get(6)?,
+        })
+    }
+
+    fn query_transform_rules(&self, condition: &str, params: impl Params) -> Result<Vec<TransformRule>, TwoDbError> {
+        self.query_rows(SELECT_TRANSFORM_RULES, condition, params, Self::build_transform_rule, "transform rules")
+    }
+
+    /// Rules of every table of the source database, in the order they were added
+    pub fn get_transform_rules_of_database(&self, database_name: &str) -> Result<Vec<TransformRule>, TwoDbError> {
+        self.query_transform_rules("WHERE database = ?1 ORDER BY table_name, id", [database_name])
+    }
+
+    /// Rules of a table of the source database, in the order they were added
+    pub fn get_transform_rules(&self, database_name: &str, table_name: &str) -> Result<Vec<TransformRule>, TwoDbError> {
+        self.query_transform_rules("WHERE database = ?1 AND table_name = ?2 ORDER BY id", [database_name, table_name])
+    }
+
+    pub fn insert_transform_rule(&self, rule: &TransformRule) -> Result<(), TwoDbError> {
+        self.execute(
+            "INSERT INTO transform_rules (database, table_name, column_name, kind, value, replacement)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                rule.database,
+                rule.table_name,
+                rule.column_name,
+                rule.kind.name(),
+                rule.value,
+                rule.replacement,
+            ],
+            || format!("insert transform rule of table {}", rule.table_name),
+        )
+    }
+
+    pub fn delete_transform_rule(&self, id: i64) -> Result<(), TwoDbError> {
+        self.execute("DELETE FROM transform_rules WHERE id = ?1", params![id], || format!("delete transform rule {}", id))
+    }
+
+    /// Build a RowFilter from a row of `SELECT_ROW_FILTERS`
+    fn build_row_filter(row: &Row<'_>) -> rusqlite::Result<RowFilter> {
+        Ok(RowFilter {
+            id: row.get(0)?,
+            database: row.get(1)?,
+            table_name: row.get(2)?,
+            condition: row.get(3)?,
+            is_seed: row.get(4)?,
+        })
+    }
+
+    /// Filters of every table of the source database, in the order they were added
+    pub fn get_row_filters_of_database(&self, database_name: &str) -> Result<Vec<RowFilter>, TwoDbError> {
+        let condition = "WHERE database = ?1 ORDER BY table_name, id";
+        self.query_rows(SELECT_ROW_FILTERS, condition, [database_name], Self::build_row_filter, "row filters")
+    }
+
+    pub fn insert_row_filter(&self, filter: &RowFilter) -> Result<(), TwoDbError> {
+        self.execute(
+            "INSERT INTO row_filters (database, table_name, condition, is_seed) VALUES (?1, ?2, ?3, ?4)",
+            params![filter.database, filter.table_name, filter.condition, filter.is_seed],
+            || format!("insert row filter of table {}", filter.table_name),
+        )
+    }
+
+    pub fn delete_row_filter(&self, id: i64) -> Result<(), TwoDbError> {
+        self.execute("DELETE FROM row_filters WHERE id = ?1", params![id], || format!("delete row filter {}", id))
+    }
+
+    /// Build a TableSync from a row of `SELECT_TABLE_SYNCS`
+    fn build_table_sync(row: &Row<'_>) -> rusqlite::Result<TableSync> {
+        Ok(TableSync {
+            id: row.get(0)?,
+            database: row.get(1)?,
+            table_name: row.get(2)?,
+            change_column: row.get(3)?,
+            watermark: row.get(4)?,
+            synced_at: row.get(5)?,
+        })
+    }
+
+    fn query_table_syncs(&self, condition: &str, params: impl Params) -> Result<Vec<TableSync>, TwoDbError> {
+        self.query_rows(SELECT_TABLE_SYNCS, condition, params, Self::build_table_sync, "table syncs")
+    }
+
+    /// Syncs of every table of the source database that has a change column or has been synced
+    pub fn get_table_syncs_of_database(&self, database_name: &str) -> Result<Vec<TableSync>, TwoDbError> {
+        self.query_table_syncs("WHERE database = ?1 ORDER BY table_name", [database_name])
+    }
+
+    pub fn get_table_sync(&self, database_name: &str, table_name: &str) -> Result<Option<TableSync>, TwoDbError> {
+        self.query_table_syncs("WHERE database = ?1 AND table_name = ?2", [database_name, table_name])
+            .map(|syncs| syncs.into_iter().next())
+    }
+
+    /// Set the change column of a table, its watermark starts over
+    pub fn upsert_change_column(&self, sync: &TableSync) -> Result<(), TwoDbError> {
+        self.execute(
+            "INSERT INTO table_syncs (database, table_name, change_column) VALUES (?1, ?2, ?3)
+            ON CONFLICT (database, table_name) DO UPDATE SET
+                change_column = excluded.change_column,
+                watermark = ''",
+            params![sync.database, sync.table_name, sync.change_column],
+            || format!("save the change column of table {}", sync.table_name),
+        )
+    }
+
+    /// Record a sync of a table, up to `watermark` when it has a change column
+    pub fn save_watermark(&self, database_name: &str, table_name: &str, watermark: &str) -> Result<(), TwoDbError> {
+        self.execute(
+            "INSERT INTO table_syncs (database, table_name, watermark, synced_at) VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+            ON CONFLICT (database, table_name) DO UPDATE SET
+                watermark = excluded.watermark,
+                synced_at = excluded.synced_at",
+            params![database_name, table_name, watermark],
+            || format!("save the watermark of table {}", table_name),
+        )
+    }
+
+    pub fn delete_table_sync(&self, id: i64) -> Result<(), TwoDbError> {
+        self.execute("DELETE FROM table_syncs WHERE id = ?1", params![id], || format!("delete table sync {}", id))
+    }
+
+    /// Get the run left unfinished between these databases, if any
+    pub fn get_running_migration_run(&self, source_database_name: &str, target_database_name: &str) -> Result<Option<i64>, TwoDbError> {
+        let conn = self.connect()?;
+        conn.query_row(
+            query_get_running_migration_run(),
+            params![source_database_name, target_database_name, MigrationRunStatus::Running.name()],
+            |row| row.get(0),
+        ).optional()
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to query the running migration run: {}", e)))
     }
-    
-    fn get_exported_tables(&self) -> Result<Vec<Table>, String> {
-        // In a real implementation, this would query SQLite for all exported tables
-        // and convert the results to Table entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching exported tables from SQLite
-                let tables = vec![
-                    Table {
-                        id: 1,
-                        name: "exported_table1".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: "database1".to_string(),
-                        export_order: 1,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 10,
-                        is_exported: true,
-                    },
-                    Table {
-                        id: 2,
-                        name: "exported_table2".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: "database1".to_string(),
-                        export_order: 2,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 20,
-                        is_exported: true,
-                    },
-                ];
-                Ok(tables)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
+
+    /// Get the latest run between these databases, finished or not
+    pub fn get_last_migration_run(&self, source_database_name: &str, target_database_name: &str) -> Result<Option<MigrationRun>, TwoDbError> {
+        let conn = self.connect()?;
+        conn.query_row(
+            "SELECT id, status, started_at, finished_at
+            FROM migration_runs
+            WHERE source_database = ?1 AND target_database = ?2
+            ORDER BY id DESC
+            LIMIT 1",
+            params![source_database_name, target_database_name],
+            |row| Ok(MigrationRun {
+                id: row.get(0)?,
+                status: MigrationRunStatus::from_name(&row.get::<_, String>(1)?),
+                started_at: row.get(2)?,
+                finished_at: row.get(3)?,
+            }),
+        ).optional()
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to query the last migration run: {}", e)))
+    }
+
+    /// Start a run between these databases, return its id
+    pub fn insert_migration_run(&self, source_database_name: &str, target_database_name: &str) -> Result<i64, TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO migration_runs (source_database, target_database, status) VALUES (?1, ?2, ?3)",
+            params![source_database_name, target_database_name, MigrationRunStatus::Running.name()],
+        ).map(|_| conn.last_insert_rowid())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to insert migration run: {}", e)))
+    }
+
+    pub fn finish_migration_run(&self, run_id: i64) -> Result<(), TwoDbError> {
+        self.execute(
+            "UPDATE migration_runs SET status = ?1, finished_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![MigrationRunStatus::Finished.name(), run_id],
+            || format!("finish migration run {}", run_id),
+        )
+    }
+
+    /// Last primary key value committed to the target for a table, as text
+    pub fn get_checkpoint(&self, table_name: &str, database_name: &str) -> Result<Option<String>, TwoDbError> {
+        let conn = self.connect()?;
+        conn.query_row(
+            "SELECT last_primary_key FROM table_checkpoints WHERE table_name = ?1 AND database = ?2",
+            params![table_name, database_name],
+            |row| row.get(0),
+        ).optional()
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to query checkpoint of table {}: {}", table_name, e)))
+    }
+
+    /// Checkpoints of the tables of a database left halfway
+    pub fn get_checkpoints(&self, database_name: &str) -> Result<Vec<TableCheckpoint>, TwoDbError> {
+        let select = "SELECT table_name, primary_key_column, last_primary_key, updated_at FROM table_checkpoints";
+        self.query_rows(select, "WHERE database = ?1 ORDER BY table_name", [database_name], |row| Ok(TableCheckpoint {
+            table_name: row.get(0)?,
+            primary_key_column: row.get(1)?,
+            last_primary_key: row.get(2)?,
+            updated_at: row.get(3)?,
+        }), "checkpoints")
+    }
+
+    /// Keep one checkpoint per table, the latest wins
+    pub fn save_checkpoint(
+        &self,
+        run_id: Option<i64>,
+        table_name: &str,
+        database_name: &str,
+        primary_key_column: &str,
+        last_primary_key: &str,
+    ) -> Result<(), TwoDbError> {
+        self.execute(
+            query_upsert_table_checkpoint(),
+            params![run_id, table_name, database_name, primary_key_column, last_primary_key],
+            || format!("save checkpoint of table {}", table_name),
+        )
+    }
+
+    pub fn delete_checkpoint(&self, table_name: &str, database_name: &str) -> Result<(), TwoDbError> {
+        self.execute(
+            "DELETE FROM table_checkpoints WHERE table_name = ?1 AND database = ?2",
+            params![table_name, database_name],
+            || format!("delete checkpoint of table {}", table_name),
+        )
+    }
+
+    pub fn insert_suppressed_trigger(
+        &self,
+        run_id: Option<i64>,
+        table_name: &str,
+        database_name: &str,
+        trigger_name: &str,
+        trigger_mode: &str,
+    ) -> Result<(), TwoDbError> {
+        self.execute(
+            "INSERT INTO suppressed_triggers (run_id, table_name, database, trigger_name, trigger_mode)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![run_id, table_name, database_name, trigger_name, trigger_mode],
+            || format!("record trigger {} of table {}", trigger_name, table_name),
+        )
+    }
+
+    /// Build a SchemaDifference from a row of `SELECT_SCHEMA_DIFFERENCES`
+    fn build_schema_difference(row: &Row<'_>) -> rusqlite::Result<SchemaDifference> {
+        Ok(SchemaDifference {
+            table_name: row.get(0)?,
+            object: SchemaObject::from_name(&row.get::<_, String>(1)?),
+            object_name: row.get(2)?,
+            kind: DifferenceKind::from_name(&row.get::<_, String>(3)?),
+            source_value: row.get(4)?,
+            target_value: row.get(5)?,
+        })
+    }
+
+    /// Differences of the last comparison of these databases, in the order they were found
+    pub fn get_schema_differences(&self, source_database_name: &str, target_database_name: &str) -> Result<Vec<SchemaDifference>, TwoDbError> {
+        self.query_rows(
+            SELECT_SCHEMA_DIFFERENCES,
+            "WHERE source_database = ?1 AND target_database = ?2 ORDER BY id",
+            [source_database_name, target_database_name],
+            Self::build_schema_difference,
+            "schema differences",
+        )
+    }
+
+    /// Replace the last comparison of these databases
+    pub fn save_schema_diff(&self, schema_diff: &SchemaDiff) -> Result<(), TwoDbError> {
+        let mut conn = self.connect()?;
+        Self::replace_schema_differences(&mut conn, schema_diff)
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to save schema differences: {}", e)))
+    }
+
+    /// Delete the differences of the databases of `schema_diff` and insert its own, in one transaction
+    fn replace_schema_differences(conn: &mut Connection, schema_diff: &SchemaDiff) -> rusqlite::Result<()> {
+        let transaction = conn.transaction()?;
+        transaction.execute(
+            "DELETE FROM schema_differences WHERE source_database = ?1 AND target_database = ?2",
+            params![schema_diff.source_database, schema_diff.target_database],
+        )?;
+        for difference in &schema_diff.differences {
+            transaction.execute(
+                "INSERT INTO schema_differences
+                (source_database, target_database, table_name, object_type, object_name, difference, source_value, target_value)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    schema_diff.source_database,
+                    schema_diff.target_database,
+                    difference.table_name,
+                    difference.object.name(),
+                    difference.object_name,
+                    difference.kind.name(),
+                    difference.source_value,
+                    difference.target_value,
+                ],
+            )?;
         }
+        transaction.commit()
+    }
+
+    /// Forget the comparisons of every pair of databases
+    pub fn delete_schema_differences(&self) -> Result<(), TwoDbError> {
+        self.execute("DELETE FROM schema_differences", params![], || String::from("delete schema differences"))
+    }
+
+    /// Forget every table, they are found again by the next update
+    pub fn delete_tables(&self) -> Result<(), TwoDbError> {
+        self.execute("DELETE FROM tables", params![], || String::from("delete tables"))
+    }
+
+    /// Forget the watermarks, the change columns are kept
+    pub fn clear_watermarks(&self) -> Result<(), TwoDbError> {
+        self.execute("UPDATE table_syncs SET watermark = '', synced_at = NULL", params![], || String::from("clear watermarks"))
+    }
+
+    /// Forget every run, with its checkpoints and suppressed triggers
+    pub fn delete_migration_runs(&self) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute_batch(
+            "DELETE FROM table_checkpoints;
+            DELETE FROM suppressed_triggers;
+            DELETE FROM migration_runs;",
+        ).map_err(|e| TwoDbError::Knowledge(format!("Failed to delete migration runs: {}", e)))
+    }
+
+    /// Get the tables matching a raw SQL condition, e.g. `WHERE is_exported = 0 ORDER BY id`
+    pub fn get_tables_with_condition(&self, condition: &str) -> Result<Vec<Table>, TwoDbError> {
+        self.query_tables(condition, [])
+    }
+
+    pub fn get_all_tables(&self) -> Result<Vec<Table>, TwoDbError> {
+        self.query_tables("", [])
+    }
+
+    pub fn get_tables_of_database(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+        self.query_tables("WHERE database = ?1", [database_name])
     }
-    
-    fn get_non_exported_tables(&self) -> Result<Vec<Table>, String> {
-        // In a real implementation, this would query SQLite for all non-exported tables
-        // and convert the results to Table entities
-        match self.connect() {
-            Ok(_) => {
-                // Simulate fetching non-exported tables from SQLite
-                let tables = vec![
-                    Table {
-                        id: 3,
-                        name: "non_exported_table1".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::SIMPLE,
-                        database: "database1".to_string(),
-                        export_order: 0,
-                        is_self_referencing: false,
-                        self_referencing_column: String::new(),
-                        row_count: 5,
-                        is_exported: false,
-                    },
-                    Table {
-                        id: 4,
-                        name: "non_exported_table2".to_string(),
-                        table_type: TableType::BaseTable,
-                        export_complexity_type: ExportComplexityType::COMPLEX,
-                        database: "database1".to_string(),
-                        export_order: 0,
-                        is_self_referencing: true,
-                        self_referencing_column: "parent_id".to_string(),
-                        row_count: 15,
-                        is_exported: false,
-                    },
-                ];
-                Ok(tables)
-            },
-            Err(e) => Err(format!("Failed to connect to database: {}", e)),
+
+    /// Get the tables of a database in export order, only those not exported yet if asked
+    pub fn get_tables_in_export_order(&self, database_name: &str, only_not_exported: bool) -> Result<Vec<Table>, TwoDbError> {
+        self.query_tables(
+            "WHERE database = ?1 AND (?2 = 0 OR is_exported = 0) ORDER BY export_order, id",
+            params![database_name, only_not_exported],
+        )
+    }
+
+    /// Check if the table of this database is already known
+    pub fn table_exists(&self, table: &Table) -> Result<bool, TwoDbError> {
+        let conn = self.connect()?;
+        conn.query_row(
+            "SELECT id FROM tables WHERE name = ?1 AND database = ?2",
+            params![table.name, table.database],
+            |row| row.get::<_, i64>(0),
+        ).optional()
+            .map(|id| id.is_some())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to query table {}: {}", table.name, e)))
+    }
+
+    pub fn insert_table(&self, table: &Table) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO tables (name,
+            table_type, export_complexity_type, database,
+            export_order,
+            is_self_referencing,
+            self_referencing_column,
+            row_count,
+            self_referencing_strategy
+            )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6,
+                ?7,
+                ?8,
+                ?9
+                )",
+            params![
+                table.name,
+                table.table_type.name(),
+                table.export_complexity_type.name(),
+                table.database,
+                table.export_order,
+                table.is_self_referencing,
+                table.self_referencing_column,
+                table.row_count,
+                table.self_referencing_strategy.name(),
+            ],
+        ).map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to insert table {}: {}", table.name, e)))
+    }
+
+    /// Save the row count of the table as it is in the struct
+    pub fn save_row_count(&self, table: &Table) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            query_update_row_count(),
+            params![
+                table.row_count,
+                table.name, // WHERE
+                table.database,
+            ],
+        ).map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to update row count of table {}: {}", table.name, e)))
+    }
+
+    pub fn update_self_referencing_strategy(&self, table: &Table) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "
+            UPDATE tables
+            SET self_referencing_strategy = ?1
+            WHERE name = ?2 AND database = ?3
+            ",
+            params![
+                table.self_referencing_strategy.name(),
+
+                table.name,
+                table.database,
+            ],
+        ).map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to update self-referencing strategy of table {}: {}", table.name, e)))
+    }
+
+    /// Save the export order of each table of the database
+    pub fn update_export_orders(&self, database_name: &str, export_orders: &[(String, i64)]) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        for (table_name, export_order) in export_orders {
+            conn.execute(
+                query_update_export_order(),
+                params![
+                    export_order,
+                    table_name, // WHERE
+                    database_name,
+                ],
+            ).map_err(|e| TwoDbError::Knowledge(format!("Failed to update export order of table {}: {}", table_name, e)))?;
         }
+        Ok(())
+    }
+}
+
+impl KnowledgeRepository for SqliteKnowledgeGateway {
+    fn get_table_knowledge(&self, database_name: &str, table_name: &str) -> Result<Table, TwoDbError> {
+        self.query_tables("WHERE name = ?1 AND database = ?2", [table_name, database_name])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| TwoDbError::Knowledge(format!("Table {} of database {} is not known yet", table_name, database_name)))
+    }
+
+    fn update_table_knowledge(&self, table: &Table) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "
+            UPDATE tables
+            SET
+
+            export_order = ?1,
+            row_count = ?2,
+            is_self_referencing = ?3,
+            self_referencing_column = ?4
+
+            WHERE name = ?5 AND database = ?6
+            ",
+            params![
+                table.export_order,
+                table.row_count,
+                table.is_self_referencing,
+                table.self_referencing_column,
+
+                // WHERE
+                table.name,
+                table.database,
+            ],
+        ).map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to update table {}: {}", table.name, e)))
+    }
+
+    fn update_row_count(&self, table: &mut Table) -> Result<(), TwoDbError> {
+        self.save_row_count(table)
+    }
+
+    fn update_self_referencing(&self, table: &mut Table, database_name: &str) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "
+            UPDATE tables
+            SET is_self_referencing = ?1, self_referencing_column = ?2
+            WHERE name = ?3 AND database = ?4
+            ",
+            params![
+                table.is_self_referencing,
+                table.self_referencing_column,
+
+                table.name,
+                database_name,
+            ],
+        ).map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to update self-referencing of table {}: {}", table.name, e)))
     }
-}
\ No newline at end of file
+
+    fn update_export_status(&self, table_name: &str, database_name: &str, is_exported: bool) -> Result<(), TwoDbError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "
+            UPDATE tables
+            SET is_exported = ?1
+            WHERE name = ?2 AND database = ?3
+            ",
+            params![is_exported, table_name, database_name],
+        ).map(|_| ())
+            .map_err(|e| TwoDbError::Knowledge(format!("Failed to update export status of table {}: {}", table_name, e)))
+    }
+
+    fn get_exported_tables(&self) -> Result<Vec<Table>, TwoDbError> {
+        self.query_tables("WHERE is_exported = 1 ORDER BY export_order, id", [])
+    }
+
+    fn get_non_exported_tables(&self) -> Result<Vec<Table>, TwoDbError> {
+        self.query_tables("WHERE is_exported = 0 ORDER BY export_order, id", [])
+    }
+
+    fn get_mapping_rules(&self, database_name: &str, table_name: &str) -> Result<Vec<MappingRule>, TwoDbError> {
+        self.query_mapping_rules("WHERE database = ?1 AND table_name = ?2 ORDER BY id", [database_name, table_name])
+    }
+}
@@ -10,6 +10,7 @@ pub struct TableKnowledgeViewModel {
     pub row_count: i64,
     pub is_self_referencing: bool,
     pub self_referencing_column: String,
+    pub self_referencing_strategy: String,
     pub is_exported: bool,
     pub export_order: i64,
     pub status: String,
@@ -37,6 +38,7 @@ impl KnowledgePresenter {
             row_count: table.row_count,
             is_self_referencing: table.is_self_referencing,
             self_referencing_column: table.self_referencing_column.clone(),
+            self_referencing_strategy: table.self_referencing_strategy.name().to_string(),
             is_exported: table.is_exported,
             export_order: table.export_order,
             status: if table.is_exported { "Exported".to_string() } else { "Not Exported".to_string() },
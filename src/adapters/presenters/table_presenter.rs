@@ -2,6 +2,7 @@
 
 use crate::domain::table::Table;
 use crate::domain::two_column::TwoColumn;
+use crate::domain::two_value::TwoValue;
 
 /// View model for a table
 pub struct TableViewModel {
@@ -13,6 +14,7 @@ pub struct TableViewModel {
     pub export_order: i64,
     pub is_self_referencing: bool,
     pub self_referencing_column: String,
+    pub self_referencing_strategy: String,
     pub row_count: i64,
     pub is_exported: bool,
     pub status: String,
@@ -44,6 +46,7 @@ impl TablePresenter {
             export_order: table.export_order,
             is_self_referencing: table.is_self_referencing,
             self_referencing_column: table.self_referencing_column.clone(),
+            self_referencing_strategy: table.self_referencing_strategy.name().to_string(),
             row_count: table.row_count,
             is_exported: table.is_exported,
             status: if table.is_exported { "Exported".to_string() } else { "Not Exported".to_string() },
@@ -69,14 +72,14 @@ impl TablePresenter {
     }
 
     /// Format a row for display in the UI
-    pub fn present_row(row: &[String]) -> RowViewModel {
+    pub fn present_row(row: &[TwoValue]) -> RowViewModel {
         RowViewModel {
-            values: row.to_vec(),
+            values: row.iter().map(|value| value.to_string()).collect(),
         }
     }
 
     /// Format a list of rows for display in the UI
-    pub fn present_rows(rows: &[Vec<String>]) -> Vec<RowViewModel> {
+    pub fn present_rows(rows: &[Vec<TwoValue>]) -> Vec<RowViewModel> {
         rows.iter().map(|row| Self::present_row(row)).collect()
     }
 }
\ No newline at end of file
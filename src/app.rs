@@ -1,6 +1,11 @@
 use egui::Align2;
 use egui_toast::{Toasts};
 use std::sync::{Arc, Mutex};
+use crate::domain::mapping_rule::MappingRule;
+use crate::domain::row_filter::RowFilter;
+use crate::domain::schema_diff::SchemaDiff;
+use crate::domain::table_sync::TableSync;
+use crate::domain::transform_rule::TransformRule;
 use crate::state::WindowsState;
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -22,6 +27,36 @@ pub struct TwoDBApp {
     pub is_busy: Arc<Mutex<bool>>, // for synchronize thread
 
     pub toast_text: Arc<Mutex<String>>,
+
+    #[serde(skip)]
+    pub toast_error: Arc<Mutex<String>>, // shown as an error toast
+
+    #[serde(skip)]
+    pub schema_diff: Arc<Mutex<Option<SchemaDiff>>>, // last comparison shown in the Schema Diff window
+
+    #[serde(skip)]
+    pub mapping_rules: Vec<MappingRule>, // shown in the Mapping Rules window
+
+    #[serde(skip)]
+    pub mapping_rule_form: MappingRule, // rule being added in the Mapping Rules window
+
+    #[serde(skip)]
+    pub transform_rules: Vec<TransformRule>, // shown in the Transform Rules window
+
+    #[serde(skip)]
+    pub transform_rule_form: TransformRule, // rule being added in the Transform Rules window
+
+    #[serde(skip)]
+    pub row_filters: Vec<RowFilter>, // shown in the Row Filters window
+
+    #[serde(skip)]
+    pub row_filter_form: RowFilter, // filter being added in the Row Filters window
+
+    #[serde(skip)]
+    pub table_syncs: Vec<TableSync>, // shown in the Watermarks window
+
+    #[serde(skip)]
+    pub table_sync_form: TableSync, // change column being set in the Watermarks window
     selected : Enum,
 }
 
@@ -35,11 +70,28 @@ impl Default for TwoDBApp {
                 window_move_one_table_open: false,
                 window_move_all_tables_open: false,
                 window_reset_open: false,
+                window_schema_diff_open: false,
+                window_apply_ddl_open: false,
+                apply_ddl_destructive: false,
+                window_mapping_open: false,
+                window_transform_open: false,
+                window_filter_open: false,
+                window_watermark_open: false,
             },
             table_name: "".to_owned(),
             is_busy_old: false,
             is_busy: Arc::new(Mutex::new(false)),
             toast_text: Arc::new(Mutex::new("".to_owned())),
+            toast_error: Arc::new(Mutex::new("".to_owned())),
+            schema_diff: Arc::new(Mutex::new(None)),
+            mapping_rules: Vec::new(),
+            mapping_rule_form: MappingRule::default(),
+            transform_rules: Vec::new(),
+            transform_rule_form: TransformRule::default(),
+            row_filters: Vec::new(),
+            row_filter_form: RowFilter::default(),
+            table_syncs: Vec::new(),
+            table_sync_form: TableSync::default(),
             selected: Enum::First,
         }
     }
@@ -63,6 +115,13 @@ impl TwoDBApp {
 
                 app.windows_state.window_move_one_table_open = false;
                 app.windows_state.window_move_all_tables_open = false;
+                app.windows_state.window_schema_diff_open = false;
+                app.windows_state.window_apply_ddl_open = false;
+                app.windows_state.apply_ddl_destructive = false;
+                app.windows_state.window_mapping_open = false;
+                app.windows_state.window_transform_open = false;
+                app.windows_state.window_filter_open = false;
+                app.windows_state.window_watermark_open = false;
 
                 app.toast_text.lock().unwrap().clear();
             }
@@ -108,10 +167,16 @@ impl eframe::App for TwoDBApp {
                         self.render_update_self_referencing_tables_button(ui);
                         self.render_clean_tables_button(ui);
                         self.render_get_empty_tables_button(ui);
+                        self.render_update_export_order_button(ui);
                     });
                     self.menu_btn_migrate_data_render(ctx, ui);
                     self.menu_btn_reset_render(ctx, ui);
                     self.menu_btn_fix_render(ctx, ui);
+                    self.menu_btn_schema_render(ctx, ui);
+                    self.menu_btn_mapping_render(ctx, ui);
+                    self.menu_btn_transform_render(ctx, ui);
+                    self.menu_btn_filter_render(ctx, ui);
+                    self.menu_btn_sync_render(ctx, ui);
                     ui.menu_button("Settings", |_| {});
 
                     if self.is_busy.lock().unwrap().clone() {
@@ -166,6 +231,19 @@ impl eframe::App for TwoDBApp {
             self.toast_text.lock().unwrap().clear();
         }
 
+        let toast_error = self.toast_error.lock().unwrap().clone();
+        if !toast_error.is_empty() {
+            toasts.add(egui_toast::Toast {
+                text: toast_error.into(),
+                kind: egui_toast::ToastKind::Error,
+                options: egui_toast::ToastOptions::default()
+                    .duration_in_seconds(10.0)
+                    .show_progress(true),
+                ..Default::default()
+            });
+            self.toast_error.lock().unwrap().clear();
+        }
+
         toasts.show(ctx);
     }
 
@@ -1,5 +1,5 @@
 use std::sync::{Arc, Mutex};
-use log::info;
+use log::{error, info};
 use crate::TwoDBApp;
 
 impl TwoDBApp {
@@ -8,4 +8,11 @@ impl TwoDBApp {
         *is_busy.lock().unwrap() = false;
         *toast_text.lock().unwrap() = text;
     }
-}
\ No newline at end of file
+
+    /// Stop the spinner and show `text` as an error toast
+    pub fn notify_error(text: String, is_busy: Arc<Mutex<bool>>, toast_error: Arc<Mutex<String>>) {
+        error!("{}", text);
+        *is_busy.lock().unwrap() = false;
+        *toast_error.lock().unwrap() = text;
+    }
+}
@@ -1,27 +1,32 @@
 /*! This file contains the KnowledgeRepository trait. */
 
+use crate::domain::mapping_rule::MappingRule;
 use crate::domain::table::Table;
+use crate::domain::two_db_error::TwoDbError;
 
 /// Repository trait for accessing and updating knowledge about tables
 pub trait KnowledgeRepository {
     /// Get knowledge about a table
-    fn get_table_knowledge(&self, database_name: &str, table_name: &str) -> Result<Table, String>;
+    fn get_table_knowledge(&self, database_name: &str, table_name: &str) -> Result<Table, TwoDbError>;
     
     /// Update knowledge about a table
-    fn update_table_knowledge(&self, table: &Table) -> Result<(), String>;
+    fn update_table_knowledge(&self, table: &Table) -> Result<(), TwoDbError>;
     
     /// Update knowledge about table row count
-    fn update_row_count(&self, table: &mut Table) -> Result<(), String>;
+    fn update_row_count(&self, table: &mut Table) -> Result<(), TwoDbError>;
     
     /// Update knowledge about table self-referencing status
-    fn update_self_referencing(&self, table: &mut Table, database_name: &str) -> Result<(), String>;
+    fn update_self_referencing(&self, table: &mut Table, database_name: &str) -> Result<(), TwoDbError>;
     
     /// Update knowledge about table export status
-    fn update_export_status(&self, table_name: &str, is_exported: bool) -> Result<(), String>;
+    fn update_export_status(&self, table_name: &str, database_name: &str, is_exported: bool) -> Result<(), TwoDbError>;
     
     /// Get all tables that have been exported
-    fn get_exported_tables(&self) -> Result<Vec<Table>, String>;
+    fn get_exported_tables(&self) -> Result<Vec<Table>, TwoDbError>;
     
     /// Get all tables that have not been exported
-    fn get_non_exported_tables(&self) -> Result<Vec<Table>, String>;
+    fn get_non_exported_tables(&self) -> Result<Vec<Table>, TwoDbError>;
+
+    /// Get the mapping rules of a table of the source database
+    fn get_mapping_rules(&self, database_name: &str, table_name: &str) -> Result<Vec<MappingRule>, TwoDbError>;
 }
\ No newline at end of file
@@ -2,39 +2,41 @@
 
 use crate::domain::table::Table;
 use crate::domain::two_column::TwoColumn;
+use crate::domain::two_value::TwoValue;
+use crate::domain::two_db_error::TwoDbError;
 
 /// Repository trait for accessing and manipulating tables
 pub trait TableRepository {
     /// Get a table by its name
-    fn get_table_by_name(&self, database_name: &str, table_name: &str) -> Result<Table, String>;
+    fn get_table_by_name(&self, database_name: &str, table_name: &str) -> Result<Table, TwoDbError>;
     
     /// Get all tables in a database
-    fn get_all_tables(&self, database_name: &str) -> Result<Vec<Table>, String>;
+    fn get_all_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError>;
     
     /// Get all empty tables in a database
-    fn get_empty_tables(&self, database_name: &str) -> Result<Vec<Table>, String>;
+    fn get_empty_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError>;
     
     /// Get all tables without foreign keys
-    fn get_clean_tables(&self, database_name: &str) -> Result<Vec<Table>, String>;
+    fn get_clean_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError>;
     
     /// Get all self-referencing tables
-    fn get_self_referencing_tables(&self, database_name: &str) -> Result<Vec<Table>, String>;
+    fn get_self_referencing_tables(&self, database_name: &str) -> Result<Vec<Table>, TwoDbError>;
     
     /// Update a table in the database
-    fn update_table(&self, table: &Table) -> Result<(), String>;
+    fn update_table(&self, table: &Table) -> Result<(), TwoDbError>;
     
     /// Insert a new table into the database
-    fn insert_table(&self, table: &Table) -> Result<(), String>;
+    fn insert_table(&self, table: &Table) -> Result<(), TwoDbError>;
     
     /// Check if a table exists
-    fn table_exists(&self, table: &Table) -> Result<bool, String>;
+    fn table_exists(&self, table: &Table) -> Result<bool, TwoDbError>;
     
     /// Get columns of a table
-    fn get_columns(&self, database_name: &str, table_name: &str) -> Result<Vec<TwoColumn>, String>;
+    fn get_columns(&self, database_name: &str, table_name: &str) -> Result<Vec<TwoColumn>, TwoDbError>;
     
     /// Get rows of a table
-    fn get_rows(&self, database_name: &str, table_name: &str) -> Result<Vec<Vec<String>>, String>;
+    fn get_rows(&self, database_name: &str, table_name: &str) -> Result<Vec<Vec<TwoValue>>, TwoDbError>;
     
-    /// Execute a query on a database
-    fn execute_query(&self, database_name: &str, query: &str) -> Result<(), String>;
+    /// Execute a query on a database, `params` are bound to `$1`, `$2`, ...
+    fn execute_query(&self, database_name: &str, query: &str, params: &[TwoValue]) -> Result<(), TwoDbError>;
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use std::rc::Rc;
 use log::info;
 use crate::application::repositories::table_repository::TableRepository;
 use crate::application::repositories::knowledge_repository::KnowledgeRepository;
+use crate::domain::two_db_error::TwoDbError;
 
 /// Service for fixing numeric data errors
 pub struct FixNumericDataService<T: TableRepository, K: KnowledgeRepository> {
@@ -21,7 +22,7 @@ impl<T: TableRepository, K: KnowledgeRepository> FixNumericDataService<T, K> {
     }
 
     /// Fix numeric data errors in a table
-    pub fn fix_numeric_data(&self, database_name: &str, table_name: &str) -> Result<(), String> {
+    pub fn fix_numeric_data(&self, database_name: &str, table_name: &str) -> Result<(), TwoDbError> {
         // Get columns of the table
         let columns = self.table_repository.get_columns(database_name, table_name)?;
         
@@ -43,7 +44,7 @@ impl<T: TableRepository, K: KnowledgeRepository> FixNumericDataService<T, K> {
             let query = self.build_fix_numeric_data_query(table_name, &column.name)?;
             
             // Execute query
-            self.table_repository.execute_query(database_name, &query)?;
+            self.table_repository.execute_query(database_name, &query, &[])?;
             
             info!("Fixed numeric data errors in column {} of table {}", column.name, table_name);
         }
@@ -52,7 +53,7 @@ impl<T: TableRepository, K: KnowledgeRepository> FixNumericDataService<T, K> {
     }
 
     /// Fix numeric data errors in all tables
-    pub fn fix_all_numeric_data(&self, database_name: &str) -> Result<(), String> {
+    pub fn fix_all_numeric_data(&self, database_name: &str) -> Result<(), TwoDbError> {
         // Get all tables
         let tables = self.table_repository.get_all_tables(database_name)?;
         
@@ -75,7 +76,7 @@ impl<T: TableRepository, K: KnowledgeRepository> FixNumericDataService<T, K> {
     }
 
     // Helper method to build a query to fix numeric data errors
-    fn build_fix_numeric_data_query(&self, table_name: &str, column_name: &str) -> Result<String, String> {
+    fn build_fix_numeric_data_query(&self, table_name: &str, column_name: &str) -> Result<String, TwoDbError> {
         // This is a simplified implementation
         // In a real application, you would need to build a more complex query based on the specific numeric data errors
         
@@ -1,5 +1,4 @@
 /*! This file contains the use case services for the application. */
 
-pub mod move_table_data;
 pub mod update_empty_tables_knowledge;
 pub mod fix_numeric_data;
\ No newline at end of file
@@ -4,6 +4,7 @@ use std::rc::Rc;
 use log::info;
 use crate::application::repositories::table_repository::TableRepository;
 use crate::application::repositories::knowledge_repository::KnowledgeRepository;
+use crate::domain::two_db_error::TwoDbError;
 
 /// Service for updating knowledge about empty tables
 pub struct UpdateEmptyTablesKnowledgeService<T: TableRepository, K: KnowledgeRepository> {
@@ -21,7 +22,7 @@ impl<T: TableRepository, K: KnowledgeRepository> UpdateEmptyTablesKnowledgeServi
     }
 
     /// Update knowledge about empty tables in a database
-    pub fn update_empty_tables_knowledge(&self, database_name: &str) -> Result<(), String> {
+    pub fn update_empty_tables_knowledge(&self, database_name: &str) -> Result<(), TwoDbError> {
         // Get all empty tables from the database
         let empty_tables = self.table_repository.get_empty_tables(database_name)?;
         
@@ -43,7 +44,7 @@ impl<T: TableRepository, K: KnowledgeRepository> UpdateEmptyTablesKnowledgeServi
     }
 
     /// Update knowledge about all tables in a database
-    pub fn update_all_tables_knowledge(&self, database_name: &str) -> Result<(), String> {
+    pub fn update_all_tables_knowledge(&self, database_name: &str) -> Result<(), TwoDbError> {
         // Get all tables from the database
         let all_tables = self.table_repository.get_all_tables(database_name)?;
         
@@ -71,7 +72,7 @@ impl<T: TableRepository, K: KnowledgeRepository> UpdateEmptyTablesKnowledgeServi
     }
 
     /// Update knowledge about clean tables (tables without foreign keys)
-    pub fn update_clean_tables_knowledge(&self, database_name: &str) -> Result<(), String> {
+    pub fn update_clean_tables_knowledge(&self, database_name: &str) -> Result<(), TwoDbError> {
         // Get all clean tables from the database
         let clean_tables = self.table_repository.get_clean_tables(database_name)?;
         
@@ -96,7 +97,7 @@ impl<T: TableRepository, K: KnowledgeRepository> UpdateEmptyTablesKnowledgeServi
     }
 
     /// Update knowledge about self-referencing tables
-    pub fn update_self_referencing_tables_knowledge(&self, database_name: &str) -> Result<(), String> {
+    pub fn update_self_referencing_tables_knowledge(&self, database_name: &str) -> Result<(), TwoDbError> {
         // Get all self-referencing tables from the database
         let self_referencing_tables = self.table_repository.get_self_referencing_tables(database_name)?;
         
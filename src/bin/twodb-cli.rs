@@ -0,0 +1,16 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::process::ExitCode;
+
+// Headless commands, the GUI is not needed
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> ExitCode {
+    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=info`), the report goes to stdout.
+
+    twodb::cli::run()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() -> ExitCode {
+    ExitCode::FAILURE
+}
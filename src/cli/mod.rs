@@ -0,0 +1,561 @@
+/*! This file contains the commands of `twodb-cli`, the menus of the GUI without the GUI.
+
+Every command prints one JSON object on stdout, logs go to stderr (`RUST_LOG=info`).
+The config file and the knowledge store are the GUI's, both in the working directory.
+The options of its `[load]` section may be given to any command, e.g. `--trigger-mode replica`.
+
+Exit codes:
+- 0: done
+- 1: some tables were not moved, see `failures`
+- 2: invalid arguments
+- 3: configuration error
+- 4: a database cannot be reached or its catalog cannot be read
+- 5: the knowledge store cannot be read or written
+- 6: the data of a table cannot be moved
+- 7: the DDL script failed on the target, nothing was changed
+- 8: a file cannot be written
+*/
+
+mod report;
+
+use std::process::ExitCode;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use crate::core::action::copy::CopyFormat;
+use crate::core::action::create_table::CreateTableMode;
+use crate::core::action::fix::fix_numeric;
+use crate::core::action::trigger::TriggerMode;
+use crate::core::action::r#move::{get_tables_to_move, get_tables_to_sync, move_all_tables, move_one_table, sync_all_tables, sync_one_table};
+use crate::core::action::update::{update_all_tables, update_clean_tables, update_empty_tables, update_table_self_references};
+use crate::core::config::{set_load_overrides, ConnectionConfig, LoadOverrides};
+use crate::core::dependency_graph::update_export_order;
+use crate::core::get_knowledge::get_tables_of_database;
+use crate::core::mapping::{add_mapping_rule, delete_mapping_rule, get_mapping_rules};
+use crate::core::migration_run::{get_checkpoints, get_last_migration_run};
+use crate::core::reset_knowledge::reset_database;
+use crate::core::row_filter::{add_row_filter, delete_row_filter, get_row_filters};
+use crate::core::schema_ddl::{apply_ddl_script, generate_ddl_script, write_ddl_script};
+use crate::core::schema_diff::{compare_schemas, get_schema_diff};
+use crate::core::table_sync::{delete_table_sync, get_table_syncs, set_change_column};
+use crate::core::transform::{add_transform_rule, delete_transform_rule, get_transform_rules};
+use crate::domain::mapping_rule::{MappingRule, MappingRuleKind};
+use crate::domain::row_filter::RowFilter;
+use crate::domain::table_sync::TableSync;
+use crate::domain::transform_rule::{TransformKind, TransformRule};
+use crate::domain::two_db_error::TwoDbError;
+use report::{
+    CommandReport, DdlReport, ErrorReport, FilterReport, MappingReport, MoveReport, Output, PlanReport, StatusReport, SyncReport,
+    TransformReport, WatermarkReport,
+};
+
+#[derive(Parser)]
+#[command(name = "twodb-cli", version, about = "Move the data of a PostgreSQL database to another one")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    #[command(flatten)]
+    load: LoadArgs,
+}
+
+/// Options of the `[load]` section of the config file, they win over it
+#[derive(Args)]
+struct LoadArgs {
+    /// How triggers of a target table are kept quiet while it is loaded: keep, replica or disable_user
+    #[arg(long, global = true)]
+    trigger_mode: Option<String>,
+    /// Defer the constraints of the target in every transaction loading a table
+    #[arg(long, global = true)]
+    defer_constraints: bool,
+    /// Format of the rows copied: text, or binary if the column types are identical on both sides
+    #[arg(long, global = true)]
+    copy_format: Option<String>,
+    /// What a move does with a table missing in the target: skip, table, or full with its keys and indexes
+    #[arg(long, global = true)]
+    create_missing_tables: Option<String>,
+    /// Source rows fetched per round trip
+    #[arg(long, global = true)]
+    batch_size: Option<i32>,
+}
+
+impl LoadArgs {
+    /// Override the load settings of the config file, an unknown value is a configuration error
+    fn apply(&self) -> Result<(), TwoDbError> {
+        set_load_overrides(LoadOverrides {
+            trigger_mode: self.trigger_mode.as_deref().map(TriggerMode::from_name).transpose()?,
+            defer_constraints: self.defer_constraints.then_some(true),
+            copy_format: self.copy_format.as_deref().map(CopyFormat::from_name).transpose()?,
+            create_missing_tables: self.create_missing_tables.as_deref().map(CreateTableMode::from_name).transpose()?,
+            batch_size: self.batch_size,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Refresh the knowledge of the tables of the source and target databases
+    Update {
+        #[arg(value_enum)]
+        kind: UpdateKind,
+    },
+    /// Move the data of one table, or of every table not moved yet
+    Move(MoveArgs),
+    /// Copy the rows inserted or changed since the move, of one table or of every table
+    Sync(SyncArgs),
+    /// Fix data moved with a wrong value
+    Fix {
+        #[arg(value_enum)]
+        kind: FixKind,
+    },
+    /// Delete the knowledge store
+    Reset {
+        /// Confirm, there is no prompt
+        #[arg(long, required = true)]
+        yes: bool,
+    },
+    /// Update the export order and list the tables left to move, parents first
+    Plan,
+    /// Show the last migration run and the tables left to move
+    Status,
+    /// Compare the schemas of the source and target databases
+    Diff {
+        /// Show the last comparison instead of reading the catalogs again
+        #[arg(long)]
+        stored: bool,
+    },
+    /// Write the DDL script bringing the target schema in line with the source
+    Ddl {
+        /// Path of the `.sql` file
+        #[arg(long, default_value = "schema_migration.sql")]
+        output: String,
+        /// Also run the script on the target, in one transaction
+        #[arg(long)]
+        apply: bool,
+        /// Also drop the unique constraints, unique indexes and foreign keys of the target only,
+        /// they are written commented out otherwise
+        #[arg(long)]
+        destructive: bool,
+    },
+    /// List, add or remove the mapping rules of the tables of the source database
+    Mapping {
+        #[command(subcommand)]
+        action: MappingAction,
+    },
+    /// List, add or remove the transform rules of the columns of the source database
+    Transform {
+        #[command(subcommand)]
+        action: TransformAction,
+    },
+    /// List, add or remove the row filters of the tables of the source database
+    Filter {
+        #[command(subcommand)]
+        action: FilterAction,
+    },
+    /// List, set or remove the change columns the tables are synced by
+    Watermark {
+        #[command(subcommand)]
+        action: WatermarkAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MappingAction {
+    /// List the rules
+    List,
+    /// Add a rule, then list the rules
+    Add(MappingRuleArgs),
+    /// Remove a rule by its id, then list the rules
+    Remove {
+        id: i64,
+    },
+}
+
+#[derive(Args)]
+struct MappingRuleArgs {
+    /// Source table
+    #[arg(long)]
+    table: String,
+    #[arg(long, value_enum)]
+    kind: MappingKind,
+    /// Source column, for `rename-column` and `exclude`
+    #[arg(long, default_value = "")]
+    column: String,
+    /// Target table for `rename-table`, target column otherwise
+    #[arg(long, default_value = "")]
+    target: String,
+    /// Value for `constant`, SQL expression for `default`
+    #[arg(long, default_value = "")]
+    value: String,
+}
+
+#[derive(Subcommand)]
+enum TransformAction {
+    /// List the rules
+    List,
+    /// Add a rule, then list the rules
+    Add(TransformRuleArgs),
+    /// Remove a rule by its id, then list the rules
+    Remove {
+        id: i64,
+    },
+}
+
+#[derive(Args)]
+struct TransformRuleArgs {
+    /// Source table
+    #[arg(long)]
+    table: String,
+    /// Source column
+    #[arg(long)]
+    column: String,
+    #[arg(long, value_enum)]
+    kind: TransformArgKind,
+    /// Category for `fake`, value for `fixed`, regular expression for `replace`
+    #[arg(long, default_value = "")]
+    value: String,
+    /// Replacement for `replace`, `$1` is the first group of the match
+    #[arg(long, default_value = "")]
+    replacement: String,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct MoveArgs {
+    /// Name of the table to move
+    #[arg(long)]
+    table: Option<String>,
+    /// Move every table not moved yet, in export order
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum UpdateKind {
+    /// Every base table
+    Tables,
+    /// Tables without foreign keys
+    Clean,
+    /// Tables without rows
+    Empty,
+    /// Tables referencing themselves
+    SelfRef,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MappingKind {
+    /// Move the rows into another target table
+    RenameTable,
+    /// Move the values of a column into another target column
+    RenameColumn,
+    /// Fill a target column with the same value in every row
+    Constant,
+    /// Fill a target column with a SQL expression evaluated by the target
+    Default,
+    /// Do not move a source column
+    Exclude,
+}
+
+impl From<MappingKind> for MappingRuleKind {
+    fn from(kind: MappingKind) -> Self {
+        match kind {
+            MappingKind::RenameTable => MappingRuleKind::RenameTable,
+            MappingKind::RenameColumn => MappingRuleKind::RenameColumn,
+            MappingKind::Constant => MappingRuleKind::Constant,
+            MappingKind::Default => MappingRuleKind::Default,
+            MappingKind::Exclude => MappingRuleKind::Exclude,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum FilterAction {
+    /// List the filters
+    List,
+    /// Add a filter, then list the filters
+    Add(RowFilterArgs),
+    /// Remove a filter by its id, then list the filters
+    Remove {
+        id: i64,
+    },
+}
+
+#[derive(Args)]
+struct RowFilterArgs {
+    /// Source table
+    #[arg(long)]
+    table: String,
+    /// SQL condition on the columns of the table, e.g. "created_at > now() - interval '90 days'"
+    #[arg(long = "where")]
+    condition: String,
+    /// Start a subset from the matching rows: only they and the rows they reference are moved
+    #[arg(long)]
+    seed: bool,
+}
+
+#[derive(Subcommand)]
+enum WatermarkAction {
+    /// List the tables with a change column or synced before
+    List,
+    /// Sync a table by watermark on a change column, then list the tables
+    Set {
+        /// Source table
+        #[arg(long)]
+        table: String,
+        /// Source column set to a higher value whenever a row is inserted or changed, e.g. `updated_at`
+        #[arg(long)]
+        column: String,
+    },
+    /// Remove a change column and its watermark by id, the table is synced by primary key, then list the tables
+    Remove {
+        id: i64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TransformArgKind {
+    /// SHA-256 of the value, in hex
+    Hash,
+    /// A made up value: name, first_name, last_name, email, phone or text
+    Fake,
+    /// NULL in every row
+    Null,
+    /// The same value in every row
+    Fixed,
+    /// Replace every match of a regular expression
+    Replace,
+    /// A keyed token of the same type, foreign keys still join
+    Tokenize,
+}
+
+impl From<TransformArgKind> for TransformKind {
+    fn from(kind: TransformArgKind) -> Self {
+        match kind {
+            TransformArgKind::Hash => TransformKind::Hash,
+            TransformArgKind::Fake => TransformKind::Fake,
+            TransformArgKind::Null => TransformKind::Null,
+            TransformArgKind::Fixed => TransformKind::Fixed,
+            TransformArgKind::Replace => TransformKind::Replace,
+            TransformArgKind::Tokenize => TransformKind::Tokenize,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FixKind {
+    /// Numeric values moved with a wrong scale
+    Numeric,
+}
+
+impl Command {
+    fn name(&self) -> &str {
+        match self {
+            Command::Update { .. } => "update",
+            Command::Move(_) => "move",
+            Command::Sync(_) => "sync",
+            Command::Fix { .. } => "fix",
+            Command::Reset { .. } => "reset",
+            Command::Plan => "plan",
+            Command::Status => "status",
+            Command::Diff { .. } => "diff",
+            Command::Ddl { .. } => "ddl",
+            Command::Mapping { .. } => "mapping",
+            Command::Transform { .. } => "transform",
+            Command::Filter { .. } => "filter",
+            Command::Watermark { .. } => "watermark",
+        }
+    }
+
+    fn execute(&self) -> Result<CommandReport, TwoDbError> {
+        match self {
+            Command::Update { kind } => update(*kind),
+            Command::Move(args) => r#move(args),
+            Command::Sync(args) => sync(args),
+            Command::Fix { kind: FixKind::Numeric } => fix_numeric().map(|_| CommandReport::Done),
+            Command::Reset { .. } => reset_database().map(|_| CommandReport::Done),
+            Command::Plan => plan(),
+            Command::Status => status(),
+            Command::Diff { stored } => diff(*stored),
+            Command::Ddl { output, apply, destructive } => ddl(output, *apply, *destructive),
+            Command::Mapping { action } => mapping(action),
+            Command::Transform { action } => transform(action),
+            Command::Filter { action } => filter(action),
+            Command::Watermark { action } => watermark(action),
+        }
+    }
+}
+
+/// Parse the arguments, run the command and print its report
+pub fn run() -> ExitCode {
+    let cli = Cli::parse();
+    let command = cli.command.name();
+
+    let output = match cli.load.apply().and_then(|_| cli.command.execute()) {
+        Ok(report) => Output::report(command, report),
+        Err(err) => Output::error(command, ErrorReport::from(&err)),
+    };
+    match serde_json::to_string_pretty(&output) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!("Cannot write the report: {}", err),
+    }
+    ExitCode::from(output.exit_code())
+}
+
+fn update(kind: UpdateKind) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let update_tables = match kind {
+        UpdateKind::Tables => update_all_tables,
+        UpdateKind::Clean => update_clean_tables,
+        UpdateKind::Empty => update_empty_tables,
+        UpdateKind::SelfRef => update_table_self_references,
+    };
+    update_tables(&config.source)?;
+    update_tables(&config.target)?;
+    Ok(CommandReport::Update { databases: vec![config.source, config.target] })
+}
+
+fn r#move(args: &MoveArgs) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let (tables, failures) = match &args.table {
+        Some(table_name) => {
+            move_one_table(table_name.clone())?;
+            (vec![table_name.clone()], Vec::new())
+        }
+        None => {
+            let tables = get_tables_to_move(&config.source)?.into_iter().map(|t| t.name).collect();
+            (tables, move_all_tables(&config.source, &config.target)?)
+        }
+    };
+    Ok(CommandReport::Move(MoveReport::new(config.source, config.target, tables, failures)))
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct SyncArgs {
+    /// Name of the table to sync
+    #[arg(long)]
+    table: Option<String>,
+    /// Sync every table, in export order
+    #[arg(long)]
+    all: bool,
+}
+
+fn sync(args: &SyncArgs) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let (tables, failures) = match &args.table {
+        Some(table_name) => {
+            sync_one_table(table_name.clone())?;
+            (vec![table_name.clone()], Vec::new())
+        }
+        None => {
+            let failures = sync_all_tables(&config.source)?;
+            let tables = get_tables_to_sync(&config.source)?.into_iter().map(|t| t.name).collect();
+            (tables, failures)
+        }
+    };
+    Ok(CommandReport::Sync(SyncReport::new(config.source, config.target, tables, failures)))
+}
+
+fn plan() -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let cycles = update_export_order(&config.source)?;
+    let tables = get_tables_to_move(&config.source)?;
+    Ok(CommandReport::Plan(PlanReport::new(config.source, &tables, cycles)))
+}
+
+fn status() -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let migration_run = get_last_migration_run(&config.source, &config.target)?;
+    let tables = get_tables_of_database(&config.source)?;
+    let checkpoints = get_checkpoints(&config.source)?;
+    Ok(CommandReport::Status(StatusReport::new(config.source, config.target, migration_run, &tables, &checkpoints)))
+}
+
+fn diff(stored: bool) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let schema_diff = if stored {
+        get_schema_diff(&config.source, &config.target)?
+    } else {
+        compare_schemas(&config.source, &config.target)?
+    };
+    Ok(CommandReport::Diff(schema_diff))
+}
+
+fn ddl(output: &str, apply: bool, destructive: bool) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    let script = generate_ddl_script(&config.source, &config.target, destructive)?;
+    write_ddl_script(&script, output)?;
+    if apply {
+        apply_ddl_script(&script)?;
+    }
+    Ok(CommandReport::Ddl(DdlReport::new(script, output.to_string(), apply)))
+}
+
+fn mapping(action: &MappingAction) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    match action {
+        MappingAction::List => {}
+        MappingAction::Add(args) => add_mapping_rule(&MappingRule {
+            database: config.source.clone(),
+            table_name: args.table.clone(),
+            kind: args.kind.into(),
+            column_name: args.column.clone(),
+            target_name: args.target.clone(),
+            value: args.value.clone(),
+            ..Default::default()
+        })?,
+        MappingAction::Remove { id } => delete_mapping_rule(*id)?,
+    }
+    let rules = get_mapping_rules(&config.source)?;
+    Ok(CommandReport::Mapping(MappingReport::new(config.source, &rules)))
+}
+
+fn transform(action: &TransformAction) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    match action {
+        TransformAction::List => {}
+        TransformAction::Add(args) => add_transform_rule(&TransformRule {
+            database: config.source.clone(),
+            table_name: args.table.clone(),
+            column_name: args.column.clone(),
+            kind: args.kind.into(),
+            value: args.value.clone(),
+            replacement: args.replacement.clone(),
+            ..Default::default()
+        })?,
+        TransformAction::Remove { id } => delete_transform_rule(*id)?,
+    }
+    let rules = get_transform_rules(&config.source)?;
+    Ok(CommandReport::Transform(TransformReport::new(config.source, &rules)))
+}
+
+fn filter(action: &FilterAction) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    match action {
+        FilterAction::List => {}
+        FilterAction::Add(args) => add_row_filter(&RowFilter {
+            database: config.source.clone(),
+            table_name: args.table.clone(),
+            condition: args.condition.clone(),
+            is_seed: args.seed,
+            ..Default::default()
+        })?,
+        FilterAction::Remove { id } => delete_row_filter(*id)?,
+    }
+    let filters = get_row_filters(&config.source)?;
+    Ok(CommandReport::Filter(FilterReport::new(config.source, &filters)))
+}
+
+fn watermark(action: &WatermarkAction) -> Result<CommandReport, TwoDbError> {
+    let config = ConnectionConfig::load()?;
+    match action {
+        WatermarkAction::List => {}
+        WatermarkAction::Set { table, column } => set_change_column(&TableSync {
+            database: config.source.clone(),
+            table_name: table.clone(),
+            change_column: column.clone(),
+            ..Default::default()
+        })?,
+        WatermarkAction::Remove { id } => delete_table_sync(*id)?,
+    }
+    let syncs = get_table_syncs(&config.source)?;
+    Ok(CommandReport::Watermark(WatermarkReport::new(config.source, &syncs)))
+}
@@ -0,0 +1,404 @@
+/*! This file contains the JSON reports printed by `twodb-cli`. */
+
+use serde::Serialize;
+use crate::domain::ddl_script::DdlScript;
+use crate::domain::mapping_rule::MappingRule;
+use crate::domain::migration_run::{MigrationRun, TableCheckpoint};
+use crate::domain::row_filter::RowFilter;
+use crate::domain::schema_diff::SchemaDiff;
+use crate::domain::table::Table;
+use crate::domain::table_sync::TableSync;
+use crate::domain::transform_rule::TransformRule;
+use crate::domain::two_db_error::TwoDbError;
+
+/// What is printed for every command
+#[derive(Serialize)]
+pub struct Output<'a> {
+    pub command: &'a str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<CommandReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+}
+
+impl<'a> Output<'a> {
+    pub fn report(command: &'a str, report: CommandReport) -> Self {
+        Output {
+            command,
+            ok: report.is_complete(),
+            result: Some(report),
+            error: None,
+        }
+    }
+
+    pub fn error(command: &'a str, error: ErrorReport) -> Self {
+        Output {
+            command,
+            ok: false,
+            result: None,
+            error: Some(error),
+        }
+    }
+
+    /// See the exit codes in the `cli` module
+    pub fn exit_code(&self) -> u8 {
+        match (&self.error, self.ok) {
+            (Some(error), _) => error.exit_code,
+            (None, true) => 0,
+            (None, false) => 1,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum CommandReport {
+    Done,
+    Update { databases: Vec<String> },
+    Move(MoveReport),
+    Sync(SyncReport),
+    Plan(PlanReport),
+    Status(StatusReport),
+    Diff(SchemaDiff),
+    Ddl(DdlReport),
+    Mapping(MappingReport),
+    Transform(TransformReport),
+    Filter(FilterReport),
+    Watermark(WatermarkReport),
+}
+
+impl CommandReport {
+    /// False when some tables were not moved or synced
+    fn is_complete(&self) -> bool {
+        match self {
+            CommandReport::Move(report) => report.failures.is_empty(),
+            CommandReport::Sync(report) => report.failures.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ErrorReport {
+    /// `configuration`, `connection`, `catalog`, `knowledge`, `transfer`, `constraint_violation`, `schema_change` or `export`
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    pub message: String,
+    #[serde(skip)]
+    exit_code: u8,
+}
+
+impl From<&TwoDbError> for ErrorReport {
+    fn from(err: &TwoDbError) -> Self {
+        let (kind, table, exit_code) = match err {
+            TwoDbError::Configuration(_) => ("configuration", None, 3),
+            TwoDbError::Connection { .. } => ("connection", None, 4),
+            TwoDbError::Catalog { .. } => ("catalog", None, 4),
+            TwoDbError::Knowledge(_) => ("knowledge", None, 5),
+            TwoDbError::Transfer { table, .. } => ("transfer", Some(table.clone()), 6),
+            TwoDbError::ConstraintViolation { table, .. } => ("constraint_violation", Some(table.clone()), 6),
+            TwoDbError::SchemaChange { .. } => ("schema_change", None, 7),
+            TwoDbError::Export { .. } => ("export", None, 8),
+        };
+        ErrorReport {
+            kind,
+            table,
+            message: err.to_string(),
+            exit_code,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MoveReport {
+    pub source: String,
+    pub target: String,
+    pub moved: Vec<String>,
+    pub failures: Vec<ErrorReport>,
+}
+
+impl MoveReport {
+    /// `tables` were attempted, the ones in `failures` were not moved
+    pub fn new(source: String, target: String, tables: Vec<String>, failures: Vec<(String, TwoDbError)>) -> Self {
+        let moved = tables.into_iter()
+            .filter(|table| !failures.iter().any(|(failed, _)| failed == table))
+            .collect();
+        let failures = failures.iter()
+            .map(|(table, err)| ErrorReport { table: Some(table.clone()), ..ErrorReport::from(err) })
+            .collect();
+        MoveReport { source, target, moved, failures }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SyncReport {
+    pub source: String,
+    pub target: String,
+    pub synced: Vec<String>,
+    pub failures: Vec<ErrorReport>,
+}
+
+impl SyncReport {
+    /// `tables` were attempted, the ones in `failures` were not synced
+    pub fn new(source: String, target: String, tables: Vec<String>, failures: Vec<(String, TwoDbError)>) -> Self {
+        let MoveReport { source, target, moved, failures } = MoveReport::new(source, target, tables, failures);
+        SyncReport { source, target, synced: moved, failures }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PlannedTable {
+    pub name: String,
+    pub export_order: i64,
+    pub row_count: i64,
+    pub self_referencing_strategy: String,
+}
+
+#[derive(Serialize)]
+pub struct PlanReport {
+    pub source: String,
+    /// Tables left to move, in the order they will be moved
+    pub tables: Vec<PlannedTable>,
+    /// Foreign-key cycles, their tables may need their constraints deferred
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl PlanReport {
+    pub fn new(source: String, tables: &[Table], cycles: Vec<Vec<String>>) -> Self {
+        let tables = tables.iter().map(|table| PlannedTable {
+            name: table.name.clone(),
+            export_order: table.export_order,
+            row_count: table.row_count,
+            self_referencing_strategy: table.self_referencing_strategy.name().to_string(),
+        }).collect();
+        PlanReport { source, tables, cycles }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MigrationRunReport {
+    pub id: i64,
+    pub status: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CheckpointReport {
+    pub table: String,
+    pub primary_key_column: String,
+    pub last_primary_key: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct StatusReport {
+    pub source: String,
+    pub target: String,
+    /// Last run between the two databases, none before the first `move --all`
+    pub migration_run: Option<MigrationRunReport>,
+    pub tables_exported: usize,
+    /// Tables left to move, in export order
+    pub tables_pending: Vec<String>,
+    /// Tables left halfway, they resume from their last primary key
+    pub checkpoints: Vec<CheckpointReport>,
+}
+
+impl StatusReport {
+    pub fn new(
+        source: String,
+        target: String,
+        migration_run: Option<MigrationRun>,
+        tables: &[Table],
+        checkpoints: &[TableCheckpoint],
+    ) -> Self {
+        let mut pending = tables.iter().filter(|table| !table.is_exported).collect::<Vec<_>>();
+        pending.sort_by_key(|table| (table.export_order, table.id));
+
+        StatusReport {
+            source,
+            target,
+            migration_run: migration_run.map(|run| MigrationRunReport {
+                id: run.id,
+                status: run.status.name().to_string(),
+                started_at: run.started_at,
+                finished_at: run.finished_at,
+            }),
+            tables_exported: tables.iter().filter(|table| table.is_exported).count(),
+            tables_pending: pending.iter().map(|table| table.name.clone()).collect(),
+            checkpoints: checkpoints.iter().map(|checkpoint| CheckpointReport {
+                table: checkpoint.table_name.clone(),
+                primary_key_column: checkpoint.primary_key_column.clone(),
+                last_primary_key: checkpoint.last_primary_key.clone(),
+                updated_at: checkpoint.updated_at.clone(),
+            }).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DdlReport {
+    pub source: String,
+    pub target: String,
+    /// The `.sql` file written
+    pub path: String,
+    /// Whether the script was run on the target
+    pub applied: bool,
+    pub tables: Vec<String>,
+    /// Statements of the script, run if `applied`
+    pub statements: Vec<String>,
+    /// Destructive statements written commented out, never run
+    pub left_out: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+impl DdlReport {
+    pub fn new(script: DdlScript, path: String, applied: bool) -> Self {
+        DdlReport {
+            tables: script.table_names().into_iter().map(String::from).collect(),
+            statements: script.statements_to_run().map(|statement| statement.sql.clone()).collect(),
+            left_out: script.statements_left_out().map(|statement| statement.sql.clone()).collect(),
+            source: script.source_database,
+            target: script.target_database,
+            path,
+            applied,
+            notes: script.notes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MappingReport {
+    pub source: String,
+    pub rules: Vec<MappingRuleReport>,
+}
+
+#[derive(Serialize)]
+pub struct MappingRuleReport {
+    pub id: i64,
+    pub table: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub column: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub target: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub value: String,
+}
+
+impl MappingReport {
+    pub fn new(source: String, rules: &[MappingRule]) -> Self {
+        MappingReport {
+            source,
+            rules: rules.iter().map(|rule| MappingRuleReport {
+                id: rule.id,
+                table: rule.table_name.clone(),
+                kind: rule.kind.name().to_string(),
+                column: rule.column_name.clone(),
+                target: rule.target_name.clone(),
+                value: rule.value.clone(),
+            }).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TransformReport {
+    pub source: String,
+    pub rules: Vec<TransformRuleReport>,
+}
+
+#[derive(Serialize)]
+pub struct TransformRuleReport {
+    pub id: i64,
+    pub table: String,
+    pub column: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub value: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub replacement: String,
+}
+
+impl TransformReport {
+    pub fn new(source: String, rules: &[TransformRule]) -> Self {
+        TransformReport {
+            source,
+            rules: rules.iter().map(|rule| TransformRuleReport {
+                id: rule.id,
+                table: rule.table_name.clone(),
+                column: rule.column_name.clone(),
+                kind: rule.kind.name().to_string(),
+                value: rule.value.clone(),
+                replacement: rule.replacement.clone(),
+            }).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FilterReport {
+    pub source: String,
+    pub filters: Vec<RowFilterReport>,
+}
+
+#[derive(Serialize)]
+pub struct RowFilterReport {
+    pub id: i64,
+    pub table: String,
+    /// `FILTER` or `SEED`
+    pub kind: String,
+    pub condition: String,
+}
+
+impl FilterReport {
+    pub fn new(source: String, filters: &[RowFilter]) -> Self {
+        FilterReport {
+            source,
+            filters: filters.iter().map(|filter| RowFilterReport {
+                id: filter.id,
+                table: filter.table_name.clone(),
+                kind: filter.kind_name().to_string(),
+                condition: filter.condition.clone(),
+            }).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct WatermarkReport {
+    pub source: String,
+    pub tables: Vec<TableSyncReport>,
+}
+
+#[derive(Serialize)]
+pub struct TableSyncReport {
+    pub id: i64,
+    pub table: String,
+    /// `WATERMARK` or `PRIMARY KEY`
+    pub mode: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub change_column: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub watermark: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synced_at: Option<String>,
+}
+
+impl WatermarkReport {
+    pub fn new(source: String, syncs: &[TableSync]) -> Self {
+        WatermarkReport {
+            source,
+            tables: syncs.iter().map(|sync| TableSyncReport {
+                id: sync.id,
+                table: sync.table_name.clone(),
+                mode: sync.mode_name().to_string(),
+                change_column: sync.change_column.clone(),
+                watermark: sync.watermark.clone(),
+                synced_at: sync.synced_at.clone(),
+            }).collect(),
+        }
+    }
+}
@@ -1,23 +1,11 @@
-use log::info;
 use crate::core::database::pg_connect;
+use crate::core::postgresql_queries::query_table_exists;
+use crate::domain::two_db_error::TwoDbError;
 
-pub fn check_if_table_existed_in_db(database_name: &String, table_name: &String) -> bool {
+pub fn check_if_table_existed_in_db(database_name: &String, table_name: &String) -> Result<bool, TwoDbError> {
     // Check if the table is existed in the target database
-    let mut pg_client = pg_connect(database_name).unwrap();
-    let query_check_table_existed = format!("
-        SELECT EXISTS (
-          SELECT 1
-          FROM pg_tables
-          WHERE schemaname = 'public'
-            AND tablename = '{}'
-        );", table_name);
-    let rows = match pg_client.query(&query_check_table_existed, &[]) {
-        Ok(rows) => rows,
-        Err(err) => {
-            info!("Error querying : {:?}", err);
-            return false;
-        }
-    };
-    let row = rows.get(0).unwrap();
-    row.get(0)
-}
\ No newline at end of file
+    let mut pg_client = pg_connect(database_name)?;
+    let row = pg_client.query_one(query_table_exists(), &[table_name])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    Ok(row.get(0))
+}
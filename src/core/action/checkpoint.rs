@@ -0,0 +1,75 @@
+use std::error::Error;
+use postgres::GenericClient;
+use crate::core::postgresql_queries::query_get_primary_key_columns_by_table;
+use crate::domain::two_db_error::TwoDbError;
+
+/// Rows loaded and committed between two checkpoints
+pub const CHECKPOINT_CHUNK_SIZE: i64 = 10_000;
+
+/// Get the primary key column of a table, only if the key has exactly one column
+pub fn get_single_primary_key(client: &mut impl GenericClient, database_name: &str, table_name: &str) -> Result<Option<String>, TwoDbError> {
+    let rows = client.query(query_get_primary_key_columns_by_table(), &[&table_name])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    match rows.as_slice() {
+        [row] => Ok(Some(row.get("column_name"))),
+        _ => Ok(None),
+    }
+}
+
+/// Quote a value read back as text, PostgreSQL casts it to the type of the column it is compared to
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn condition_after(primary_key: &str, after: Option<&str>) -> String {
+    match after {
+        Some(after) => format!("WHERE {} > {}", primary_key, quote_literal(after)),
+        None => String::from(""),
+    }
+}
+
+/// Highest primary key of the next chunk after `after`, `None` once every row has been read
+pub fn get_chunk_upper_bound(
+    source_client: &mut impl GenericClient,
+    source_query: &str,
+    primary_key: &str,
+    after: Option<&str>,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let query = format!(
+        "SELECT {primary_key}::text FROM (
+            SELECT {primary_key} FROM ({source_query}) AS source_rows {condition}
+            ORDER BY {primary_key} LIMIT {CHECKPOINT_CHUNK_SIZE}
+        ) AS chunk
+        ORDER BY {primary_key} DESC LIMIT 1",
+        condition = condition_after(primary_key, after),
+    );
+    let row = source_client.query_opt(&query, &[])?;
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// SELECT returning the rows of one chunk, `after` excluded and `upper_bound` included
+pub fn query_chunk(source_query: &str, primary_key: &str, after: Option<&str>, upper_bound: &str) -> String {
+    let condition = match after {
+        Some(_) => format!("{} AND {} <= {}", condition_after(primary_key, after), primary_key, quote_literal(upper_bound)),
+        None => format!("WHERE {} <= {}", primary_key, quote_literal(upper_bound)),
+    };
+    format!("SELECT * FROM ({}) AS source_rows {} ORDER BY {}", source_query, condition, primary_key)
+}
+
+/// Where to resume loading a table into the target
+///
+/// Chunks are committed in primary key order, so rows above the checkpoint in the target
+/// mean the app stopped after a commit and before the checkpoint was written.
+pub fn get_resume_point(
+    target_client: &mut impl GenericClient,
+    table_name: &str,
+    primary_key: &str,
+    checkpoint: &str,
+) -> Result<String, Box<dyn Error>> {
+    let query = format!(
+        "SELECT MAX({primary_key})::text FROM {table_name} {condition}",
+        condition = condition_after(primary_key, Some(checkpoint)),
+    );
+    let last_committed: Option<String> = target_client.query_one(&query, &[])?.get(0);
+    Ok(last_committed.unwrap_or(checkpoint.to_string()))
+}
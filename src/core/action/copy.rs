@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::io;
+use log::info;
+use postgres::GenericClient;
+use serde::Deserialize;
+use crate::domain::two_column::TwoColumn;
+use crate::domain::two_db_error::TwoDbError;
+
+/// Format used by `COPY ... TO STDOUT` / `COPY ... FROM STDIN`
+///
+/// Binary is faster but requires the column types of source and target to be identical
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CopyFormat {
+    #[default]
+    Text,
+    Binary,
+}
+
+impl CopyFormat {
+    pub fn name(&self) -> &str {
+        match self {
+            CopyFormat::Text => "text",
+            CopyFormat::Binary => "binary",
+        }
+    }
+
+    /// Format named `text` or `binary`, as in the `[load]` section
+    pub fn from_name(name: &str) -> Result<CopyFormat, TwoDbError> {
+        match name.to_lowercase().as_str() {
+            "text" => Ok(CopyFormat::Text),
+            "binary" => Ok(CopyFormat::Binary),
+            other => Err(TwoDbError::Configuration(format!("Unknown copy format: {}", other))),
+        }
+    }
+}
+
+impl TryFrom<String> for CopyFormat {
+    type Error = TwoDbError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        CopyFormat::from_name(&name)
+    }
+}
+
+/// Stream rows of `table_name` from the source to the target database with COPY
+///
+/// `source_query` is the SELECT the rows are read from on `source_client`, e.g. `SELECT * FROM table_name`.
+/// Only `columns` are copied, in the same order on both sides.
+/// COPY is atomic: if it fails, nothing is written to the target table.
+/// `target_client` may be a transaction, the rows are then visible once it commits.
+///
+/// Return the number of rows written to the target
+pub fn copy_one_table(
+    source_client: &mut impl GenericClient,
+    target_client: &mut impl GenericClient,
+    table_name: &str,
+    source_query: &str,
+    columns: &[&TwoColumn],
+    format: CopyFormat,
+) -> Result<u64, Box<dyn Error>> {
+    let columns_str = columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>().join(", ");
+    let query_out = format!(
+        "COPY (SELECT {} FROM ({}) AS source_rows) TO STDOUT (FORMAT {})",
+        columns_str, source_query, format.name()
+    );
+    let query_in = format!("COPY {} ({}) FROM STDIN (FORMAT {})", table_name, columns_str, format.name());
+    info!("Copy out: {}", query_out);
+    info!("Copy in: {}", query_in);
+
+    let mut reader = source_client.copy_out(&query_out)?;
+    let mut writer = target_client.copy_in(&query_in)?;
+    io::copy(&mut reader, &mut writer)?;
+    let row_count = writer.finish()?;
+
+    info!("Copied {} rows into table: {}", row_count, table_name);
+    Ok(row_count)
+}
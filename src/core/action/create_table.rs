@@ -0,0 +1,83 @@
+use log::info;
+use serde::Deserialize;
+use crate::core::schema_ddl::{apply_ddl_script, build_create_table_scripts, get_missing_enum_types};
+use crate::core::schema_diff::get_schema;
+use crate::domain::ddl_script::DdlScript;
+use crate::domain::two_db_error::TwoDbError;
+
+/// What `move_one_table` does with a table missing in the target
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CreateTableMode {
+    /// The table is marked exported, its rows are not moved
+    #[default]
+    Skip,
+    /// The table is created with its columns, defaults and primary key,
+    /// and the sequences and enum types they use
+    Table,
+    /// Also its unique constraints, foreign keys and indexes, added once its rows are loaded.
+    /// They are not added if the load is interrupted, `twodb-cli ddl` lists them.
+    Full,
+}
+
+impl CreateTableMode {
+    pub fn name(&self) -> &str {
+        match self {
+            CreateTableMode::Skip => "SKIP",
+            CreateTableMode::Table => "TABLE",
+            CreateTableMode::Full => "FULL",
+        }
+    }
+
+    /// Mode named `skip`, `table` or `full`, as in the `[load]` section
+    pub fn from_name(name: &str) -> Result<CreateTableMode, TwoDbError> {
+        match name.to_lowercase().as_str() {
+            "skip" => Ok(CreateTableMode::Skip),
+            "table" => Ok(CreateTableMode::Table),
+            "full" => Ok(CreateTableMode::Full),
+            other => Err(TwoDbError::Configuration(format!("Unknown create missing tables mode: {}", other))),
+        }
+    }
+}
+
+impl TryFrom<String> for CreateTableMode {
+    type Error = TwoDbError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        CreateTableMode::from_name(&name)
+    }
+}
+
+/// Create `table_name` in the target as it is in the catalog of the source
+///
+/// Return the statements to run once its rows are loaded, none unless the mode is Full
+pub fn create_missing_table(
+    source_database_name: &str,
+    target_database_name: &str,
+    table_name: &str,
+    mode: CreateTableMode,
+) -> Result<DdlScript, TwoDbError> {
+    let Some(table) = get_schema(source_database_name)?.remove(table_name) else {
+        let message = format!("Table {} is not in the public schema", table_name);
+        return Err(TwoDbError::catalog(source_database_name, message));
+    };
+    let enum_types = get_missing_enum_types(source_database_name, target_database_name)?;
+
+    let new_script = || DdlScript {
+        source_database: source_database_name.to_string(),
+        target_database: target_database_name.to_string(),
+        ..Default::default()
+    };
+    let mut before_load = new_script();
+    let mut after_load = new_script();
+    build_create_table_scripts(&table, enum_types, &mut before_load, &mut after_load);
+
+    apply_ddl_script(&before_load)?;
+    info!("Table: {} created in {} with mode: {}", table_name, target_database_name, mode.name());
+
+    if mode != CreateTableMode::Full {
+        info!("Table: {} keys and indexes not created: {:?}", table_name, after_load.statements);
+        after_load.statements.clear();
+    }
+    Ok(after_load)
+}
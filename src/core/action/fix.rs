@@ -1,100 +1,112 @@
-use std::env::var;
 use log::{error, info};
-use postgres::Row;
-use crate::core::action::TWODB_NULL;
-use crate::core::action::working_database::{get_cell_value_by_column_name, get_rows};
+use postgres::types::ToSql;
+use crate::core::action::working_database::{get_cell_value_by_column_name, read_rows_in_batches};
+use crate::core::config::{get_load_settings, get_source_database_name, get_target_database_name};
 use crate::core::database::pg_connect;
 use crate::core::get_knowledge::{get_columns, get_tables_with_condition};
+use crate::domain::two_db_error::TwoDbError;
+use crate::domain::two_value::TwoValue;
 
-pub fn fix_numeric() {
-    let tables_to_fix = get_tables_numeric_wrong_data(0);
+pub fn fix_numeric() -> Result<(), TwoDbError> {
+    let tables_to_fix = get_tables_numeric_wrong_data(0)?;
     info!("Tables to fix length: {}", tables_to_fix.len());
     for table in tables_to_fix {
-        fix_numeric_for_one_table(table);
+        fix_numeric_for_one_table(table)?;
     }
+    Ok(())
 }
 
-pub fn fix_numeric_for_one_table(table_name: String) {
+pub fn fix_numeric_for_one_table(table_name: String) -> Result<(), TwoDbError> {
     info!("Fixing table: {}", table_name);
-    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-    let columns_source = get_columns(&source_database_name, &table_name);
+    let source_database_name = get_source_database_name()?;
+    let columns_source = get_columns(&source_database_name, &table_name)?;
     let numeric_columns_source = columns_source.iter().filter(|c| c.data_type == "numeric").collect::<Vec<_>>();
 
-    let target_database_name = var("POSTGRES_DB_TARGET").unwrap_or(String::from(""));
-    let columns_target = get_columns(&target_database_name, &table_name);
+    let target_database_name = get_target_database_name()?;
+    let columns_target = get_columns(&target_database_name, &table_name)?;
     let numeric_columns_target = columns_target.iter().filter(|c| c.data_type == "numeric").collect::<Vec<_>>();
 
-    let source_rows: Vec<Row> = get_rows(&source_database_name, &table_name);
-
     let final_columns = numeric_columns_target.iter().filter(|c| {
         numeric_columns_source.iter().any(|c2| c2.name == c.name)
     }).collect::<Vec<_>>();
 
-    let mut update_queries: Vec<String> = Vec::new();
+    if final_columns.is_empty() {
+        return Ok(());
+    }
 
-    // Check if there is no numeric column to fix
-    // let query_source;
-    // let query_target;
+    // Values are bound as parameters, the last one is the id
+    let set_pair: Vec<String> = final_columns.iter().enumerate()
+        .map(|(i, c)| format!("{} = ${}", c.name, i + 1))
+        .collect();
+    let query = format!("UPDATE {} SET {} WHERE id = ${}",
+                        table_name,
+                        set_pair.join(", "),
+                        final_columns.len() + 1);
+    info!("Query to update: {}", query);
 
-    for row in source_rows {
-        let set_pair : Vec<String> = final_columns.iter().map(|c| {
-            let value = get_cell_value_by_column_name(&table_name, &row, c.name.clone());
-            if value == TWODB_NULL {
-                return format!("{} = NULL", c.name);
-            }
-            format!("{} = '{}'", c.name, value)
-        }).collect();
-        let id: i64 = row.get("id");
-        // TODO: Build UPDATE SQL for each row
-        let query = format!("UPDATE {} SET {} WHERE id = {}",
-                            table_name,
-                            set_pair.join(", "),
-                            id.to_string());
-        update_queries.push(query);
-    }
+    let mut pg_client = pg_connect(&target_database_name)?;
+    let statement = pg_client.prepare(&query)
+        .map_err(|err| TwoDbError::transfer(&table_name, &err))?;
 
-    info!("Queries to update: {:?}", update_queries);
-    // Execute all queries
-    let mut pg_client = pg_connect(&target_database_name).unwrap();
-    for query in update_queries {
-        match pg_client.execute(&query, &[]) {
-            Ok(_) => info!("Run query {} successfully", query),
-            Err(err) => error!("Error updating: {:?}", err)
+    let mut source_client = pg_connect(&source_database_name)?;
+    let source_query = format!("SELECT * FROM {}", table_name);
+    let read = read_rows_in_batches(&mut source_client, &source_query, get_load_settings()?.batch_size, |rows| {
+        for row in rows {
+            let values: Result<Vec<TwoValue>, TwoDbError> = final_columns.iter()
+                .map(|c| get_cell_value_by_column_name(&table_name, row, &c.name))
+                .collect();
+            let values = match values {
+                Ok(values) => values,
+                Err(err) => {
+                    error!("Error reading row: {}", err);
+                    continue;
+                }
+            };
+            let id: i64 = row.get("id");
+            let mut params = values.iter().map(|v| v as &(dyn ToSql + Sync)).collect::<Vec<_>>();
+            params.push(&id);
+
+            match pg_client.execute(&statement, &params) {
+                Ok(_) => info!("Update row {} of table {} successfully", id, table_name),
+                Err(err) => error!("Error updating: {:?}", err)
+            }
         }
-    }
+        Ok(())
+    });
+    read.map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))
 }
 
-pub fn get_tables_numeric_wrong_data(limit:i8) -> Vec<String> {
-    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
+pub fn get_tables_numeric_wrong_data(limit:i8) -> Result<Vec<String>, TwoDbError> {
+    let source_database_name = get_source_database_name()?;
     let condition = format!("WHERE row_count > 0 AND \"database\" = '{}'", source_database_name);
     let tables_from_sqlite = get_tables_with_condition(
         &condition
-    );
+    )?;
     let mut tables_to_fix = Vec::new();
 
 
     for table in tables_from_sqlite {
         let table_name = table.name.clone();
-        if check_numeric_column(&table_name) {
+        if check_numeric_column(&table_name)? {
             tables_to_fix.push(table_name);
         }
     }
 
     let len_tables = tables_to_fix.len();
     if (limit > len_tables as i8) || (limit == 0) {
-        return tables_to_fix;
+        return Ok(tables_to_fix);
     }
 
-    tables_to_fix[0..limit as usize].to_vec()
+    Ok(tables_to_fix[0..limit as usize].to_vec())
 }
 
-fn check_numeric_column(table_name: &String) -> bool {
-    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-    let columns = get_columns(&source_database_name, table_name);
+fn check_numeric_column(table_name: &str) -> Result<bool, TwoDbError> {
+    let source_database_name = get_source_database_name()?;
+    let columns = get_columns(&source_database_name, table_name)?;
     for column in columns {
         if column.data_type == "numeric" {
-            return true;
+            return Ok(true);
         }
     }
-    false
+    Ok(false)
 }
\ No newline at end of file
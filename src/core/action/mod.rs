@@ -2,6 +2,10 @@ pub mod update;
 pub mod working_database;
 pub mod r#move;
 pub mod fix;
+pub mod copy;
+pub mod self_reference;
+pub mod checkpoint;
+pub mod trigger;
+pub mod sequence;
+pub mod create_table;
 mod check;
-
-pub const TWODB_NULL: &str = "twodb_NULL";
\ No newline at end of file
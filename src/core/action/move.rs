@@ -1,143 +1,757 @@
-use std::env::var;
-use log::info;
-use postgres::Row;
-use postgres::error::DbError;
-use crate::core::action::{check, TWODB_NULL};
-use crate::core::action::working_database::{get_cell_value_by_column_name, get_rows};
-use crate::core::get_knowledge::get_columns;
-use crate::domain::table::Table;
+use std::error::Error;
+use log::{error, info, warn};
+use postgres::{Client, Row, Statement, Transaction};
+use postgres::types::ToSql;
+use crate::core::action::check;
+use crate::core::action::sequence::resync_sequences;
+use crate::core::action::trigger::LoadSession;
+use crate::core::action::create_table::{create_missing_table, CreateTableMode};
+use crate::core::action::checkpoint::{get_chunk_upper_bound, get_resume_point, get_single_primary_key, query_chunk, quote_literal};
+use crate::core::action::copy::copy_one_table;
+use crate::core::action::self_reference::{backfill_self_reference, choose_strategy, get_self_reference, query_parent_first_rows, SelfReference};
+use crate::core::action::working_database::{check_supported_columns, different_row_count, get_cell_value_by_column_name, read_rows_in_batches};
+use crate::core::dependency_graph::update_export_order;
+use crate::core::get_knowledge::{get_columns_with, get_tables_in_export_order};
+use crate::domain::ddl_script::DdlScript;
+use crate::domain::mapping_rule::{ColumnSource, MappedColumn, TableMapping};
+use crate::domain::table::{SelfReferencingStrategy, Table};
 use crate::domain::two_column::TwoColumn;
+use crate::domain::two_db_error::TwoDbError;
+use crate::domain::two_value::TwoValue;
+use crate::core::config::{get_load_settings, get_source_database_name, get_target_database_name};
 use crate::core::database::pg_connect;
-use crate::core::table::update_is_exported;
+use crate::core::mapping::get_table_mapping;
+use crate::core::row_filter::SourceRows;
+use crate::core::schema_ddl::apply_ddl_script_with;
+use crate::core::table_sync::{copy_changed_keys, get_max_value, get_table_sync, query_row_hash, query_watermark_condition, save_watermark};
+use crate::core::transform::{get_row_transform, RowTransform};
+use crate::core::migration_run::{
+    delete_checkpoint, finish_migration_run, get_checkpoint, get_running_migration_run, record_suppressed_triggers,
+    save_checkpoint, start_migration_run,
+};
+use crate::core::table::{update_is_exported, update_self_referencing_strategy};
 
-fn set_table_is_exported(table_name: &String, is_exported: bool) {
-    let mut default_table = Table::default();
-    default_table.name = table_name.clone();
-    default_table.is_exported = is_exported;
-    update_is_exported(&mut default_table);
+fn set_table_is_exported(table_name: &str, database_name: &str, is_exported: bool) -> Result<(), TwoDbError> {
+    let mut table = Table {
+        name: table_name.to_string(),
+        database: database_name.to_string(),
+        is_exported,
+        ..Default::default()
+    };
+    update_is_exported(&mut table)
 }
 
-fn prepare_insert_queries(table_name: &String, rows: &Vec<Row>) -> Vec<String> {
-    let mut queries: Vec<String> = Vec::new();
+/// Get the columns of the target table filled by the move, in target order
+///
+/// Without mapping rules, the columns that exist in both source and target databases
+fn get_mapped_columns(source_client: &mut Client, target_client: &mut Client, mapping: &TableMapping) -> Result<Vec<MappedColumn>, TwoDbError> {
+    let table_name = &mapping.table_name;
+    let source_database_name = get_source_database_name()?;
+    let columns_source = get_columns_with(source_client, &source_database_name, table_name)?;
 
-    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-    let columns_source = get_columns(&source_database_name, table_name);
+    let target_database_name = get_target_database_name()?;
+    let columns_target = get_columns_with(target_client, &target_database_name, mapping.target_table())?;
 
-    let target_database_name = var("POSTGRES_DB_TARGET").unwrap_or(String::from(""));
-    let columns_target = get_columns(&target_database_name, table_name);
+    for column in &columns_source {
+        match mapping.target_column(&column.name) {
+            None => info!("Column {}.{} is excluded, its data is not moved", table_name, column.name),
+            Some(target_column) if !columns_target.iter().any(|c| c.name == target_column) => {
+                warn!("Column {}.{} is not in {}, its data is not moved", mapping.target_table(), target_column, target_database_name);
+            }
+            Some(_) => {}
+        }
+    }
 
-    let final_columns = columns_target.iter().filter(|c| {
-        columns_source.iter().any(|c2| c2.name == c.name)
-    }).collect::<Vec<_>>();
+    let final_columns = mapping.map_columns(&columns_source, columns_target);
     info!("Final columns: {:?}", final_columns);
+    Ok(final_columns)
+}
 
-    // STEP 2: Insert data into target database
-    for source_row in rows {
-        // TODO: Build columns that have in source db only
-
-        let query: String = build_insert_query_2(table_name, &final_columns, source_row);
-        queries.push(query);
+/// SELECT giving the source columns the names of their target columns
+///
+/// `source_query` as is when no column is renamed
+fn query_mapped_columns(source_query: &str, columns: &[&MappedColumn]) -> String {
+    let is_renamed = |c: &MappedColumn| matches!(&c.source, ColumnSource::Column(name) if *name != c.target.name);
+    if !columns.iter().any(|c| is_renamed(c)) {
+        return source_query.to_string();
     }
+    let select = columns.iter().filter_map(|c| match &c.source {
+        ColumnSource::Column(name) if is_renamed(c) => Some(format!("{} AS {}", name, c.target.name)),
+        ColumnSource::Column(name) => Some(name.clone()),
+        _ => None,
+    }).collect::<Vec<_>>();
+    format!("SELECT {} FROM ({}) AS source_rows", select.join(", "), source_query)
+}
 
-    queries
+/// A target column filled by a Constant or Default mapping rule, selected with the rows
+struct ColumnFill<'a> {
+    column: &'a TwoColumn,
+    /// SQL expression, a quoted literal for a constant
+    expression: String,
+    /// A Default is evaluated by the target, the rows of its table are inserted instead of copied
+    is_constant: bool,
 }
 
-pub fn get_queries_one_table(table_name: &String) {
-    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-    let source_rows: Vec<Row> = get_rows(&source_database_name, &table_name);
-    let queries: Vec<String> = prepare_insert_queries(table_name, &source_rows);
-    for query in queries {
-        info!("Query: {:?}", query);
-    }
+fn get_column_fills<'a>(filled_columns: &[&'a MappedColumn]) -> Vec<ColumnFill<'a>> {
+    filled_columns.iter().filter_map(|c| {
+        let (expression, is_constant) = match &c.source {
+            ColumnSource::Constant(value) => (quote_literal(value), true),
+            ColumnSource::Default(expression) => (expression.clone(), false),
+            ColumnSource::Column(_) => return None,
+        };
+        Some(ColumnFill { column: &c.target, expression, is_constant })
+    }).collect()
+}
+
+/// Columns of the rows loaded into the target, and how their values are transformed
+struct FinalColumns<'a> {
+    /// Columns read from the source
+    columns: Vec<&'a TwoColumn>,
+    fills: Vec<ColumnFill<'a>>,
+    transform: RowTransform,
+    /// Primary key of the target, rows already there are updated instead of inserted
+    conflict_key: Option<String>,
+}
+
+/// Source and target names of the columns read from the source, for their transform rules
+fn get_transform_columns<'a>(sourced_columns: &[&'a MappedColumn]) -> Vec<(&'a str, &'a TwoColumn)> {
+    sourced_columns.iter().filter_map(|c| match &c.source {
+        ColumnSource::Column(name) => Some((name.as_str(), &c.target)),
+        _ => None,
+    }).collect()
 }
 
-pub fn move_one_table(table_name: String) {
-    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-    let target_database_name = var("POSTGRES_DB_TARGET").unwrap_or(String::from(""));
+fn set_table_self_referencing_strategy(database_name: &str, table_name: &str, strategy: SelfReferencingStrategy) -> Result<(), TwoDbError> {
+    let table = Table {
+        name: table_name.to_string(),
+        database: database_name.to_string(),
+        self_referencing_strategy: strategy,
+        ..Default::default()
+    };
+    update_self_referencing_strategy(&table)
+}
 
-    // STEP 1: Get data of table from source database and target database
-    let source_rows: Vec<Row> = get_rows(&source_database_name, &table_name);
-    let target_rows: Vec<Row> = get_rows(&target_database_name, &table_name);
+/// Read the values of `columns` from a source row, in the same order
+fn read_row_values(table_name: &str, columns: &[&TwoColumn], row: &Row) -> Result<Vec<TwoValue>, TwoDbError> {
+    // TODO: Build columns that have in source db only
+    columns.iter()
+        .map(|c| get_cell_value_by_column_name(table_name, row, &c.name))
+        .collect()
+}
 
+pub fn get_queries_one_table(table_name: &str) -> Result<(), TwoDbError> {
+    let source_database_name = get_source_database_name()?;
+    let mapping = get_table_mapping(&source_database_name, table_name)?;
+    let mut source = SourceRows::open(&source_database_name, table_name, None)?;
+    let mut pg_client = pg_connect(&get_target_database_name()?)?;
+    let mapped_columns = get_mapped_columns(&mut source.client, &mut pg_client, &mapping)?;
+    let (sourced_columns, filled_columns): (Vec<_>, Vec<_>) = mapped_columns.iter()
+        .partition(|c| matches!(c.source, ColumnSource::Column(_)));
+    let final_columns = sourced_columns.iter().map(|c| &c.target).collect::<Vec<_>>();
+    let row_transform = get_row_transform(&source_database_name, table_name, &get_transform_columns(&sourced_columns))?;
+    info!("Query: {:?}", build_insert_query(mapping.target_table(), &final_columns, &get_column_fills(&filled_columns)));
+    let source_query = query_mapped_columns(&format!("SELECT * FROM {} AS source_rows", source.relation), &sourced_columns);
+    let read = read_rows_in_batches(&mut source.client, &source_query, get_load_settings()?.batch_size, |rows| {
+        for source_row in rows {
+            info!("Values: {:?}", read_row_values(table_name, &final_columns, source_row).and_then(|values| row_transform.apply(values)));
+        }
+        Ok(())
+    });
+    read.map_err(|err| TwoDbError::transfer(table_name, err.as_ref()))
+}
 
-    // STEP 2: Check if data has been extracted
+/// Tables of the source not exported yet, in export order
+pub fn get_tables_to_move(source_database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+    get_tables_in_export_order(source_database_name, true)
+}
 
-    // Case : Both source and target databases are empty
-    if source_rows.len() == 0 && target_rows.len() == 0 {
-        set_table_is_exported(&table_name, true);
-        info!("Both source and target databases are empty");
-        return;
-    }
+/// Move every table not exported yet, parents first
+///
+/// A table that fails is skipped and the run stays unfinished, so the next run resumes it.
+/// Return the tables that were not moved, with their error
+pub fn move_all_tables(source_database_name: &str, target_database_name: &str) -> Result<Vec<(String, TwoDbError)>, TwoDbError> {
+    // Tables left halfway by an unfinished run continue from their checkpoint
+    let run_id = start_migration_run(source_database_name, target_database_name)?;
 
-    // Case 1: Data has been extracted
-    if target_rows.len() > 0 && source_rows.len() > 0 && source_rows.len() == target_rows.len() {
-        set_table_is_exported(&table_name, true);
-        info!("Data has been extracted from source database");
-        return;
-    }
+    // Parents first, so foreign keys are satisfied when children are loaded
+    update_export_order(source_database_name)?;
+    let tables_from_sqlite = get_tables_to_move(source_database_name)?;
+    info!("Tables from sqlite: {:?}", tables_from_sqlite);
 
-    if !check::check_if_table_existed_in_db(&target_database_name, &table_name) {
-        set_table_is_exported(&table_name, true);
-        info!("Table: {} does not exist in the target database", table_name);
-        return;
+    let mut failures = Vec::new();
+    for table in tables_from_sqlite {
+        if let Err(err) = move_one_table(table.name.clone()) {
+            error!("{}", err);
+            failures.push((table.name, err));
+        }
     }
+    if failures.is_empty() {
+        finish_migration_run(run_id)?;
+    }
+    Ok(failures)
+}
+
+pub fn move_one_table(table_name: String) -> Result<(), TwoDbError> {
+    let source_database_name = get_source_database_name()?;
+    let target_database_name = get_target_database_name()?;
+    let mapping = get_table_mapping(&source_database_name, &table_name)?;
+    let target_table_name = mapping.target_table().to_string();
 
-    let queries: Vec<String> = prepare_insert_queries(&table_name, &source_rows);
-    // STEP 2: Insert data into target database
+    // STEP 1: Create the table if it is missing, then check if data has been extracted, counting is enough
+    let mut after_load = None;
+    if !check::check_if_table_existed_in_db(&target_database_name, &target_table_name)? {
+        let mode = get_load_settings()?.create_missing_tables;
+        if mode == CreateTableMode::Skip {
+            set_table_is_exported(&table_name, &source_database_name, true)?;
+            warn!("Table: {} does not exist in the target database, its rows are not moved", target_table_name);
+            return Ok(());
+        }
+        // The rules describe a table of the target that is not there
+        if !mapping.rules.is_empty() {
+            return Err(TwoDbError::Transfer {
+                table: table_name,
+                message: format!("Table {} of its mapping rules does not exist in the target database", target_table_name),
+            });
+        }
+        after_load = Some(create_missing_table(&source_database_name, &target_database_name, &table_name, mode)?);
+    }
 
-    // len
-    info!("Queries len: {:?}", queries.len());
-    let mut failed_queries: Vec<String> = Vec::new();
+    // A table created above is dropped if its rows or keys are not all in, the next run creates it again
+    let Some(after_load) = after_load else {
+        return load_one_table(&source_database_name, &target_database_name, table_name, &mapping, None);
+    };
+    let loaded = load_one_table(&source_database_name, &target_database_name, table_name, &mapping, Some(&after_load));
+    if loaded.is_err() {
+        drop_created_table(&source_database_name, &target_database_name, &target_table_name);
+    }
+    loaded
+}
 
-    let mut pg_client = pg_connect(&target_database_name).unwrap();
+/// Load the rows of a table existing in the target, unless they are already there
+///
+/// `after_load` are the keys and indexes of a table created by the move.
+/// One client of each database is borrowed for the whole table, a pool of one connection is enough.
+fn load_one_table(
+    source_database_name: &str,
+    target_database_name: &str,
+    table_name: String,
+    mapping: &TableMapping,
+    after_load: Option<&DdlScript>,
+) -> Result<(), TwoDbError> {
+    let target_table_name = mapping.target_table().to_string();
+    let run_id = get_running_migration_run(source_database_name, target_database_name)?;
+    let mut source = SourceRows::open(source_database_name, &table_name, run_id)?;
+    let mut pg_client = pg_connect(target_database_name)?;
 
-    // TODO: Disable trigger before insert data
+    if !different_row_count(&mut source, &mut pg_client, target_database_name, &target_table_name)? {
+        info!("Data has been extracted from source database");
+        return finish_table(&mut source, &mut pg_client, mapping, after_load);
+    }
 
-    for query in queries {
-        info!("Query: {:?}", query);
+    // STEP 2: Self-referencing tables need their parents inserted before their children
+    let relation = source.relation.clone();
+    let self_reference = get_self_reference(&mut *source.client, source_database_name, &table_name)?;
+    let strategy = match &self_reference {
+        Some(self_reference) => choose_strategy(&mut *source.client, &table_name, &relation, self_reference)?,
+        None => SelfReferencingStrategy::Unknown,
+    };
+    set_table_self_referencing_strategy(source_database_name, &table_name, strategy)?;
+    info!("Table: {} self referencing strategy: {:?}", table_name, strategy);
 
-        // Run query
-        match pg_client.query(&query, &[]) {
-            Ok(_) => {
-                info!("Query executed successfully");
-                set_table_is_exported(&table_name, true);
+    let mut mapped_columns = get_mapped_columns(&mut source.client, &mut pg_client, mapping)?;
+    let mut source_query = format!("SELECT * FROM {} AS source_rows", relation);
+    if let Some(self_reference) = &self_reference {
+        match strategy {
+            SelfReferencingStrategy::ParentFirst => {
+                source_query = query_parent_first_rows(&relation, self_reference);
             }
-            Err(err) => {
-                failed_queries.push(query);
-                // error!("Error when migrate data to table: {} \n Error: {:?}", table_name, err);
-
-                let err: &DbError = err.as_db_error().unwrap();
-                let detail = err.detail().unwrap(); // "Key (document_id)=(55) is not present in table \"materialflowresources_document\"."
-                let table_ref = detail.split(" ").last().unwrap().replace("\"", "");
-                let table_ref = table_ref.trim_end_matches('.').to_string();
-
-                move_one_table(table_ref);
-
-                // let table_name = err.table().unwrap();
-                // let constraint = err.constraint().unwrap();
-                // error!("Error when migrate data to table: {} by constraint: {} \n Error: {:?}", table_name, constraint, err);
-                // let constraint_table = get_constraint_table(&target_database_name, constraint);
-                // info!("Constraint table: {:?}", constraint_table);
+            SelfReferencingStrategy::Backfill => {
+                mapped_columns.retain(|c| c.source != ColumnSource::Column(self_reference.column.clone()));
             }
+            SelfReferencingStrategy::Unknown => {}
+        }
+    }
+    // Columns filled by a mapping rule are not read from the source
+    let (sourced_columns, filled_columns): (Vec<_>, Vec<_>) = mapped_columns.iter()
+        .partition(|c| matches!(c.source, ColumnSource::Column(_)));
+    let source_query = query_mapped_columns(&source_query, &sourced_columns);
+    let final_columns = FinalColumns {
+        columns: sourced_columns.iter().map(|c| &c.target).collect(),
+        fills: get_column_fills(&filled_columns),
+        transform: get_row_transform(source_database_name, &table_name, &get_transform_columns(&sourced_columns))?,
+        conflict_key: None,
+    };
+
+    // STEP 3: Load the table, a failure leaves the target table as it was at the last commit.
+    // Parent first order cannot be split by primary key, such tables are loaded in one transaction.
+    // So are tables with a transformed primary key, the target keys are not in source order.
+    let primary_key = match strategy {
+        SelfReferencingStrategy::ParentFirst => None,
+        _ => get_single_primary_key(&mut *source.client, source_database_name, &table_name)?
+            .and_then(|primary_key| mapping.target_column(&primary_key).map(String::from))
+            .filter(|primary_key| final_columns.columns.iter().any(|c| &c.name == primary_key))
+            .filter(|primary_key| !final_columns.transform.is_transformed(primary_key)),
+    };
+    let load_session = LoadSession::prepare(&mut *pg_client, &target_table_name, &get_load_settings()?)
+        .map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))?;
+    record_suppressed_triggers(run_id, target_database_name, &load_session)?;
+
+    let loaded = match &primary_key {
+        Some(primary_key) => load_with_checkpoints(
+            &mut pg_client,
+            &load_session,
+            run_id,
+            &mut source,
+            &source_query,
+            &final_columns,
+            primary_key,
+        ),
+        None => load_in_one_transaction(&mut pg_client, &load_session, &mut source, &source_query, &final_columns),
+    }.and_then(|_| {
+        // No transaction, nor triggers disabled again, when there is nothing to backfill
+        let Some((self_reference, target_self_reference)) = get_self_reference_to_backfill(mapping, &self_reference, strategy) else {
+            return Ok(());
         };
+        let mut transaction = load_session.begin(&mut pg_client)?;
+        backfill(&mut transaction, &mut source, mapping, self_reference, &target_self_reference)?;
+        load_session.commit(transaction)
+    });
+    if let Err(err) = loaded {
+        error!("Table: {} rolled back \n Error: {}", table_name, err);
+        return Err(TwoDbError::transfer(&table_name, err.as_ref()));
+    }
+
+    // STEP 4: The table is exported only if nothing is missing in the target
+    if different_row_count(&mut source, &mut pg_client, target_database_name, &target_table_name)? {
+        return Err(TwoDbError::Transfer {
+            table: table_name,
+            message: String::from("Committed but the row counts do not match"),
+        });
     }
+    finish_table(&mut source, &mut pg_client, mapping, after_load)?;
+    info!("Table: {} moved to {}", table_name, target_table_name);
+    Ok(())
+}
+
+/// Add the keys of a created table, resync its sequences, then record it as exported
+///
+/// Keys and indexes are faster to build once the rows are in.
+/// Explicit ids were inserted, the next ones must come after them.
+/// A table whose keys or resync failed is not exported, so the next run does them again.
+fn finish_table(
+    source: &mut SourceRows,
+    pg_client: &mut Client,
+    mapping: &TableMapping,
+    after_load: Option<&DdlScript>,
+) -> Result<(), TwoDbError> {
+    if let Some(after_load) = after_load {
+        apply_ddl_script_with(pg_client, after_load)?;
+    }
+    resync_sequences(&mut *source.client, pg_client, mapping)
+        .map_err(|err| TwoDbError::transfer(&mapping.table_name, err.as_ref()))?;
+    set_table_is_exported(&mapping.table_name, &source.database_name, true)?;
+    delete_checkpoint(mapping.target_table(), &source.database_name)
+}
 
-    if failed_queries.len() > 0 {
-        info!("Failed queries: {:?}", failed_queries);
+/// Drop a table created by a move that failed, with its checkpoint
+///
+/// Its keys are only known when it is created, a table left behind would never get them.
+fn drop_created_table(source_database_name: &str, target_database_name: &str, table_name: &str) {
+    let dropped = pg_connect(target_database_name)
+        .and_then(|mut client| client.batch_execute(&format!("DROP TABLE {}", table_name))
+            .map_err(|err| TwoDbError::schema_change(target_database_name, "DROP TABLE", err)))
+        .and_then(|_| delete_checkpoint(table_name, source_database_name));
+    match dropped {
+        Ok(_) => info!("Table: {} created by the failed move is dropped", table_name),
+        Err(err) => error!("Table: {} created by the failed move cannot be dropped, drop it before the next run: {}", table_name, err),
     }
 }
 
-fn build_insert_query_2(table_name: &String, columns: &Vec<&TwoColumn>, row: &Row) -> String {
-    let columns_str = columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>().join(", ");
-    let values_str = columns.iter().map(
-        |c|
-        {
-            let value = get_cell_value_by_column_name(table_name, row, c.name.clone());
-            if value == TWODB_NULL {
-                return "NULL".to_string();
+/// Every table of the source, exported or not, in export order
+pub fn get_tables_to_sync(source_database_name: &str) -> Result<Vec<Table>, TwoDbError> {
+    get_tables_in_export_order(source_database_name, false)
+}
+
+/// Sync every table of the source, parents first
+///
+/// A table that fails is skipped, return the tables that were not synced, with their error
+pub fn sync_all_tables(source_database_name: &str) -> Result<Vec<(String, TwoDbError)>, TwoDbError> {
+    update_export_order(source_database_name)?;
+    let tables_from_sqlite = get_tables_to_sync(source_database_name)?;
+
+    let mut failures = Vec::new();
+    for table in tables_from_sqlite {
+        if let Err(err) = sync_one_table(table.name.clone()) {
+            error!("{}", err);
+            failures.push((table.name, err));
+        }
+    }
+    Ok(failures)
+}
+
+/// Copy the rows of a table inserted or changed since it was moved, see `table_sync`
+///
+/// The rows are upserted on the primary key in one transaction, parents first in a
+/// self-referencing table. A table missing from the target is moved instead.
+pub fn sync_one_table(table_name: String) -> Result<(), TwoDbError> {
+    let source_database_name = get_source_database_name()?;
+    let target_database_name = get_target_database_name()?;
+    let mapping = get_table_mapping(&source_database_name, &table_name)?;
+    let target_table_name = mapping.target_table().to_string();
+    if !check::check_if_table_existed_in_db(&target_database_name, &target_table_name)? {
+        return move_one_table(table_name);
+    }
+
+    // STEP 1: Changed rows are matched with the target rows by primary key
+    let mut pg_client = pg_connect(&target_database_name)?;
+    let mut source = SourceRows::open(&source_database_name, &table_name, None)?;
+    let mapped_columns = get_mapped_columns(&mut source.client, &mut pg_client, &mapping)?;
+    let (sourced_columns, filled_columns): (Vec<_>, Vec<_>) = mapped_columns.iter()
+        .partition(|c| matches!(c.source, ColumnSource::Column(_)));
+    let row_transform = get_row_transform(&source_database_name, &table_name, &get_transform_columns(&sourced_columns))?;
+    let source_primary_key = get_single_primary_key(&mut *source.client, &source_database_name, &table_name)?;
+    let primary_key = sourced_columns.iter()
+        .find(|c| source_primary_key.as_ref().is_some_and(|key| c.source == ColumnSource::Column(key.clone())));
+    let (Some(source_primary_key), Some(primary_key)) = (source_primary_key, primary_key) else {
+        return Err(TwoDbError::Transfer {
+            table: table_name,
+            message: String::from("Only a table with a single column primary key moved to the target can be synced"),
+        });
+    };
+    let primary_key = &primary_key.target;
+
+    // STEP 2: Find the rows to sync, by watermark or by primary key
+    let relation = source.relation.clone();
+    let table_sync = get_table_sync(&source_database_name, &table_name)?.unwrap_or_default();
+    let (condition, watermark) = if table_sync.change_column.is_empty() {
+        if row_transform.is_transformed(&primary_key.name) {
+            return Err(TwoDbError::Configuration(format!(
+                "Table {} has a transformed primary key, set a change column to sync it", table_name
+            )));
+        }
+        // Rows are compared on the columns moved as they are
+        let (source_columns, target_columns): (Vec<_>, Vec<_>) = sourced_columns.iter().filter_map(|c| match &c.source {
+            ColumnSource::Column(name) if !row_transform.is_transformed(&c.target.name) => Some((name.as_str(), c.target.name.as_str())),
+            _ => None,
+        }).unzip();
+        let key_query = format!(
+            "SELECT {} AS {}, {} AS twodb_row_hash FROM {} AS source_rows",
+            source_primary_key, primary_key.name, query_row_hash(&source_columns), relation
+        );
+        let changed_count = copy_changed_keys(
+            &mut source, &mut pg_client, &key_query, &source_primary_key, &target_table_name, primary_key, &query_row_hash(&target_columns),
+        ).map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))?;
+        info!("Table: {} has {} rows missing from {} or changed", table_name, changed_count, target_table_name);
+        let condition = (changed_count > 0)
+            .then(|| format!("{} IN (SELECT {} FROM twodb_changed_keys)", source_primary_key, primary_key.name));
+        (condition, String::new())
+    } else {
+        let change_column = &table_sync.change_column;
+        let last_watermark = match table_sync.watermark.as_str() {
+            // Before the first sync, the target has the rows moved so far, unless their values were transformed
+            "" => match mapping.target_column(change_column) {
+                Some(target_column) if !row_transform.is_transformed(target_column) => {
+                    get_max_value(&mut *pg_client, &target_table_name, target_column)
+                        .map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))?
+                }
+                _ => None,
+            },
+            watermark => Some(watermark.to_string()),
+        };
+        // Rows changed while they are copied are left for the next sync
+        let next_watermark = get_max_value(&mut *source.client, &relation, change_column)
+            .map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))?;
+        info!("Table: {} syncs {} from {:?} to {:?}", table_name, change_column, last_watermark, next_watermark);
+        let condition = query_watermark_condition(change_column, last_watermark.as_deref(), next_watermark.as_deref());
+        (condition, next_watermark.or(last_watermark).unwrap_or_default())
+    };
+    let Some(condition) = condition else {
+        info!("Table: {} is in sync", table_name);
+        return save_watermark(&source_database_name, &table_name, &watermark);
+    };
+    let changed_rows = format!("(SELECT * FROM {} AS source_rows WHERE {})", relation, condition);
+
+    // STEP 3: Parents first, a changed row may point to a row changed after it
+    let source_query = match get_self_reference(&mut *source.client, &source_database_name, &table_name)? {
+        Some(self_reference) => {
+            let strategy = choose_strategy(&mut *source.client, &table_name, &changed_rows, &self_reference)?;
+            if strategy != SelfReferencingStrategy::ParentFirst {
+                return Err(TwoDbError::Transfer {
+                    table: table_name,
+                    message: String::from("The changed rows reference each other in a cycle, they cannot be synced parent first"),
+                });
             }
-            format!("'{}'", get_cell_value_by_column_name(table_name, row, c.name.clone()))
+            query_parent_first_rows(&changed_rows, &self_reference)
+        }
+        None => format!("SELECT * FROM {} AS source_rows", changed_rows),
+    };
+    let source_query = query_mapped_columns(&source_query, &sourced_columns);
+    let final_columns = FinalColumns {
+        columns: sourced_columns.iter().map(|c| &c.target).collect(),
+        fills: get_column_fills(&filled_columns),
+        transform: row_transform,
+        conflict_key: Some(primary_key.name.clone()),
+    };
+
+    // STEP 4: Upsert the rows, a failure leaves the target table and the watermark as they were
+    let load_session = LoadSession::prepare(&mut *pg_client, &target_table_name, &get_load_settings()?)
+        .map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))?;
+    record_suppressed_triggers(None, &target_database_name, &load_session)?;
+    let loaded = load_in_one_transaction(&mut pg_client, &load_session, &mut source, &source_query, &final_columns);
+    if let Err(err) = loaded {
+        error!("Table: {} rolled back \n Error: {}", table_name, err);
+        return Err(TwoDbError::transfer(&table_name, err.as_ref()));
+    }
+    // The watermark moves on once the sequences are past the new ids, a failed resync is synced again
+    resync_sequences(&mut *source.client, &mut *pg_client, &mapping)
+        .map_err(|err| TwoDbError::transfer(&table_name, err.as_ref()))?;
+    save_watermark(&source_database_name, &table_name, &watermark)?;
+    info!("Table: {} synced to {}", table_name, target_table_name);
+    Ok(())
+}
+
+fn load_in_one_transaction(
+    pg_client: &mut Client,
+    load_session: &LoadSession,
+    source: &mut SourceRows,
+    source_query: &str,
+    final_columns: &FinalColumns<'_>,
+) -> Result<(), Box<dyn Error>> {
+    let mut transaction = load_session.begin(pg_client)?;
+    load_rows(&mut transaction, load_session, source, source_query, final_columns)?;
+    load_session.commit(transaction)
+}
+
+/// Load the table chunk by chunk in primary key order, each chunk is committed with a checkpoint
+///
+/// Start after the checkpoint of a previous run, if any.
+fn load_with_checkpoints(
+    pg_client: &mut Client,
+    load_session: &LoadSession,
+    run_id: Option<i64>,
+    source: &mut SourceRows,
+    source_query: &str,
+    final_columns: &FinalColumns<'_>,
+    primary_key: &str,
+) -> Result<(), Box<dyn Error>> {
+    let table_name = load_session.table_name.as_str();
+
+    let mut after = match get_checkpoint(table_name, &source.database_name)? {
+        Some(checkpoint) => {
+            let resume_point = get_resume_point(pg_client, table_name, primary_key, &checkpoint)?;
+            info!("Table: {} resumes after {} = {}", table_name, primary_key, resume_point);
+            Some(resume_point)
+        }
+        None => None,
+    };
+
+    while let Some(upper_bound) = get_chunk_upper_bound(&mut *source.client, source_query, primary_key, after.as_deref())? {
+        let chunk_query = query_chunk(source_query, primary_key, after.as_deref(), &upper_bound);
+        let mut transaction = load_session.begin(pg_client)?;
+        load_rows(&mut transaction, load_session, source, &chunk_query, final_columns)?;
+        load_session.commit(transaction)?;
+
+        save_checkpoint(run_id, table_name, &source.database_name, primary_key, &upper_bound)?;
+        info!("Table: {} committed up to {} = {}", table_name, primary_key, upper_bound);
+        after = Some(upper_bound);
+    }
+    Ok(())
+}
+
+/// Stream data with COPY, fall back to row-by-row INSERT if it fails
+///
+/// Rows with transformed values are always inserted, COPY passes them through as they are.
+/// So are rows upserted, COPY cannot update a row.
+fn load_rows(
+    transaction: &mut Transaction<'_>,
+    load_session: &LoadSession,
+    source: &mut SourceRows,
+    source_query: &str,
+    final_columns: &FinalColumns<'_>,
+) -> Result<(), Box<dyn Error>> {
+    let table_name = load_session.table_name.as_str();
+    let has_default = final_columns.fills.iter().any(|fill| !fill.is_constant);
+    if !final_columns.transform.is_empty() || final_columns.conflict_key.is_some() || has_default {
+        info!("Table: {} has transformed, upserted or default filled rows, its rows are inserted", table_name);
+        return insert_rows(transaction, source, table_name, source_query, final_columns);
+    }
+    // Constants are copied with the rows
+    let mut copy_columns = final_columns.columns.clone();
+    let mut select = copy_columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+    for fill in &final_columns.fills {
+        copy_columns.push(fill.column);
+        select.push(format!("{} AS {}", fill.expression, fill.column.name));
+    }
+    let copy_query = format!("SELECT {} FROM ({}) AS source_rows", select.join(", "), source_query);
+
+    // A failed COPY aborts only its savepoint, not the whole transaction
+    let mut savepoint = transaction.savepoint("twodb_copy")?;
+    match copy_one_table(&mut *source.client, &mut savepoint, table_name, &copy_query, &copy_columns, load_session.copy_format) {
+        Ok(_) => savepoint.commit()?,
+        Err(err) => {
+            error!("COPY failed for table: {}, fall back to INSERT \n Error: {:?}", table_name, err);
+            savepoint.rollback()?;
+
+            insert_rows(transaction, source, table_name, source_query, final_columns)?;
+        }
+    }
+    Ok(())
+}
+
+/// The self reference of the source and its columns in the target, if the table is backfilled
+///
+/// Nothing to backfill when the self-referencing column is not moved.
+fn get_self_reference_to_backfill<'a>(
+    mapping: &TableMapping,
+    self_reference: &'a Option<SelfReference>,
+    strategy: SelfReferencingStrategy,
+) -> Option<(&'a SelfReference, SelfReference)> {
+    if strategy != SelfReferencingStrategy::Backfill {
+        return None;
+    }
+    let self_reference = self_reference.as_ref()?;
+    Some((self_reference, self_reference.mapped(mapping)?))
+}
+
+fn backfill(
+    transaction: &mut Transaction<'_>,
+    source: &mut SourceRows,
+    mapping: &TableMapping,
+    self_reference: &SelfReference,
+    target_self_reference: &SelfReference,
+) -> Result<(), Box<dyn Error>> {
+    // The keys are transformed like the rows they belong to
+    let target_columns = get_columns_with(transaction, &get_target_database_name()?, mapping.target_table())?;
+    let pair = [
+        (&self_reference.referenced_column, &target_self_reference.referenced_column),
+        (&self_reference.column, &target_self_reference.column),
+    ];
+    let transform_columns = pair.iter().filter_map(|(source_column, target_column)| {
+        let target_column = target_columns.iter().find(|c| &&c.name == target_column)?;
+        Some((source_column.as_str(), target_column))
+    }).collect::<Vec<_>>();
+    let row_transform = get_row_transform(&source.database_name, &mapping.table_name, &transform_columns)?;
+    backfill_self_reference(source, transaction, mapping, self_reference, target_self_reference, &row_transform)?;
+    Ok(())
+}
+
+/// Insert rows in batches as they are read from the source, values are bound as parameters
+///
+/// Tables are moved parents first, a missing parent row is reported as a ConstraintViolation.
+fn insert_rows(
+    transaction: &mut Transaction<'_>,
+    source: &mut SourceRows,
+    table_name: &str,
+    source_query: &str,
+    final_columns: &FinalColumns<'_>,
+) -> Result<(), Box<dyn Error>> {
+    let query = match &final_columns.conflict_key {
+        Some(conflict_key) => build_upsert_query(table_name, &final_columns.columns, &final_columns.fills, conflict_key),
+        None => build_insert_query(table_name, &final_columns.columns, &final_columns.fills),
+    };
+    info!("Query: {:?}", query);
+    let statement = transaction.prepare(&query)?;
+    let column_names = final_columns.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    let mut row_count = 0;
+
+    read_rows_in_batches(&mut source.client, source_query, get_load_settings()?.batch_size, |batch| {
+        if row_count == 0 {
+            check_supported_columns(table_name, &batch[0], &column_names)?;
         }
-    ).collect::<Vec<_>>().join(", ");
-    format!("INSERT INTO {} ({}) VALUES ({})", table_name, columns_str, values_str)
-}
\ No newline at end of file
+        row_count += batch.len();
+
+        insert_batch(transaction, &statement, table_name, final_columns, batch)
+            .map_err(|err| TwoDbError::transfer(table_name, err.as_ref()).into())
+    })?;
+    info!("Rows len: {:?}", row_count);
+    Ok(())
+}
+
+fn insert_batch(
+    transaction: &mut Transaction<'_>,
+    statement: &Statement,
+    table_name: &str,
+    final_columns: &FinalColumns<'_>,
+    rows: &[Row],
+) -> Result<(), Box<dyn Error>> {
+    for row in rows {
+        let values = final_columns.transform.apply(read_row_values(table_name, &final_columns.columns, row)?)?;
+        let params = values.iter().map(|v| v as &(dyn ToSql + Sync)).collect::<Vec<_>>();
+        transaction.execute(statement, &params)?;
+    }
+    Ok(())
+}
+
+/// INSERT with one `$n` placeholder per column, then the expression of each filled column
+///
+/// Source values of identity columns are kept, even for `GENERATED ALWAYS` ones
+fn build_insert_query(table_name: &str, columns: &[&TwoColumn], fills: &[ColumnFill<'_>]) -> String {
+    let columns_str = columns.iter().map(|c| c.name.clone())
+        .chain(fills.iter().map(|fill| fill.column.name.clone()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders_str = (1..=columns.len()).map(|i| format!("${}", i))
+        .chain(fills.iter().map(|fill| fill.expression.clone()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let overriding = if columns.iter().chain(fills.iter().map(|fill| &fill.column)).any(|c| c.is_identity_always) {
+        " OVERRIDING SYSTEM VALUE"
+    } else {
+        ""
+    };
+    format!("INSERT INTO {} ({}){} VALUES ({})", table_name, columns_str, overriding, placeholders_str)
+}
+
+/// INSERT updating the row with the same `conflict_key` instead, if any
+///
+/// `GENERATED ALWAYS` identity columns other than the key and filled columns keep their target value
+fn build_upsert_query(table_name: &str, columns: &[&TwoColumn], fills: &[ColumnFill<'_>], conflict_key: &str) -> String {
+    let updates = columns.iter()
+        .filter(|c| c.name != conflict_key && !c.is_identity_always)
+        .map(|c| format!("{} = EXCLUDED.{}", c.name, c.name))
+        .collect::<Vec<_>>();
+    let action = if updates.is_empty() {
+        String::from("NOTHING")
+    } else {
+        format!("UPDATE SET {}", updates.join(", "))
+    };
+    format!("{} ON CONFLICT ({}) DO {}", build_insert_query(table_name, columns, fills), conflict_key, action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, is_identity_always: bool) -> TwoColumn {
+        TwoColumn { name: name.to_string(), data_type: String::from("integer"), is_identity_always, max_length: None }
+    }
+
+    #[test]
+    fn upsert_updates_every_column_but_the_key() {
+        let (id, name, price) = (column("id", false), column("name", false), column("price", false));
+        assert_eq!(
+            build_upsert_query("item", &[&id, &name, &price], &[], "id"),
+            "INSERT INTO item (id, name, price) VALUES ($1, $2, $3) ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, price = EXCLUDED.price",
+        );
+    }
+
+    #[test]
+    fn upsert_keeps_the_identity_and_filled_columns_of_the_target() {
+        let (id, serial, name, status) = (column("id", false), column("serial", true), column("name", false), column("status", false));
+        let fills = [ColumnFill { column: &status, expression: quote_literal("new"), is_constant: true }];
+        assert_eq!(
+            build_upsert_query("item", &[&id, &serial, &name], &fills, "id"),
+            "INSERT INTO item (id, serial, name, status) OVERRIDING SYSTEM VALUE VALUES ($1, $2, $3, 'new') \
+             ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name",
+        );
+    }
+
+    #[test]
+    fn upsert_of_a_key_alone_does_nothing_on_conflict() {
+        let id = column("id", false);
+        assert_eq!(
+            build_upsert_query("tag", &[&id], &[], "id"),
+            "INSERT INTO tag (id) VALUES ($1) ON CONFLICT (id) DO NOTHING",
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::error::Error;
+use std::io;
+use log::info;
+use postgres::GenericClient;
+use crate::core::action::working_database::{get_cell_value_by_column_name, read_rows_in_batches};
+use crate::core::config::get_load_settings;
+use crate::core::postgresql_queries::query_get_self_references_by_table;
+use crate::core::row_filter::SourceRows;
+use crate::core::transform::RowTransform;
+use crate::domain::mapping_rule::TableMapping;
+use crate::domain::table::SelfReferencingStrategy;
+use crate::domain::two_db_error::TwoDbError;
+
+/// Foreign key of a table pointing to the table itself
+#[derive(Debug, Clone)]
+pub struct SelfReference {
+    /// Column holding the parent key, e.g. `parent_id`
+    pub column: String,
+    /// Column referenced by `column`, e.g. `id`
+    pub referenced_column: String,
+}
+
+impl SelfReference {
+    /// The same reference with the column names of the target, none if a column is not moved
+    pub fn mapped(&self, mapping: &TableMapping) -> Option<SelfReference> {
+        Some(SelfReference {
+            column: mapping.target_column(&self.column)?.to_string(),
+            referenced_column: mapping.target_column(&self.referenced_column)?.to_string(),
+        })
+    }
+}
+
+/// Query the database for the self reference of a table, if any
+pub fn get_self_reference(client: &mut impl GenericClient, database_name: &str, table_name: &str) -> Result<Option<SelfReference>, TwoDbError> {
+    let rows = client.query(
+        &query_get_self_references_by_table(),
+        &[&table_name],
+    ).map_err(|err| TwoDbError::catalog(database_name, err))?;
+
+    Ok(rows.first().map(|row| SelfReference {
+        column: row.get("column_name"),
+        referenced_column: row.get("referenced_column_name"),
+    }))
+}
+
+/// SELECT returning every row of the table, parents before their children
+///
+/// Roots are rows without a parent, rows pointing to themselves,
+/// and rows whose parent is missing. Rows only reachable through a cycle are left out.
+/// `table_name` may also be a parenthesized SELECT, see `SourceRows`.
+pub fn query_parent_first_rows(table_name: &str, self_reference: &SelfReference) -> String {
+    let column = &self_reference.column;
+    let referenced_column = &self_reference.referenced_column;
+    format!("
+        WITH RECURSIVE twodb_tree AS (
+            SELECT t.{referenced_column}, 0 AS twodb_depth
+            FROM {table_name} AS t
+            WHERE t.{column} IS NULL
+                OR t.{column} = t.{referenced_column}
+                OR NOT EXISTS (SELECT 1 FROM {table_name} AS p WHERE p.{referenced_column} = t.{column})
+            UNION ALL
+            SELECT c.{referenced_column}, twodb_tree.twodb_depth + 1
+            FROM {table_name} AS c
+            JOIN twodb_tree ON c.{column} = twodb_tree.{referenced_column}
+            WHERE c.{column} <> c.{referenced_column}
+        )
+        SELECT t.*
+        FROM {table_name} AS t
+        JOIN (
+            SELECT {referenced_column}, MIN(twodb_depth) AS twodb_depth
+            FROM twodb_tree
+            GROUP BY {referenced_column}
+        ) AS d ON d.{referenced_column} = t.{referenced_column}
+        ORDER BY d.twodb_depth")
+}
+
+/// Pick ParentFirst when the recursive walk reaches every row of `relation`, otherwise Backfill
+///
+/// `relation` holds the rows of `table_name` to move, see `SourceRows`
+pub fn choose_strategy(
+    client: &mut impl GenericClient,
+    table_name: &str,
+    relation: &str,
+    self_reference: &SelfReference,
+) -> Result<SelfReferencingStrategy, TwoDbError> {
+
+    let query_all = format!("SELECT COUNT(*) FROM {} AS source_rows", relation);
+    let count_all: i64 = client.query_one(&query_all, &[])
+        .map_err(|err| TwoDbError::transfer(table_name, &err))?
+        .get(0);
+
+    let query_reachable = format!(
+        "SELECT COUNT(*) FROM ({}) AS parent_first_rows",
+        query_parent_first_rows(relation, self_reference)
+    );
+    let count_reachable: i64 = client.query_one(&query_reachable, &[])
+        .map_err(|err| TwoDbError::transfer(table_name, &err))?
+        .get(0);
+
+    info!("Table: {} has {} rows, {} reachable parent first", table_name, count_all, count_reachable);
+    if count_all == count_reachable {
+        Ok(SelfReferencingStrategy::ParentFirst)
+    } else {
+        Ok(SelfReferencingStrategy::Backfill)
+    }
+}
+
+/// Fill the self-referencing column of the target table from the source table
+///
+/// Used after the rows were loaded without that column.
+/// The pairs are staged in a temporary table, then applied with a single UPDATE.
+/// `self_reference` has the column names of the source, `target_self_reference` those of the target.
+/// The pairs are read from the rows moved, see `SourceRows`.
+/// Pairs with transformed keys are inserted one by one, the others are copied.
+pub fn backfill_self_reference(
+    source: &mut SourceRows,
+    target_client: &mut impl GenericClient,
+    mapping: &TableMapping,
+    self_reference: &SelfReference,
+    target_self_reference: &SelfReference,
+    row_transform: &RowTransform,
+) -> Result<u64, Box<dyn Error>> {
+    let source_table_name = &mapping.table_name;
+    let table_name = mapping.target_table();
+    let column = &target_self_reference.column;
+    let referenced_column = &target_self_reference.referenced_column;
+
+    target_client.batch_execute(&format!(
+        "CREATE TEMP TABLE twodb_backfill AS SELECT {referenced_column}, {column} FROM {table_name} WITH NO DATA"
+    ))?;
+
+    let query_pairs = format!(
+        "SELECT {}, {} FROM {} AS source_rows WHERE {} IS NOT NULL",
+        self_reference.referenced_column, self_reference.column, source.relation, self_reference.column,
+    );
+    if row_transform.is_empty() {
+        let mut reader = source.client.copy_out(&format!("COPY ({}) TO STDOUT", query_pairs))?;
+        let mut writer = target_client.copy_in("COPY twodb_backfill FROM STDIN")?;
+        io::copy(&mut reader, &mut writer)?;
+        writer.finish()?;
+    } else {
+        let statement = target_client.prepare("INSERT INTO twodb_backfill VALUES ($1, $2)")?;
+        read_rows_in_batches(&mut source.client, &query_pairs, get_load_settings()?.batch_size, |rows| {
+            for row in rows {
+                let values = row_transform.apply(vec![
+                    get_cell_value_by_column_name(source_table_name, row, &self_reference.referenced_column)?,
+                    get_cell_value_by_column_name(source_table_name, row, &self_reference.column)?,
+                ])?;
+                target_client.execute(&statement, &[&values[0], &values[1]])?;
+            }
+            Ok(())
+        })?;
+    }
+
+    let updated = target_client.execute(&format!(
+        "UPDATE {table_name} AS t
+        SET {column} = b.{column}
+        FROM twodb_backfill AS b
+        WHERE t.{referenced_column} = b.{referenced_column}"
+    ), &[])?;
+    target_client.batch_execute("DROP TABLE twodb_backfill")?;
+
+    info!("Backfilled {} self references of table: {}", updated, table_name);
+    Ok(updated)
+}
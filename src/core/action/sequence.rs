@@ -0,0 +1,63 @@
+use std::error::Error;
+use log::info;
+use postgres::GenericClient;
+use crate::core::postgresql_queries::query_get_owned_sequences_by_table;
+use crate::domain::mapping_rule::{ColumnSource, TableMapping};
+
+/// Sequence owned by a serial or identity column
+#[derive(Debug, Clone)]
+pub struct OwnedSequence {
+    pub column: String,
+    /// Schema qualified, as returned by `pg_get_serial_sequence`
+    pub sequence: String,
+}
+
+pub fn get_owned_sequences(client: &mut impl GenericClient, table_name: &str) -> Result<Vec<OwnedSequence>, Box<dyn Error>> {
+    let rows = client.query(query_get_owned_sequences_by_table(), &[&table_name])?;
+    Ok(rows.iter().map(|row| OwnedSequence {
+        column: row.get("column_name"),
+        sequence: row.get("sequence_name"),
+    }).collect())
+}
+
+/// Last value handed out by the source sequence of the same column, if it was ever used
+fn get_source_last_value(source_client: &mut impl GenericClient, table_name: &str, column_name: &str) -> Option<i64> {
+    let sequence: Option<String> = source_client
+        .query_one("SELECT pg_get_serial_sequence($1, $2)", &[&table_name, &column_name])
+        .ok()?
+        .get(0);
+    let row = source_client
+        .query_one(&format!("SELECT last_value, is_called FROM {}", sequence?), &[])
+        .ok()?;
+    let is_called: bool = row.get("is_called");
+    is_called.then(|| row.get("last_value"))
+}
+
+/// Move every sequence owned by the target table past the ids already in use
+///
+/// The next value follows the highest of `MAX(column)` in the target
+/// and the last value of the source sequence. Sequences of empty tables are left alone.
+pub fn resync_sequences(
+    source_client: &mut impl GenericClient,
+    target_client: &mut impl GenericClient,
+    mapping: &TableMapping,
+) -> Result<(), Box<dyn Error>> {
+    let table_name = mapping.target_table();
+
+    for owned_sequence in get_owned_sequences(target_client, table_name)? {
+        let query_max = format!("SELECT MAX({})::bigint FROM {}", owned_sequence.column, table_name);
+        let target_max: Option<i64> = target_client.query_one(&query_max, &[])?.get(0);
+        let source_last_value = match mapping.column_source(&owned_sequence.column) {
+            Some(ColumnSource::Column(source_column)) => get_source_last_value(source_client, &mapping.table_name, &source_column),
+            _ => None,
+        };
+
+        let value = match target_max.into_iter().chain(source_last_value).max() {
+            Some(value) => value,
+            None => continue,
+        };
+        target_client.query_one("SELECT setval($1::text::regclass, $2, true)", &[&owned_sequence.sequence, &value])?;
+        info!("Sequence: {} of table: {} set to {}", owned_sequence.sequence, table_name, value);
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::error::Error;
+use log::info;
+use postgres::{Client, GenericClient, Transaction};
+use serde::Deserialize;
+use crate::core::action::copy::CopyFormat;
+use crate::core::config::LoadSettings;
+use crate::core::postgresql_queries::query_get_user_triggers_by_table;
+use crate::domain::two_db_error::TwoDbError;
+
+/// How triggers of the target table are kept quiet while it is loaded
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TriggerMode {
+    /// Triggers fire on every row
+    #[default]
+    Keep,
+    /// `SET LOCAL session_replication_role = replica`, requires a superuser.
+    /// Foreign keys are not checked either.
+    Replica,
+    /// `ALTER TABLE ... DISABLE TRIGGER` on each enabled user trigger
+    DisableUser,
+}
+
+impl TriggerMode {
+    pub fn name(&self) -> &str {
+        match self {
+            TriggerMode::Keep => "KEEP",
+            TriggerMode::Replica => "REPLICA",
+            TriggerMode::DisableUser => "DISABLE_USER",
+        }
+    }
+
+    /// Mode named `keep`, `replica` or `disable_user`, as in the `[load]` section
+    pub fn from_name(name: &str) -> Result<TriggerMode, TwoDbError> {
+        match name.to_lowercase().as_str() {
+            "keep" => Ok(TriggerMode::Keep),
+            "replica" => Ok(TriggerMode::Replica),
+            "disable_user" => Ok(TriggerMode::DisableUser),
+            other => Err(TwoDbError::Configuration(format!("Unknown trigger mode: {}", other))),
+        }
+    }
+}
+
+impl TryFrom<String> for TriggerMode {
+    type Error = TwoDbError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        TriggerMode::from_name(&name)
+    }
+}
+
+/// A user trigger and how it fired before the load
+#[derive(Debug, Clone)]
+pub struct SuppressedTrigger {
+    pub name: String,
+    /// `pg_trigger.tgenabled`: O, R or A
+    pub enabled: String,
+}
+
+impl SuppressedTrigger {
+    /// ALTER TABLE action putting the trigger back as it was
+    fn enable_action(&self) -> String {
+        match self.enabled.as_str() {
+            "A" => format!("ENABLE ALWAYS TRIGGER {}", self.name),
+            "R" => format!("ENABLE REPLICA TRIGGER {}", self.name),
+            _ => format!("ENABLE TRIGGER {}", self.name),
+        }
+    }
+}
+
+/// Settings applied to every transaction loading one table
+#[derive(Debug)]
+pub struct LoadSession {
+    pub table_name: String,
+    pub trigger_mode: TriggerMode,
+    /// User triggers that do not fire during the load
+    pub suppressed_triggers: Vec<SuppressedTrigger>,
+    /// `SET CONSTRAINTS ALL DEFERRED`
+    pub defer_constraints: bool,
+    /// Format of the rows copied into the table
+    pub copy_format: CopyFormat,
+}
+
+impl LoadSession {
+    /// Find the triggers suppressed by the load settings
+    pub fn prepare(target_client: &mut impl GenericClient, table_name: &str, settings: &LoadSettings) -> Result<LoadSession, Box<dyn Error>> {
+        let trigger_mode = settings.trigger_mode;
+        let defer_constraints = settings.defer_constraints;
+
+        let suppressed_triggers = match trigger_mode {
+            TriggerMode::Keep => Vec::new(),
+            _ => {
+                let rows = target_client.query(query_get_user_triggers_by_table(), &[&table_name])?;
+                rows.iter()
+                    .map(|row| SuppressedTrigger { name: row.get("trigger_name"), enabled: row.get("enabled") })
+                    .filter(|trigger| match trigger_mode {
+                        // Only triggers firing in origin mode are skipped by replica mode
+                        TriggerMode::Replica => trigger.enabled == "O",
+                        _ => trigger.enabled != "D",
+                    })
+                    .collect()
+            }
+        };
+        info!("Table: {} trigger mode: {}, suppressed triggers: {:?}", table_name, trigger_mode.name(), suppressed_triggers);
+
+        Ok(LoadSession {
+            table_name: table_name.to_string(),
+            trigger_mode,
+            suppressed_triggers,
+            defer_constraints,
+            copy_format: settings.copy_format,
+        })
+    }
+
+    /// Start a transaction with triggers suppressed and constraints deferred
+    ///
+    /// Everything is undone by a rollback, as DDL is transactional in PostgreSQL.
+    pub fn begin<'a>(&self, target_client: &'a mut Client) -> Result<Transaction<'a>, Box<dyn Error>> {
+        let mut transaction = target_client.transaction()?;
+        match self.trigger_mode {
+            TriggerMode::Keep => {}
+            TriggerMode::Replica => transaction.batch_execute("SET LOCAL session_replication_role = replica")?,
+            TriggerMode::DisableUser => {
+                for trigger in &self.suppressed_triggers {
+                    transaction.batch_execute(&format!("ALTER TABLE {} DISABLE TRIGGER {}", self.table_name, trigger.name))?;
+                }
+            }
+        }
+        if self.defer_constraints {
+            transaction.batch_execute("SET CONSTRAINTS ALL DEFERRED")?;
+        }
+        Ok(transaction)
+    }
+
+    /// Put the suppressed triggers back as they were, then commit
+    ///
+    /// The session role and deferred constraints end with the transaction.
+    pub fn commit(&self, mut transaction: Transaction<'_>) -> Result<(), Box<dyn Error>> {
+        if self.trigger_mode == TriggerMode::DisableUser {
+            for trigger in &self.suppressed_triggers {
+                transaction.batch_execute(&format!("ALTER TABLE {} {}", self.table_name, trigger.enable_action()))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
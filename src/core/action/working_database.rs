@@ -1,153 +1,103 @@
-use std::env::var;
-use std::time::SystemTime;
-use chrono::NaiveDate;
+use std::error::Error;
 use log::{error, info};
-use postgres::error::SqlState;
-use postgres::{Column, Row};
-use crate::core::action::TWODB_NULL;
-use crate::core::database::pg_connect;
+use postgres::{Client, Column, Row};
+use postgres::types::{FromSql, Type};
+use crate::core::row_filter::SourceRows;
+use crate::domain::two_db_error::TwoDbError;
+use crate::domain::two_value::TwoValue;
 
-pub fn get_rows(database_name: &String, table_name: &String) -> Vec<Row> {
-    // TODO: Check if table empty (no records)
-
-    let mut pg_client = pg_connect(database_name).unwrap();
-    let query = "SELECT * FROM ".to_string() + table_name.as_str();
-    let rows = match pg_client.query(&query, &[]) {
-        Ok(rows) => rows,
-        Err(err) => {
-            if let Some(db_err) = err.as_db_error() {
-                if db_err.code() == &SqlState::from_code("42P01") {
-                    info!("Table: {} does not exist in the database {}", table_name, database_name);
-                    return Vec::new();
-                }
-            }
-            panic!("Error querying database {}: {:?}", database_name, err);
+/// Read the rows of `query` through a server-side cursor, `batch_size` rows at a time
+///
+/// Only one batch is held in memory, whatever the size of the table.
+/// Returning an error from `on_batch` stops the read.
+pub fn read_rows_in_batches<F>(pg_client: &mut Client, query: &str, batch_size: i32, mut on_batch: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&[Row]) -> Result<(), Box<dyn Error>>,
+{
+    // A portal only lives inside a transaction
+    let mut transaction = pg_client.transaction()?;
+    let portal = transaction.bind(query, &[])?;
+    loop {
+        let rows = transaction.query_portal(&portal, batch_size)?;
+        if rows.is_empty() {
+            break;
         }
-    };
-    rows
-}
-
-/// Get cells from a row based on the column type
-pub fn get_cells(row: &Row) -> Vec<String> {
-    let columns = row.columns();
-    let cells: Vec<String> = columns.iter().map(|column| {
-        let name = column.name();
-        let type_ = column.type_();
-        match type_.name() {
-            "int8" => {
-                let value: Option<i64> = row.try_get(name).unwrap_or(None);
-                format!("{}: {}", name, value.unwrap_or(0))
-            }
-            "int4" => {
-                let value: Option<i32> = row.try_get(name).unwrap_or(None);
-                format!("{}: {}", name, value.unwrap_or(0))
-            }
-            "varchar" => {
-                let value: Option<&str> = row.try_get(name).unwrap_or(None);
-                format!("{}: {}", name, value.unwrap_or("None"))
-            }
-            "bool" => {
-                let value: Option<bool> = row.try_get(name).unwrap_or(None);
-                format!("{}: {}", name, value.unwrap_or(false))
-            }
-            "timestamp" => {
-                let value: Option<SystemTime> = row.try_get(name).unwrap_or(None);
-                format!("{}: {}", name, value.unwrap_or(SystemTime::UNIX_EPOCH)
-                    .duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs())
-            }
-            "date" => {
-                let value: Option<NaiveDate> = row.try_get(name).unwrap_or(None);
-                format!("{}: {}", name, value.unwrap_or(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()))
-            }
-            _ => {
-                error!("Unknown type: {:?}", type_.name());
-                format!("{}: {}", name, "Unknown")
-            }
+        on_batch(&rows)?;
+        if rows.len() < batch_size as usize {
+            break;
         }
-    }).collect();
-    cells
+    }
+    transaction.commit()?;
+    Ok(())
 }
 
-pub fn get_cell_value_by_column_name(table_name: &String, row: &Row, column_name: String) -> String {
+/// Read a cell as a typed value, NULL included
+///
+/// Fail on types that cannot be carried to the target database
+pub fn get_cell_value_by_column_name(table_name: &str, row: &Row, column_name: &str) -> Result<TwoValue, TwoDbError> {
     let columns: &[Column] = row.columns();
-    let column = columns.iter().find(|column| column.name() == column_name.clone()).unwrap();
-    let type_ = column.type_();
-
-    match type_.name() {
-        "int8" => {
-            let value: Option<i64> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or(0).to_string()
-        }
-        "int4" => {
-            let value: Option<i32> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or(0).to_string()
-        }
-        "varchar" => {
-            let value: Option<&str> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or("None").to_string()
-        }
-        "bool" => {
-            let value: Option<bool> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or(false).to_string()
-        }
-        "timestamp" => {
-            let value: Option<SystemTime> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            use chrono::prelude::{DateTime, Utc};
-            let dt: DateTime<Utc> = value.clone().unwrap().into();
-            dt.to_rfc3339()
-        }
+    let column = columns.iter().find(|column| column.name() == column_name)
+        .ok_or_else(|| TwoDbError::Transfer {
+            table: table_name.to_string(),
+            message: format!("Column: {} not found", column_name),
+        })?;
 
-        // This datatype is not supported by crate postgres
-        "numeric" => {
-            // Code chữa cháy, code này chạy cực lâu
-            let column_name = column.name();
-            let row_id: i64 = row.try_get("id").unwrap_or(0);
-            let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-            let mut pg_client = pg_connect(&source_database_name).unwrap();
-            let query = format!("SELECT {}::text FROM {} WHERE id = {}", column_name, table_name, row_id);
-            let rows = pg_client.query(&query, &[]).unwrap();
-            let row = rows.get(0).unwrap();
-            let value: Option<&str> = row.try_get(0).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or("0.0").to_string()
+    row.try_get::<_, TwoValue>(column_name).map_err(|err| {
+        error!("get_cell_value_by_column_name - Unknown type: {:?}", column.type_().name());
+        TwoDbError::Transfer {
+            table: table_name.to_string(),
+            message: format!("Error reading column: {} \n Error: {:?}", column_name, err),
         }
+    })
+}
 
-        "text" => {
-            let value: Option<&str> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or("None").to_string()
-        }
-        "date" => {
-            let value: Option<NaiveDate> = row.try_get(column.name()).unwrap_or(None);
-            if value.is_none() {
-                return TWODB_NULL.to_string();
-            }
-            value.unwrap_or(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).to_string()
-        }
-        _ => {
-            error!("get_cell_value_by_column_name - Unknown type: {:?}", type_.name());
-            "Unknown".to_string()
-        }
+/// Check that every column in `column_names` can be read as a TwoValue
+///
+/// Returns the unsupported columns with their type, e.g. `shape (polygon)`
+pub fn check_supported_columns(table_name: &str, row: &Row, column_names: &[&str]) -> Result<(), TwoDbError> {
+    let unsupported_columns = row.columns().iter()
+        .filter(|column| column_names.contains(&column.name()))
+        .filter(|column| !is_supported_type(column.type_()))
+        .map(|column| format!("{} ({})", column.name(), column.type_().name()))
+        .collect::<Vec<_>>();
+    if unsupported_columns.is_empty() {
+        return Ok(());
     }
+    Err(TwoDbError::Transfer {
+        table: table_name.to_string(),
+        message: format!("Columns of unsupported types: {}", unsupported_columns.join(", ")),
+    })
 }
 
-pub fn different_row_count() {}
\ No newline at end of file
+fn is_supported_type(type_: &Type) -> bool {
+    <TwoValue as FromSql>::accepts(type_)
+}
+
+/// Count the rows of `relation`, a table or a parenthesized SELECT of the rows of `table_name`
+fn count_relation_rows(pg_client: &mut Client, database_name: &str, table_name: &str, relation: &str) -> Result<i64, TwoDbError> {
+    let query = format!("SELECT COUNT(*) FROM {} AS counted_rows", relation);
+    pg_client.query_one(&query, &[])
+        .and_then(|row| row.try_get(0))
+        .map_err(|err| TwoDbError::Transfer {
+            table: table_name.to_string(),
+            message: format!("Error counting rows in database: {} \n Error: {:?}", database_name, err),
+        })
+}
+
+/// Check whether a table has a different number of rows in the source and the target database
+///
+/// Only the rows of the source kept by its row filters are counted.
+/// `target_table_name` is its name in the target, see `TableMapping::target_table`
+pub fn different_row_count(
+    source: &mut SourceRows,
+    target_client: &mut Client,
+    target_database_name: &str,
+    target_table_name: &str,
+) -> Result<bool, TwoDbError> {
+    let source_count = count_relation_rows(&mut source.client, &source.database_name, &source.table_name, &source.relation)?;
+    let target_count = count_relation_rows(target_client, target_database_name, target_table_name, target_table_name)?;
+    if source_count != target_count {
+        info!("Table: {} has {} rows in source and {} rows in target", source.table_name, source_count, target_count);
+    }
+    Ok(source_count != target_count)
+}
\ No newline at end of file
@@ -0,0 +1,535 @@
+/*! This file contains the connection profiles of the source and target databases.
+
+Profiles are read from the TOML file named by `TWODB_CONFIG`, `twodb.toml` by default:
+
+```toml
+source = "legacy"
+target = "new"
+
+[profiles.legacy]
+host = "10.0.0.5"
+port = 5432
+user = "postgres"
+password = "secret"
+dbname = "erp"
+
+[profiles.new]
+host = "db.example.com"
+user = "app"
+passfile = "~/.pgpass"
+dbname = "erp"
+sslmode = "verify-full"
+sslrootcert = "certs/root.crt"
+options = "-c statement_timeout=0"
+
+[profiles.new.pool]
+max_size = 4
+idle_timeout = 60
+
+[load]
+trigger_mode = "disable_user"
+defer_constraints = true
+copy_format = "binary"
+create_missing_tables = "full"
+batch_size = 5000
+```
+
+The TLS settings are described in the `tls` module, the pool settings in `PoolSettings`,
+the load settings in `LoadSettings`. The command line of `twodb-cli` may override the latter.
+Databases are named by their profile everywhere else, in the knowledge store too.
+Without a config file, the `POSTGRES_*` variables give one profile per database, named after it.
+*/
+
+use std::collections::HashMap;
+use std::env::var;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use postgres::Config;
+use postgres_native_tls::MakeTlsConnector;
+use r2d2::Pool;
+use r2d2_postgres::PostgresConnectionManager;
+use serde::Deserialize;
+use crate::core::action::copy::CopyFormat;
+use crate::core::action::create_table::CreateTableMode;
+use crate::core::action::trigger::TriggerMode;
+use crate::core::tls::{fall_back_to_plaintext, make_tls_connector, TlsMode};
+use crate::domain::two_db_error::TwoDbError;
+
+const CONFIG_FILE_PATH: &str = "twodb.toml";
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: u16 = 5432;
+
+/// Rows fetched per round trip when none is configured
+const DEFAULT_BATCH_SIZE: i32 = 1000;
+
+pub type PgPool = Pool<PostgresConnectionManager<MakeTlsConnector>>;
+
+fn default_host() -> String {
+    String::from(DEFAULT_HOST)
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+/// Connections kept open to one database, durations are in seconds
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PoolSettings {
+    /// Connections open at most
+    pub max_size: u32,
+    /// Idle connections kept open, the others are opened when needed
+    pub min_idle: u32,
+    /// Close connections idle for longer, 0 to keep them
+    pub idle_timeout: u64,
+    /// Give up waiting for a free connection after this delay
+    pub connection_timeout: u64,
+    /// Check that a connection is alive before handing it out
+    pub test_on_check_out: bool,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        PoolSettings {
+            max_size: 8,
+            min_idle: 0,
+            idle_timeout: 300,
+            connection_timeout: 30,
+            test_on_check_out: true,
+        }
+    }
+}
+
+impl PoolSettings {
+    /// Reject the settings the pool cannot be built with
+    pub fn validate(&self, profile_name: &str) -> Result<(), TwoDbError> {
+        let problem = if self.max_size == 0 {
+            "max_size must be at least 1"
+        } else if self.connection_timeout == 0 {
+            "connection_timeout must be at least 1 second"
+        } else if self.min_idle > self.max_size {
+            "min_idle must not be greater than max_size"
+        } else {
+            return Ok(());
+        };
+        Err(TwoDbError::Configuration(format!("Invalid pool of profile {}: {}", profile_name, problem)))
+    }
+}
+
+/// How rows are written to the target, unknown values are rejected when the file is read
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoadSettings {
+    /// `keep`, `replica` or `disable_user`, see `TriggerMode`
+    pub trigger_mode: TriggerMode,
+    /// `SET CONSTRAINTS ALL DEFERRED` in every transaction loading a table
+    pub defer_constraints: bool,
+    /// `text` or `binary`, see `CopyFormat`
+    pub copy_format: CopyFormat,
+    /// `skip`, `table` or `full`, see `CreateTableMode`
+    pub create_missing_tables: CreateTableMode,
+    /// Source rows fetched per round trip, only one batch is held in memory
+    pub batch_size: i32,
+}
+
+impl Default for LoadSettings {
+    fn default() -> Self {
+        LoadSettings {
+            trigger_mode: TriggerMode::default(),
+            defer_constraints: false,
+            copy_format: CopyFormat::default(),
+            create_missing_tables: CreateTableMode::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+impl LoadSettings {
+    /// Reject the settings rows cannot be loaded with
+    pub fn validate(&self) -> Result<(), TwoDbError> {
+        if self.batch_size < 1 {
+            return Err(TwoDbError::Configuration(String::from("Invalid load settings: batch_size must be at least 1")));
+        }
+        Ok(())
+    }
+}
+
+/// Load settings given on the command line, they win over the `[load]` section
+#[derive(Debug, Clone, Default)]
+pub struct LoadOverrides {
+    pub trigger_mode: Option<TriggerMode>,
+    pub defer_constraints: Option<bool>,
+    pub copy_format: Option<CopyFormat>,
+    pub create_missing_tables: Option<CreateTableMode>,
+    pub batch_size: Option<i32>,
+}
+
+impl LoadOverrides {
+    fn apply(&self, settings: &mut LoadSettings) {
+        if let Some(trigger_mode) = self.trigger_mode {
+            settings.trigger_mode = trigger_mode;
+        }
+        if let Some(defer_constraints) = self.defer_constraints {
+            settings.defer_constraints = defer_constraints;
+        }
+        if let Some(copy_format) = self.copy_format {
+            settings.copy_format = copy_format;
+        }
+        if let Some(create_missing_tables) = self.create_missing_tables {
+            settings.create_missing_tables = create_missing_tables;
+        }
+        if let Some(batch_size) = self.batch_size {
+            settings.batch_size = batch_size;
+        }
+    }
+}
+
+static LOAD_OVERRIDES: OnceLock<LoadOverrides> = OnceLock::new();
+
+/// Override the load settings of every config read from now on, only the first call counts
+pub fn set_load_overrides(overrides: LoadOverrides) {
+    let _ = LOAD_OVERRIDES.set(overrides);
+}
+
+/// How to reach one database
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectionProfile {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub user: String,
+    pub password: Option<String>,
+    /// Looked up when there is no password, in the format of `.pgpass`
+    pub passfile: Option<String>,
+    pub dbname: String,
+    /// `disable`, `prefer`, `require`, `verify-ca` or `verify-full`, default is `prefer`
+    pub sslmode: Option<String>,
+    /// Trusted root certificates, PEM
+    pub sslrootcert: Option<String>,
+    /// Client certificate, PEM
+    pub sslcert: Option<String>,
+    /// Key of the client certificate, PEM in PKCS#8
+    pub sslkey: Option<String>,
+    /// Command-line options sent to the server, e.g. `-c search_path=erp`
+    pub options: Option<String>,
+    #[serde(default)]
+    pub pool: PoolSettings,
+}
+
+impl ConnectionProfile {
+    /// Settings to connect with, the password is read from the passfile if needed
+    pub fn pg_config(&self) -> Result<Config, TwoDbError> {
+        if self.host.is_empty() {
+            return Err(TwoDbError::Configuration(format!("No host for database {}", self.dbname)));
+        }
+        let mut config = Config::new();
+        config.host(&self.host);
+        config.port(self.port);
+        config.user(&self.user);
+        config.dbname(&self.dbname);
+        config.ssl_mode(self.tls_mode()?.ssl_mode());
+        if let Some(password) = self.get_password()? {
+            config.password(password);
+        }
+        if let Some(options) = &self.options {
+            config.options(options);
+        }
+        Ok(config)
+    }
+
+    pub fn tls_mode(&self) -> Result<TlsMode, TwoDbError> {
+        TlsMode::from_name(self.sslmode.as_deref().unwrap_or("prefer"))
+    }
+
+    /// Pool of connections to the database of the profile, `database_name` is only used in errors
+    ///
+    /// No connection is opened before the first one is needed, unless `min_idle` asks for it.
+    pub fn pool(&self, database_name: &str) -> Result<PgPool, TwoDbError> {
+        self.pool.validate(database_name)?;
+        let mut pg_config = self.pg_config()?;
+        let tls = make_tls_connector(self)?;
+        fall_back_to_plaintext(self.tls_mode()?, &mut pg_config, &tls, database_name);
+        let manager = PostgresConnectionManager::new(pg_config, tls);
+        let idle_timeout = match self.pool.idle_timeout {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        };
+        Pool::builder()
+            .max_size(self.pool.max_size)
+            .min_idle(Some(self.pool.min_idle))
+            .idle_timeout(idle_timeout)
+            .connection_timeout(Duration::from_secs(self.pool.connection_timeout))
+            .test_on_check_out(self.pool.test_on_check_out)
+            .build(manager)
+            .map_err(|err| TwoDbError::connection(database_name, err))
+    }
+
+    fn get_password(&self) -> Result<Option<String>, TwoDbError> {
+        if self.password.is_some() {
+            return Ok(self.password.clone());
+        }
+        let passfile = match &self.passfile {
+            Some(passfile) => passfile,
+            None => return Ok(None),
+        };
+        let path = expand_home(passfile);
+        let content = fs::read_to_string(&path)
+            .map_err(|err| TwoDbError::Configuration(format!("Cannot read passfile {}: {}", path.display(), err)))?;
+        Ok(content.lines().find_map(|line| self.match_passfile_line(line)))
+    }
+
+    /// Password of a `hostname:port:database:username:password` line matching the profile
+    fn match_passfile_line(&self, line: &str) -> Option<String> {
+        if line.starts_with('#') {
+            return None;
+        }
+        let fields = split_passfile_line(line);
+        let [host, port, dbname, user, password] = fields.as_slice() else {
+            return None;
+        };
+        let port_str = self.port.to_string();
+        let matches = [(host, &self.host), (port, &port_str), (dbname, &self.dbname), (user, &self.user)]
+            .iter()
+            .all(|(field, value)| field.as_ref().map_or(true, |field| field == *value));
+        matches.then(|| password.clone().unwrap_or(String::from("*")))
+    }
+}
+
+/// Path with a leading `~/` replaced by the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => Path::new(&var("HOME").unwrap_or(String::from(""))).join(rest),
+        None => Path::new(path).to_path_buf(),
+    }
+}
+
+/// Split on `:`, `\:` and `\\` are escaped characters
+///
+/// A field that is a bare `*` matches anything and is none, `\*` is a literal star.
+fn split_passfile_line(line: &str) -> Vec<Option<String>> {
+    let mut fields = vec![(String::new(), false)];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        let (field, is_escaped) = fields.last_mut().unwrap();
+        match c {
+            '\\' => {
+                field.extend(chars.next());
+                *is_escaped = true;
+            }
+            ':' => fields.push((String::new(), false)),
+            _ => field.push(c),
+        }
+    }
+    fields.into_iter()
+        .map(|(field, is_escaped)| (is_escaped || field != "*").then_some(field))
+        .collect()
+}
+
+/// Connection profiles and which of them are migrated from and to
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectionConfig {
+    /// Profile name of the source database
+    pub source: String,
+    /// Profile name of the target database
+    pub target: String,
+    #[serde(default)]
+    pub profiles: HashMap<String, ConnectionProfile>,
+    #[serde(default)]
+    pub load: LoadSettings,
+}
+
+impl ConnectionConfig {
+    /// Read the config file, or the `POSTGRES_*` variables when there is none,
+    /// with the load settings given on the command line
+    pub fn load() -> Result<ConnectionConfig, TwoDbError> {
+        let mut config = ConnectionConfig::read()?;
+        if let Some(overrides) = LOAD_OVERRIDES.get() {
+            overrides.apply(&mut config.load);
+        }
+        config.load.validate()?;
+        Ok(config)
+    }
+
+    fn read() -> Result<ConnectionConfig, TwoDbError> {
+        let path = var("TWODB_CONFIG").unwrap_or(String::from(CONFIG_FILE_PATH));
+        if !Path::new(&path).exists() {
+            return Ok(ConnectionConfig::from_env());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|err| TwoDbError::Configuration(format!("Cannot read {}: {}", path, err)))?;
+        let config = ConnectionConfig::from_toml(&content)
+            .map_err(|err| TwoDbError::Configuration(format!("Invalid {}: {}", path, err)))?;
+        config.profile(&config.source)?;
+        config.profile(&config.target)?;
+        for (name, profile) in &config.profiles {
+            profile.pool.validate(name)?;
+        }
+        Ok(config)
+    }
+
+    pub fn from_toml(content: &str) -> Result<ConnectionConfig, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// One profile for `POSTGRES_DB_SOURCE` and one for `POSTGRES_DB_TARGET`, on the same server
+    pub fn from_env() -> ConnectionConfig {
+        let source = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
+        let target = var("POSTGRES_DB_TARGET").unwrap_or(String::from(""));
+        // POSTGRES_HOST used to be given as host:port
+        let (host, port) = split_host_port(&var("POSTGRES_HOST").unwrap_or(String::from("")));
+
+        let profiles = [&source, &target].into_iter().map(|dbname| {
+            (dbname.clone(), ConnectionProfile {
+                host: host.clone(),
+                port,
+                user: var("POSTGRES_USER").unwrap_or(String::from("")),
+                password: var("POSTGRES_PASSWORD").ok(),
+                passfile: None,
+                dbname: dbname.clone(),
+                sslmode: None,
+                sslrootcert: None,
+                sslcert: None,
+                sslkey: None,
+                options: None,
+                pool: PoolSettings::default(),
+            })
+        }).collect();
+
+        ConnectionConfig { source, target, profiles, load: LoadSettings::default() }
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&ConnectionProfile, TwoDbError> {
+        self.profiles.get(name)
+            .ok_or_else(|| TwoDbError::Configuration(format!("No connection profile named: {}", name)))
+    }
+}
+
+/// `host`, `host:port` or `[address]:port`, an IPv6 address alone is a host
+fn split_host_port(value: &str) -> (String, u16) {
+    let parse_port = |port: &str| port.parse().unwrap_or(DEFAULT_PORT);
+    if let Some((host, rest)) = value.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        return (host.to_string(), rest.strip_prefix(':').map_or(DEFAULT_PORT, parse_port));
+    }
+    match value.split_once(':') {
+        Some((host, port)) if !port.contains(':') => (host.to_string(), parse_port(port)),
+        _ => (value.to_string(), DEFAULT_PORT),
+    }
+}
+
+/// Name of the source database, i.e. of its profile
+pub fn get_source_database_name() -> Result<String, TwoDbError> {
+    ConnectionConfig::load().map(|config| config.source)
+}
+
+/// Name of the target database, i.e. of its profile
+pub fn get_target_database_name() -> Result<String, TwoDbError> {
+    ConnectionConfig::load().map(|config| config.target)
+}
+
+/// How rows are written to the target
+pub fn get_load_settings() -> Result<LoadSettings, TwoDbError> {
+    ConnectionConfig::load().map(|config| config.load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = "source = \"a\"\ntarget = \"b\"\n[profiles.a]\ndbname = \"a\"\n[profiles.b]\ndbname = \"b\"\n";
+
+    #[test]
+    fn load_section_is_optional_and_read_when_given() {
+        let config = ConnectionConfig::from_toml(PROFILES).unwrap();
+        assert_eq!(config.load.trigger_mode, TriggerMode::Keep);
+        assert!(!config.load.defer_constraints);
+
+        let content = format!("{}[load]\ntrigger_mode = \"DISABLE_USER\"\ndefer_constraints = true\ncopy_format = \"binary\"\ncreate_missing_tables = \"table\"\n", PROFILES);
+        let config = ConnectionConfig::from_toml(&content).unwrap();
+        assert_eq!(config.load.trigger_mode, TriggerMode::DisableUser);
+        assert!(config.load.defer_constraints);
+        assert_eq!(config.load.copy_format, CopyFormat::Binary);
+        assert_eq!(config.load.create_missing_tables, CreateTableMode::Table);
+        assert_eq!(config.load.batch_size, 1000);
+    }
+
+    #[test]
+    fn unknown_load_values_are_rejected() {
+        let content = format!("{}[load]\ntrigger_mode = \"off\"\n", PROFILES);
+        let err = ConnectionConfig::from_toml(&content).unwrap_err();
+        assert!(err.to_string().contains("Unknown trigger mode: off"), "{}", err);
+        let content = format!("{}[load]\ncopy_format = \"csv\"\n", PROFILES);
+        let err = ConnectionConfig::from_toml(&content).unwrap_err();
+        assert!(err.to_string().contains("Unknown copy format: csv"), "{}", err);
+        let content = format!("{}[load]\ncreate_missing_tables = true\n", PROFILES);
+        assert!(ConnectionConfig::from_toml(&content).is_err());
+        let content = format!("{}[load]\nbatch_size = 0\n", PROFILES);
+        let config = ConnectionConfig::from_toml(&content).unwrap();
+        assert!(matches!(config.load.validate(), Err(TwoDbError::Configuration(_))));
+    }
+
+    #[test]
+    fn overrides_win_over_the_load_section() {
+        let mut settings = LoadSettings { trigger_mode: TriggerMode::Replica, defer_constraints: true, ..Default::default() };
+        let overrides = LoadOverrides { trigger_mode: Some(TriggerMode::Keep), ..Default::default() };
+        overrides.apply(&mut settings);
+        assert_eq!(settings.trigger_mode, TriggerMode::Keep);
+        assert!(settings.defer_constraints);
+    }
+
+    #[test]
+    fn splits_the_port_off_the_host() {
+        assert_eq!(split_host_port("db.example.com"), (String::from("db.example.com"), 5432));
+        assert_eq!(split_host_port("db.example.com:5433"), (String::from("db.example.com"), 5433));
+        assert_eq!(split_host_port("10.0.0.5:"), (String::from("10.0.0.5"), 5432));
+        assert_eq!(split_host_port("[::1]:6543"), (String::from("::1"), 6543));
+        assert_eq!(split_host_port("[::1]"), (String::from("::1"), 5432));
+        assert_eq!(split_host_port("fe80::1"), (String::from("fe80::1"), 5432));
+        assert_eq!(split_host_port(""), (String::new(), 5432));
+    }
+
+    fn profile() -> ConnectionProfile {
+        toml::from_str("host = \"db.example.com\"\nport = 5433\nuser = \"app\"\ndbname = \"erp\"").unwrap()
+    }
+
+    #[test]
+    fn matches_passfile_lines_with_wildcards() {
+        let profile = profile();
+        assert_eq!(profile.match_passfile_line("db.example.com:5433:erp:app:secret"), Some(String::from("secret")));
+        assert_eq!(profile.match_passfile_line("*:*:*:*:any"), Some(String::from("any")));
+        assert_eq!(profile.match_passfile_line("db.example.com:*:erp:*:some"), Some(String::from("some")));
+        assert_eq!(profile.match_passfile_line("db.example.com:5432:erp:app:other port"), None);
+        assert_eq!(profile.match_passfile_line("db.example.com:5433:erp:admin:other user"), None);
+        assert_eq!(profile.match_passfile_line("#*:*:*:*:commented"), None);
+        assert_eq!(profile.match_passfile_line("*:*:*:missing password"), None);
+    }
+
+    #[test]
+    fn unescapes_passfile_fields() {
+        let field = |value: &str| Some(String::from(value));
+        assert_eq!(split_passfile_line(r"a\:b:c\\d:e"), vec![field("a:b"), field(r"c\d"), field("e")]);
+        assert_eq!(split_passfile_line(r"*:\*:x*:pa\:ss\\word"), vec![None, field("*"), field("x*"), field(r"pa:ss\word")]);
+        let profile = profile();
+        assert_eq!(profile.match_passfile_line(r"*:*:*:*:se\:cr\\et"), Some(String::from(r"se:cr\et")));
+        assert_eq!(profile.match_passfile_line("*:*:*:*:*"), Some(String::from("*")));
+        // An escaped star is a user named `*`, not a wildcard
+        assert_eq!(profile.match_passfile_line(r"*:*:*:\*:star"), None);
+    }
+
+    #[test]
+    fn rejects_pools_that_cannot_be_built() {
+        assert!(PoolSettings::default().validate("erp").is_ok());
+        let invalid = [
+            PoolSettings { max_size: 0, ..Default::default() },
+            PoolSettings { connection_timeout: 0, ..Default::default() },
+            PoolSettings { min_idle: 9, max_size: 8, ..Default::default() },
+        ];
+        for settings in invalid {
+            assert!(matches!(settings.validate("erp"), Err(TwoDbError::Configuration(_))), "{:?}", settings);
+        }
+    }
+}
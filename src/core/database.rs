@@ -1,23 +1,48 @@
-use std::env::var;
-use postgres::{Client, Error, NoTls};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use postgres_native_tls::MakeTlsConnector;
+use r2d2::PooledConnection;
+use r2d2_postgres::PostgresConnectionManager;
+use crate::core::config::{ConnectionConfig, ConnectionProfile, PgPool};
+use crate::domain::two_db_error::TwoDbError;
+
+/// Connection borrowed from the pool of its database, given back when dropped
+pub type PgClient = PooledConnection<PostgresConnectionManager<MakeTlsConnector>>;
+
+/// One pool per connection profile, shared by the whole application
+static POOLS: OnceLock<Mutex<HashMap<String, PgPool>>> = OnceLock::new();
 
 /// Connect to the PostgreSQL database
 ///
-/// Return a postgres::client::Client object
+/// `database_name` is the name of its connection profile
+///
+/// Return a client of the pool of the database, it derefs to a postgres::client::Client
 ///
 /// Author : Ta Quang Khoi
-pub fn pg_connect(database_name: &String) -> rusqlite::Result<Client, Error> {
-    // TODO: Use one client for the whole application
-    let username = var("POSTGRES_USER").unwrap_or(String::from(""));
-    let password = var("POSTGRES_PASSWORD").unwrap_or(String::from(""));
-    let host = var("POSTGRES_HOST").unwrap_or(String::from(""));
+pub fn pg_connect(database_name: &str) -> Result<PgClient, TwoDbError> {
+    pg_connect_with(database_name, || ConnectionConfig::load()?.profile(database_name).cloned())
+}
 
-    let database_url = format!("postgresql://{}:{}@{}/{}", username, password, host, database_name);
-
-    let client = Client::connect(
-        database_url.as_str(),
-        NoTls,
-    )?;
+/// Borrow a client of the pool of the database
+///
+/// The pool is created on first use, from the profile returned by `get_profile`
+pub fn pg_connect_with(
+    database_name: &str,
+    get_profile: impl FnOnce() -> Result<ConnectionProfile, TwoDbError>,
+) -> Result<PgClient, TwoDbError> {
+    let known = lock_pools().get(database_name).cloned();
+    let pool = match known {
+        Some(pool) => pool,
+        None => {
+            // Built without the lock, with min_idle it waits for the first connections
+            let pool = get_profile()?.pool(database_name)?;
+            lock_pools().entry(database_name.to_string()).or_insert(pool).clone()
+        }
+    };
+    pool.get().map_err(|err| TwoDbError::connection(database_name, err))
+}
 
-    Ok(client)
-}
\ No newline at end of file
+/// The pools stay usable even if a thread panicked while holding the lock
+fn lock_pools() -> MutexGuard<'static, HashMap<String, PgPool>> {
+    POOLS.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner)
+}
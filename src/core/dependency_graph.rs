@@ -0,0 +1,233 @@
+/*! This file contains the foreign-key dependency graph of a database. */
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use log::{info, warn};
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::core::database::pg_connect;
+use crate::core::postgresql_queries::{query_get_base_tables, query_get_foreign_keys};
+use crate::domain::two_db_error::TwoDbError;
+
+/// Tables of a database and the tables each of them references
+///
+/// Self references are left out, they are handled when the table itself is moved.
+pub struct DependencyGraph {
+    /// table name -> referenced table names
+    parents: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self {
+            parents: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_table(&mut self, table_name: &str) {
+        self.parents.entry(table_name.to_string()).or_default();
+    }
+
+    /// `table_name` has a foreign key to `referenced_table_name`
+    pub fn add_reference(&mut self, table_name: &str, referenced_table_name: &str) {
+        self.add_table(referenced_table_name);
+        if table_name == referenced_table_name {
+            self.add_table(table_name);
+            return;
+        }
+        self.parents.entry(table_name.to_string()).or_default().insert(referenced_table_name.to_string());
+    }
+
+    /// Read all base tables and foreign keys of the `public` schema
+    pub fn from_database(database_name: &str) -> Result<DependencyGraph, TwoDbError> {
+        let mut client = pg_connect(database_name)?;
+        let mut graph = DependencyGraph::new();
+
+        let tables = client.query(query_get_base_tables(), &[])
+            .map_err(|err| TwoDbError::catalog(database_name, err))?;
+        for row in tables {
+            let table_name: String = row.get("table_name");
+            graph.add_table(&table_name);
+        }
+
+        let foreign_keys = client.query(query_get_foreign_keys(), &[])
+            .map_err(|err| TwoDbError::catalog(database_name, err))?;
+        for row in foreign_keys {
+            let table_name: String = row.get("table_name");
+            let referenced_table_name: String = row.get("referenced_table_name");
+            graph.add_reference(&table_name, &referenced_table_name);
+        }
+
+        Ok(graph)
+    }
+
+    /// Tarjan's algorithm
+    ///
+    /// Because edges go from a table to the tables it references,
+    /// components come out parents first, which is the order to load them in.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<String>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: 0,
+            indexes: HashMap::new(),
+            low_links: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            components: Vec::new(),
+        };
+        for table_name in self.parents.keys() {
+            if !tarjan.indexes.contains_key(table_name.as_str()) {
+                tarjan.visit(table_name);
+            }
+        }
+        tarjan.components
+    }
+
+    /// Components of more than one table, i.e. foreign-key cycles
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect()
+    }
+
+    /// Export order of each table, starting from 1
+    ///
+    /// Tables of the same cycle share one export order.
+    pub fn export_orders(&self) -> Vec<(String, i64)> {
+        let mut orders = Vec::new();
+        for (i, component) in self.strongly_connected_components().into_iter().enumerate() {
+            for table_name in component {
+                orders.push((table_name, i as i64 + 1));
+            }
+        }
+        orders
+    }
+}
+
+impl Default for DependencyGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Tarjan<'a> {
+    graph: &'a DependencyGraph,
+    index: usize,
+    indexes: HashMap<&'a str, usize>,
+    low_links: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: BTreeSet<&'a str>,
+    components: Vec<Vec<String>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, table_name: &'a str) {
+        self.indexes.insert(table_name, self.index);
+        self.low_links.insert(table_name, self.index);
+        self.index += 1;
+        self.stack.push(table_name);
+        self.on_stack.insert(table_name);
+
+        let graph = self.graph;
+        for parent in &graph.parents[table_name] {
+            let parent = parent.as_str();
+            if !self.indexes.contains_key(parent) {
+                self.visit(parent);
+                let low_link = self.low_links[table_name].min(self.low_links[parent]);
+                self.low_links.insert(table_name, low_link);
+            } else if self.on_stack.contains(parent) {
+                let low_link = self.low_links[table_name].min(self.indexes[parent]);
+                self.low_links.insert(table_name, low_link);
+            }
+        }
+
+        if self.low_links[table_name] == self.indexes[table_name] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member.to_string());
+                if member == table_name {
+                    break;
+                }
+            }
+            component.sort();
+            self.components.push(component);
+        }
+    }
+}
+
+/// Compute the export order of every table of the database and save it to SQLite
+///
+/// Return the foreign-key cycles found
+pub fn update_export_order(database_name: &str) -> Result<Vec<Vec<String>>, TwoDbError> {
+    let graph = DependencyGraph::from_database(database_name)?;
+
+    SqliteKnowledgeGateway::default().update_export_orders(database_name, &graph.export_orders())?;
+
+    let cycles = graph.cycles();
+    for cycle in &cycles {
+        warn!("Foreign key cycle in {}: {}", database_name, cycle.join(", "));
+    }
+    info!("Export order updated for {}, {} cycles found", database_name, cycles.len());
+    Ok(cycles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a graph from `(table, referenced table)` pairs
+    fn graph(references: &[(&str, &str)]) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for (table_name, referenced_table_name) in references {
+            graph.add_reference(table_name, referenced_table_name);
+        }
+        graph
+    }
+
+    fn order_of(orders: &[(String, i64)], table_name: &str) -> i64 {
+        orders.iter().find(|(name, _)| name == table_name).map(|(_, order)| *order).unwrap()
+    }
+
+    #[test]
+    fn chain_is_ordered_parents_first() {
+        let graph = graph(&[("order_line", "orders"), ("orders", "customer")]);
+        assert_eq!(
+            graph.strongly_connected_components(),
+            vec![vec!["customer"], vec!["orders"], vec!["order_line"]]
+        );
+        assert!(graph.cycles().is_empty());
+        let orders = graph.export_orders();
+        assert_eq!(order_of(&orders, "customer"), 1);
+        assert_eq!(order_of(&orders, "orders"), 2);
+        assert_eq!(order_of(&orders, "order_line"), 3);
+    }
+
+    #[test]
+    fn diamond_loads_the_shared_parent_once_and_first() {
+        let graph = graph(&[("left", "root"), ("right", "root"), ("bottom", "left"), ("bottom", "right")]);
+        let orders = graph.export_orders();
+        assert_eq!(orders.len(), 4);
+        assert_eq!(order_of(&orders, "root"), 1);
+        assert!(order_of(&orders, "left") < order_of(&orders, "bottom"));
+        assert!(order_of(&orders, "right") < order_of(&orders, "bottom"));
+        assert_ne!(order_of(&orders, "left"), order_of(&orders, "right"));
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn self_loop_is_not_a_cycle() {
+        let graph = graph(&[("employee", "employee"), ("employee", "department")]);
+        assert_eq!(graph.strongly_connected_components(), vec![vec!["department"], vec!["employee"]]);
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn two_table_cycle_shares_one_export_order() {
+        let graph = graph(&[("a", "b"), ("b", "a"), ("a", "parent"), ("child", "b")]);
+        assert_eq!(graph.cycles(), vec![vec!["a", "b"]]);
+        let orders = graph.export_orders();
+        assert_eq!(order_of(&orders, "a"), order_of(&orders, "b"));
+        assert!(order_of(&orders, "parent") < order_of(&orders, "a"));
+        assert!(order_of(&orders, "a") < order_of(&orders, "child"));
+    }
+}
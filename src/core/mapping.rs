@@ -0,0 +1,56 @@
+/*! This file contains the mapping rules between the source and target databases.
+
+Rules are set by the user per source table and kept in the `mapping_rules` table of the
+SQLite database. A table without rules is moved to the table of the same name, column by
+column of the same name.
+*/
+
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::application::repositories::knowledge_repository::KnowledgeRepository;
+use crate::domain::mapping_rule::{MappingRule, MappingRuleKind, TableMapping};
+use crate::domain::two_db_error::TwoDbError;
+
+/// Rules of a table of the source database
+pub fn get_table_mapping(database_name: &str, table_name: &str) -> Result<TableMapping, TwoDbError> {
+    let rules = SqliteKnowledgeGateway::default().get_mapping_rules(database_name, table_name)?;
+    Ok(TableMapping::new(table_name, rules))
+}
+
+/// Rules of every table of the source database
+pub fn get_mapping_rules(database_name: &str) -> Result<Vec<MappingRule>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_mapping_rules_of_database(database_name)
+}
+
+/// Save a rule once the names it needs are set
+///
+/// A table is renamed once, a column once, and a target column is filled by one rule at most
+pub fn add_mapping_rule(rule: &MappingRule) -> Result<(), TwoDbError> {
+    let needs_column = matches!(rule.kind, MappingRuleKind::RenameColumn | MappingRuleKind::Exclude);
+    let needs_target = rule.kind != MappingRuleKind::Exclude;
+    let missing = if rule.database.is_empty() || rule.table_name.is_empty() {
+        Some("source table")
+    } else if needs_column && rule.column_name.is_empty() {
+        Some("source column")
+    } else if needs_target && rule.target_name.is_empty() {
+        Some(if rule.kind == MappingRuleKind::RenameTable { "target table" } else { "target column" })
+    } else if rule.kind == MappingRuleKind::Default && rule.value.is_empty() {
+        Some("SQL expression")
+    } else {
+        None
+    };
+    if let Some(missing) = missing {
+        return Err(TwoDbError::Configuration(format!("A {} rule needs a {}", rule.kind.name(), missing)));
+    }
+    let mapping = get_table_mapping(&rule.database, &rule.table_name)?;
+    if let Some(conflict) = mapping.conflicting_rule(rule) {
+        return Err(TwoDbError::Configuration(format!(
+            "Table {} already has a {} rule to {}, remove rule {} first",
+            rule.table_name, conflict.kind.name(), conflict.target_name, conflict.id
+        )));
+    }
+    SqliteKnowledgeGateway::default().insert_mapping_rule(rule)
+}
+
+pub fn delete_mapping_rule(id: i64) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().delete_mapping_rule(id)
+}
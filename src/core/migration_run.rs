@@ -0,0 +1,72 @@
+/*! This file contains the bookkeeping of migration runs, table checkpoints and suppressed triggers in SQLite. */
+
+use log::info;
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::core::action::trigger::LoadSession;
+use crate::domain::migration_run::{MigrationRun, TableCheckpoint};
+use crate::domain::two_db_error::TwoDbError;
+
+/// Get the run left unfinished between these databases, if any
+pub fn get_running_migration_run(source_database_name: &str, target_database_name: &str) -> Result<Option<i64>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_running_migration_run(source_database_name, target_database_name)
+}
+
+/// Get the latest run between these databases, finished or not
+pub fn get_last_migration_run(source_database_name: &str, target_database_name: &str) -> Result<Option<MigrationRun>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_last_migration_run(source_database_name, target_database_name)
+}
+
+/// Resume the unfinished run between these databases, or start a new one
+///
+/// Return the id of the run
+pub fn start_migration_run(source_database_name: &str, target_database_name: &str) -> Result<i64, TwoDbError> {
+    if let Some(run_id) = get_running_migration_run(source_database_name, target_database_name)? {
+        info!("Resume migration run: {}", run_id);
+        return Ok(run_id);
+    }
+
+    let run_id = SqliteKnowledgeGateway::default().insert_migration_run(source_database_name, target_database_name)?;
+    info!("Start migration run: {}", run_id);
+    Ok(run_id)
+}
+
+pub fn finish_migration_run(run_id: i64) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().finish_migration_run(run_id)
+}
+
+/// Last primary key value committed to the target for a table, as text
+pub fn get_checkpoint(table_name: &str, database_name: &str) -> Result<Option<String>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_checkpoint(table_name, database_name)
+}
+
+/// Checkpoints of the tables of a database left halfway
+pub fn get_checkpoints(database_name: &str) -> Result<Vec<TableCheckpoint>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_checkpoints(database_name)
+}
+
+/// Record that every row up to `last_primary_key` has been committed to the target
+///
+/// `run_id` is empty when a single table is moved outside of a run
+pub fn save_checkpoint(run_id: Option<i64>, table_name: &str, database_name: &str, primary_key_column: &str, last_primary_key: &str) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().save_checkpoint(run_id, table_name, database_name, primary_key_column, last_primary_key)
+}
+
+/// Forget the checkpoint of a table once it is fully exported
+pub fn delete_checkpoint(table_name: &str, database_name: &str) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().delete_checkpoint(table_name, database_name)
+}
+
+/// Keep track of the triggers that did not fire while a table was loaded into `database_name`
+pub fn record_suppressed_triggers(run_id: Option<i64>, database_name: &str, load_session: &LoadSession) -> Result<(), TwoDbError> {
+    let gateway = SqliteKnowledgeGateway::default();
+    for trigger in &load_session.suppressed_triggers {
+        gateway.insert_suppressed_trigger(
+            run_id,
+            &load_session.table_name,
+            database_name,
+            &trigger.name,
+            load_session.trigger_mode.name(),
+        )?;
+    }
+    Ok(())
+}
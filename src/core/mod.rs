@@ -2,10 +2,22 @@
 
 pub mod get_knowledge;
 pub mod table;
-mod sqlite_queries;
+pub(crate) mod sqlite_queries;
 pub(crate) mod reset_knowledge;
+pub mod config;
 pub mod database;
 pub mod postgresql_queries;
 pub mod action;
+pub mod dependency_graph;
+pub mod pg_types;
+pub mod pg_errors;
+pub mod migration_run;
+pub mod tls;
+pub mod schema_diff;
+pub mod schema_ddl;
+pub mod mapping;
+pub mod transform;
+pub mod row_filter;
+pub mod table_sync;
 
 pub const SQLITE_DATABASE_PATH: &str = "twodb.db";
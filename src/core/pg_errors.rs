@@ -0,0 +1,80 @@
+/*! This file contains the conversions of PostgreSQL and SQLite errors into TwoDbError. */
+
+use std::error::Error;
+use postgres::error::{DbError, SqlState};
+use crate::domain::two_db_error::TwoDbError;
+
+impl From<rusqlite::Error> for TwoDbError {
+    fn from(err: rusqlite::Error) -> Self {
+        TwoDbError::Knowledge(err.to_string())
+    }
+}
+
+impl TwoDbError {
+    pub fn connection(database_name: &str, err: impl ToString) -> TwoDbError {
+        TwoDbError::Connection {
+            database: database_name.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn catalog(database_name: &str, err: impl ToString) -> TwoDbError {
+        TwoDbError::Catalog {
+            database: database_name.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn schema_change(database_name: &str, statement: &str, err: impl ToString) -> TwoDbError {
+        TwoDbError::SchemaChange {
+            database: database_name.to_string(),
+            statement: statement.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    /// Classify an error raised while moving `table_name`
+    ///
+    /// A TwoDbError is kept as is, integrity errors of the server
+    /// become a ConstraintViolation, anything else is a Transfer error.
+    pub fn transfer(table_name: &str, err: &(dyn Error + 'static)) -> TwoDbError {
+        if let Some(err) = err.downcast_ref::<TwoDbError>() {
+            return err.clone();
+        }
+        let db_error = err.downcast_ref::<postgres::Error>().and_then(|err| err.as_db_error());
+        if let Some(violation) = db_error.and_then(constraint_violation) {
+            return violation;
+        }
+        TwoDbError::Transfer {
+            table: table_name.to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Parse an integrity error (class 23) of the server
+///
+/// The key comes from the detail, e.g. "Key (document_id)=(55) is not present in table \"document\"."
+fn constraint_violation(db_error: &DbError) -> Option<TwoDbError> {
+    if !db_error.code().code().starts_with("23") {
+        return None;
+    }
+    let detail = db_error.detail().unwrap_or("");
+    let key = detail.strip_prefix("Key ")
+        .and_then(|rest| rest.split_once(" is ").or_else(|| rest.split_once(" already ")))
+        .map(|(key, _)| key.to_string())
+        .unwrap_or_default();
+    let referenced_table = if db_error.code() == &SqlState::FOREIGN_KEY_VIOLATION {
+        detail.split(' ').last().map(|table| table.replace('"', "").trim_end_matches('.').to_string())
+    } else {
+        None
+    };
+
+    Some(TwoDbError::ConstraintViolation {
+        table: db_error.table().unwrap_or("").to_string(),
+        constraint: db_error.constraint().unwrap_or("").to_string(),
+        key,
+        referenced_table,
+        message: db_error.message().to_string(),
+    })
+}
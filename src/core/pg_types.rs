@@ -0,0 +1,581 @@
+/*! This file contains the PostgreSQL wire conversions of TwoValue.
+
+A value is written into the type of the target column, which may differ from the type it was read
+from when the schemas drifted apart. Numbers are widened, or narrowed if they fit, into any other
+number type, dates and timestamps into the other timestamp types, text into json.
+*/
+
+use std::error::Error;
+use std::fmt::Display;
+use bytes::{Buf, BufMut, BytesMut};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use postgres::types::{to_sql_checked, FromSql, IsNull, Kind, ToSql, Type};
+use postgres_protocol::types as protocol;
+use crate::domain::two_value::TwoValue;
+
+impl<'a> FromSql<'a> for TwoValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        match ty.kind() {
+            Kind::Domain(inner) => return TwoValue::from_sql(inner, raw),
+            Kind::Enum(_) => return Ok(TwoValue::Enum(protocol::text_from_sql(raw)?.to_string())),
+            Kind::Array(member) => return array_from_sql(member, raw),
+            _ => {}
+        }
+        match *ty {
+            Type::BOOL => Ok(TwoValue::Bool(bool::from_sql(ty, raw)?)),
+            Type::INT2 => Ok(TwoValue::Int2(i16::from_sql(ty, raw)?)),
+            Type::INT4 => Ok(TwoValue::Int4(i32::from_sql(ty, raw)?)),
+            Type::INT8 => Ok(TwoValue::Int8(i64::from_sql(ty, raw)?)),
+            Type::FLOAT4 => Ok(TwoValue::Float4(f32::from_sql(ty, raw)?)),
+            Type::FLOAT8 => Ok(TwoValue::Float8(f64::from_sql(ty, raw)?)),
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => Ok(TwoValue::Text(String::from_sql(ty, raw)?)),
+            Type::NUMERIC => Ok(TwoValue::Numeric(numeric_from_sql(raw)?)),
+            Type::MONEY => Ok(TwoValue::Money(protocol::int8_from_sql(raw)?)),
+            Type::DATE => Ok(TwoValue::Date(NaiveDate::from_sql(ty, raw)?)),
+            Type::TIME => Ok(TwoValue::Time(NaiveTime::from_sql(ty, raw)?)),
+            Type::TIMETZ => {
+                let mut buf = raw;
+                if buf.len() != 12 {
+                    return Err("Invalid timetz length".into());
+                }
+                let microseconds = buf.get_i64();
+                let zone = buf.get_i32();
+                Ok(TwoValue::TimeTz { time: time_from_microseconds(microseconds)?, zone })
+            }
+            Type::TIMESTAMP => Ok(TwoValue::Timestamp(NaiveDateTime::from_sql(ty, raw)?)),
+            Type::TIMESTAMPTZ => Ok(TwoValue::TimestampTz(DateTime::<Utc>::from_sql(ty, raw)?)),
+            Type::INTERVAL => {
+                let mut buf = raw;
+                if buf.len() != 16 {
+                    return Err("Invalid interval length".into());
+                }
+                let microseconds = buf.get_i64();
+                let days = buf.get_i32();
+                let months = buf.get_i32();
+                Ok(TwoValue::Interval { months, days, microseconds })
+            }
+            Type::UUID => Ok(TwoValue::Uuid(protocol::uuid_from_sql(raw)?)),
+            Type::JSON => Ok(TwoValue::Json(protocol::text_from_sql(raw)?.to_string())),
+            Type::JSONB => match raw.split_first() {
+                Some((1, json)) => Ok(TwoValue::Json(protocol::text_from_sql(json)?.to_string())),
+                _ => Err("Unsupported jsonb version".into()),
+            },
+            Type::BYTEA => Ok(TwoValue::Bytea(protocol::bytea_from_sql(raw).to_vec())),
+            Type::INET | Type::CIDR => {
+                let inet = protocol::inet_from_sql(raw)?;
+                Ok(TwoValue::Inet { addr: inet.addr(), netmask: inet.netmask() })
+            }
+            Type::MACADDR => Ok(TwoValue::MacAddr(protocol::macaddr_from_sql(raw)?)),
+            _ => Err(format!("Unsupported type: {}", ty.name()).into()),
+        }
+    }
+
+    fn from_sql_null(_: &Type) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(TwoValue::Null)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        match ty.kind() {
+            Kind::Domain(inner) => <TwoValue as FromSql>::accepts(inner),
+            Kind::Enum(_) => true,
+            Kind::Array(member) => <TwoValue as FromSql>::accepts(member),
+            _ => matches!(
+                *ty,
+                Type::BOOL | Type::INT2 | Type::INT4 | Type::INT8 | Type::FLOAT4 | Type::FLOAT8
+                    | Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::NUMERIC | Type::MONEY
+                    | Type::DATE | Type::TIME | Type::TIMETZ | Type::TIMESTAMP | Type::TIMESTAMPTZ
+                    | Type::INTERVAL | Type::UUID | Type::JSON | Type::JSONB | Type::BYTEA
+                    | Type::INET | Type::CIDR | Type::MACADDR
+            ),
+        }
+    }
+}
+
+impl ToSql for TwoValue {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        if let Kind::Domain(inner) = ty.kind() {
+            return self.to_sql(inner, out);
+        }
+        match self {
+            TwoValue::Null => Ok(IsNull::Yes),
+            TwoValue::Bool(value) => value.to_sql_checked(ty, out),
+            TwoValue::Int2(value) => integer_to_sql(i64::from(*value), ty, out),
+            TwoValue::Int4(value) => integer_to_sql(i64::from(*value), ty, out),
+            TwoValue::Int8(value) => integer_to_sql(*value, ty, out),
+            TwoValue::Float4(value) if *ty == Type::FLOAT4 => value.to_sql(ty, out),
+            TwoValue::Float4(value) => float_to_sql(f64::from(*value), value, ty, out),
+            TwoValue::Float8(value) => float_to_sql(*value, value, ty, out),
+            // A text column may be loaded into an enum column
+            TwoValue::Text(value) | TwoValue::Enum(value) if matches!(ty.kind(), Kind::Enum(_)) => {
+                protocol::text_to_sql(value, out);
+                Ok(IsNull::No)
+            }
+            TwoValue::Text(value) if matches!(*ty, Type::JSON | Type::JSONB) => json_to_sql(value, ty, out),
+            TwoValue::Text(value) | TwoValue::Enum(value) => value.to_sql_checked(ty, out),
+            TwoValue::Numeric(value) => numeric_value_to_sql(value, ty, out),
+            TwoValue::Money(value) => {
+                expect_type(ty, &[Type::MONEY], "money")?;
+                protocol::int8_to_sql(*value, out);
+                Ok(IsNull::No)
+            }
+            TwoValue::Date(value) if *ty == Type::TIMESTAMP => value.and_time(NaiveTime::MIN).to_sql(ty, out),
+            TwoValue::Date(value) if *ty == Type::TIMESTAMPTZ => value.and_time(NaiveTime::MIN).and_utc().to_sql(ty, out),
+            TwoValue::Date(value) => value.to_sql_checked(ty, out),
+            TwoValue::Time(value) => value.to_sql_checked(ty, out),
+            TwoValue::TimeTz { time, zone } => {
+                expect_type(ty, &[Type::TIMETZ], "timetz")?;
+                let microseconds = (*time - NaiveTime::MIN).num_microseconds().ok_or("Invalid timetz value")?;
+                out.put_i64(microseconds);
+                out.put_i32(*zone);
+                Ok(IsNull::No)
+            }
+            // As PostgreSQL casts them with the UTC time zone
+            TwoValue::Timestamp(value) if *ty == Type::TIMESTAMPTZ => value.and_utc().to_sql(ty, out),
+            TwoValue::Timestamp(value) => value.to_sql_checked(ty, out),
+            TwoValue::TimestampTz(value) if *ty == Type::TIMESTAMP => value.naive_utc().to_sql(ty, out),
+            TwoValue::TimestampTz(value) => value.to_sql_checked(ty, out),
+            TwoValue::Interval { months, days, microseconds } => {
+                expect_type(ty, &[Type::INTERVAL], "interval")?;
+                out.put_i64(*microseconds);
+                out.put_i32(*days);
+                out.put_i32(*months);
+                Ok(IsNull::No)
+            }
+            TwoValue::Uuid(value) => {
+                expect_type(ty, &[Type::UUID], "uuid")?;
+                protocol::uuid_to_sql(*value, out);
+                Ok(IsNull::No)
+            }
+            TwoValue::Json(value) => {
+                expect_type(ty, &[Type::JSON, Type::JSONB], "json")?;
+                json_to_sql(value, ty, out)
+            }
+            TwoValue::Bytea(value) => value.to_sql_checked(ty, out),
+            TwoValue::Inet { addr, netmask } => {
+                expect_type(ty, &[Type::INET, Type::CIDR], "inet")?;
+                protocol::inet_to_sql(*addr, *netmask, out);
+                Ok(IsNull::No)
+            }
+            TwoValue::MacAddr(value) => {
+                expect_type(ty, &[Type::MACADDR], "macaddr")?;
+                protocol::macaddr_to_sql(*value, out);
+                Ok(IsNull::No)
+            }
+            TwoValue::Array(values) => {
+                let member = match ty.kind() {
+                    Kind::Array(member) => member,
+                    _ => return Err(format!("Cannot write array into {}", ty.name()).into()),
+                };
+                let dimension = protocol::ArrayDimension { len: i32::try_from(values.len())?, lower_bound: 1 };
+                protocol::array_to_sql(
+                    Some(dimension).filter(|_| !values.is_empty()),
+                    member.oid(),
+                    values,
+                    |value, out| match value.to_sql(member, out)? {
+                        IsNull::Yes => Ok(postgres_protocol::IsNull::Yes),
+                        IsNull::No => Ok(postgres_protocol::IsNull::No),
+                    },
+                    out,
+                )?;
+                Ok(IsNull::No)
+            }
+        }
+    }
+
+    /// The variant decides, see `to_sql`
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+fn expect_type(ty: &Type, expected: &[Type], value_name: &str) -> Result<(), Box<dyn Error + Sync + Send>> {
+    if expected.contains(ty) {
+        return Ok(());
+    }
+    Err(format!("Cannot write {} into {}", value_name, ty.name()).into())
+}
+
+/// An integer into any number type, a value out of the range of a narrower integer is an error
+fn integer_to_sql(value: i64, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    let out_of_range = || format!("Integer {} is out of range for {}", value, ty.name());
+    match *ty {
+        Type::INT2 => i16::try_from(value).map_err(|_| out_of_range())?.to_sql(ty, out),
+        Type::INT4 => i32::try_from(value).map_err(|_| out_of_range())?.to_sql(ty, out),
+        Type::INT8 => value.to_sql(ty, out),
+        Type::FLOAT4 => (value as f32).to_sql(ty, out),
+        Type::FLOAT8 => (value as f64).to_sql(ty, out),
+        Type::NUMERIC => {
+            numeric_to_sql(&value.to_string(), out)?;
+            Ok(IsNull::No)
+        }
+        _ => Err(format!("Cannot write integer into {}", ty.name()).into()),
+    }
+}
+
+/// A float into a float or numeric column, `decimal` is its shortest decimal text
+fn float_to_sql(value: f64, decimal: &dyn Display, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    match *ty {
+        Type::FLOAT4 if value.is_finite() && !(value as f32).is_finite() => {
+            Err(format!("Float {} is out of range for float4", value).into())
+        }
+        Type::FLOAT4 => (value as f32).to_sql(ty, out),
+        Type::FLOAT8 => value.to_sql(ty, out),
+        Type::NUMERIC => {
+            let text = if value.is_nan() {
+                String::from("NaN")
+            } else if value.is_infinite() {
+                String::from(if value > 0.0 { "Infinity" } else { "-Infinity" })
+            } else {
+                // Display never uses an exponent
+                decimal.to_string()
+            };
+            numeric_to_sql(&text, out)?;
+            Ok(IsNull::No)
+        }
+        _ => Err(format!("Cannot write float into {}", ty.name()).into()),
+    }
+}
+
+/// The decimal text of a numeric into any number type, a fraction is not rounded into an integer
+fn numeric_value_to_sql(text: &str, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    let invalid = || format!("Cannot write numeric {} into {}", text, ty.name());
+    match *ty {
+        Type::NUMERIC => {
+            numeric_to_sql(text, out)?;
+            Ok(IsNull::No)
+        }
+        Type::FLOAT4 => text.parse::<f32>().map_err(|_| invalid())?.to_sql(ty, out),
+        Type::FLOAT8 => text.parse::<f64>().map_err(|_| invalid())?.to_sql(ty, out),
+        Type::INT2 | Type::INT4 | Type::INT8 => {
+            let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+            if !fraction.chars().all(|c| c == '0') {
+                return Err(invalid().into());
+            }
+            integer_to_sql(integer.parse().map_err(|_| invalid())?, ty, out)
+        }
+        _ => Err(invalid().into()),
+    }
+}
+
+fn json_to_sql(text: &str, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    if *ty == Type::JSONB {
+        out.put_u8(1);
+    }
+    protocol::text_to_sql(text, out);
+    Ok(IsNull::No)
+}
+
+/// Time of day from microseconds since midnight, `24:00:00` is not supported
+fn time_from_microseconds(microseconds: i64) -> Result<NaiveTime, Box<dyn Error + Sync + Send>> {
+    let seconds = u32::try_from(microseconds.div_euclid(1_000_000))?;
+    let nanoseconds = microseconds.rem_euclid(1_000_000) as u32 * 1_000;
+    NaiveTime::from_num_seconds_from_midnight_opt(seconds, nanoseconds)
+        .ok_or_else(|| format!("Invalid time: {} microseconds", microseconds).into())
+}
+
+/// Decode a one-dimensional array, NULL elements included
+fn array_from_sql(member: &Type, raw: &[u8]) -> Result<TwoValue, Box<dyn Error + Sync + Send>> {
+    let mut buf = raw;
+    if buf.len() < 12 {
+        return Err("Invalid array header".into());
+    }
+    let dimensions = buf.get_i32();
+    let _has_nulls = buf.get_i32();
+    let _element_type = buf.get_u32();
+    if dimensions == 0 {
+        return Ok(TwoValue::Array(Vec::new()));
+    }
+    if dimensions != 1 {
+        return Err(format!("Unsupported array of {} dimensions", dimensions).into());
+    }
+    if buf.len() < 8 {
+        return Err("Invalid array dimension".into());
+    }
+    let len = buf.get_i32();
+    let _lower_bound = buf.get_i32();
+
+    let mut values = Vec::with_capacity(len.max(0) as usize);
+    for _ in 0..len {
+        if buf.len() < 4 {
+            return Err("Invalid array element".into());
+        }
+        let element_len = buf.get_i32();
+        if element_len < 0 {
+            values.push(TwoValue::Null);
+            continue;
+        }
+        let element_len = element_len as usize;
+        if buf.len() < element_len {
+            return Err("Invalid array element length".into());
+        }
+        values.push(TwoValue::from_sql(member, &buf[..element_len])?);
+        buf.advance(element_len);
+    }
+    Ok(TwoValue::Array(values))
+}
+
+const NUMERIC_POS: u16 = 0x0000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+const NUMERIC_PINF: u16 = 0xD000;
+const NUMERIC_NINF: u16 = 0xF000;
+
+/// Encode the decimal text of a numeric into the binary NUMERIC format
+///
+/// The value is a list of base-10000 digits, the weight of the first one,
+/// a sign and the number of decimal digits after the point.
+pub fn numeric_to_sql(text: &str, out: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let text = text.trim();
+    let special_sign = match text {
+        "NaN" => Some(NUMERIC_NAN),
+        "Infinity" => Some(NUMERIC_PINF),
+        "-Infinity" => Some(NUMERIC_NINF),
+        _ => None,
+    };
+    if let Some(sign) = special_sign {
+        out.put_i16(0);
+        out.put_i16(0);
+        out.put_u16(sign);
+        out.put_u16(0);
+        return Ok(());
+    }
+
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (NUMERIC_NEG, rest),
+        None => (NUMERIC_POS, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    if integer.is_empty() && fraction.is_empty()
+        || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid numeric: {}", text).into());
+    }
+    let scale = fraction.len() as u16;
+
+    // Pad both parts to whole groups of 4 decimal digits around the point
+    let integer_padding = (4 - integer.len() % 4) % 4;
+    let fraction_padding = (4 - fraction.len() % 4) % 4;
+    let padded = "0".repeat(integer_padding) + integer + fraction + &"0".repeat(fraction_padding);
+    let mut digits: Vec<i16> = padded.as_bytes()
+        .chunks(4)
+        .map(|chunk| chunk.iter().fold(0i16, |acc, d| acc * 10 + (d - b'0') as i16))
+        .collect();
+    let mut weight = ((integer.len() + integer_padding) / 4) as i16 - 1;
+
+    while digits.first() == Some(&0) {
+        digits.remove(0);
+        weight -= 1;
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    let sign = if digits.is_empty() { NUMERIC_POS } else { sign };
+    if digits.is_empty() {
+        weight = 0;
+    }
+
+    out.put_i16(digits.len() as i16);
+    out.put_i16(weight);
+    out.put_u16(sign);
+    out.put_u16(scale);
+    for digit in digits {
+        out.put_i16(digit);
+    }
+    Ok(())
+}
+
+/// Decode the binary NUMERIC format into its exact decimal text
+///
+/// Digits after the point are kept up to the display scale, so `1.50` stays `1.50`.
+pub fn numeric_from_sql(raw: &[u8]) -> Result<String, Box<dyn Error + Sync + Send>> {
+    let mut buf = raw;
+    if buf.len() < 8 {
+        return Err("Invalid numeric header".into());
+    }
+    let digit_count = buf.get_i16();
+    let weight = buf.get_i16() as i32;
+    let sign = buf.get_u16();
+    let scale = buf.get_u16() as usize;
+    if digit_count < 0 || buf.len() != digit_count as usize * 2 {
+        return Err("Invalid numeric length".into());
+    }
+    let digits = (0..digit_count).map(|_| buf.get_i16()).collect::<Vec<_>>();
+
+    match sign {
+        NUMERIC_NAN => return Ok(String::from("NaN")),
+        NUMERIC_PINF => return Ok(String::from("Infinity")),
+        NUMERIC_NINF => return Ok(String::from("-Infinity")),
+        NUMERIC_POS | NUMERIC_NEG => {}
+        _ => return Err(format!("Invalid numeric sign: {:#x}", sign).into()),
+    }
+
+    // Base-10000 digit whose power is `power`, zero outside the stored digits
+    let digit_at = |power: i32| -> i16 {
+        usize::try_from(weight - power).ok()
+            .and_then(|index| digits.get(index).copied())
+            .unwrap_or(0)
+    };
+
+    let mut text = String::new();
+    if sign == NUMERIC_NEG {
+        text.push('-');
+    }
+    if weight < 0 {
+        text.push('0');
+    } else {
+        text.push_str(&digit_at(weight).to_string());
+        for power in (0..weight).rev() {
+            text.push_str(&format!("{:04}", digit_at(power)));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut power = -1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit_at(power)));
+            power -= 1;
+        }
+        fraction.truncate(scale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(text: &str) -> BytesMut {
+        let mut out = BytesMut::new();
+        numeric_to_sql(text, &mut out).unwrap();
+        out
+    }
+
+    fn round_trip(text: &str) -> String {
+        numeric_from_sql(&encode(text)).unwrap()
+    }
+
+    /// digit count, weight, sign, scale then the base-10000 digits
+    fn header_and_digits(text: &str) -> Vec<u16> {
+        let mut buf = &encode(text)[..];
+        let mut words = Vec::new();
+        while buf.has_remaining() {
+            words.push(buf.get_u16());
+        }
+        words
+    }
+
+    #[test]
+    fn round_trips_zero() {
+        assert_eq!(round_trip("0"), "0");
+        assert_eq!(round_trip("0.00"), "0.00");
+        assert_eq!(round_trip("-0"), "0");
+        assert_eq!(header_and_digits("0"), vec![0, 0, NUMERIC_POS, 0]);
+    }
+
+    #[test]
+    fn round_trips_negatives() {
+        assert_eq!(round_trip("-1"), "-1");
+        assert_eq!(round_trip("-12.345"), "-12.345");
+        assert_eq!(round_trip("-0.5"), "-0.5");
+        assert_eq!(round_trip("-98765432109876543210"), "-98765432109876543210");
+    }
+
+    #[test]
+    fn round_trips_large_scales() {
+        assert_eq!(round_trip("1.50"), "1.50");
+        assert_eq!(round_trip("0.000000000123456789012345"), "0.000000000123456789012345");
+        assert_eq!(round_trip("123456789.987654321000"), "123456789.987654321000");
+        assert_eq!(round_trip("+5"), "5");
+        assert_eq!(round_trip(".5"), "0.5");
+    }
+
+    #[test]
+    fn round_trips_special_values() {
+        assert_eq!(round_trip("NaN"), "NaN");
+        assert_eq!(round_trip("Infinity"), "Infinity");
+        assert_eq!(round_trip("-Infinity"), "-Infinity");
+        assert_eq!(header_and_digits("NaN"), vec![0, 0, NUMERIC_NAN, 0]);
+    }
+
+    #[test]
+    fn encodes_like_postgresql() {
+        assert_eq!(round_trip("0.0001"), "0.0001");
+        assert_eq!(header_and_digits("0.0001"), vec![1, (-1i16) as u16, NUMERIC_POS, 4, 1]);
+        assert_eq!(round_trip("1000000"), "1000000");
+        assert_eq!(header_and_digits("1000000"), vec![1, 1, NUMERIC_POS, 0, 100]);
+    }
+
+    #[test]
+    fn rejects_invalid_text() {
+        for text in ["", ".", "-", "1e5", "inf", "1.2.3", "12a"] {
+            assert!(numeric_to_sql(text, &mut BytesMut::new()).is_err(), "{} is not a numeric", text);
+        }
+    }
+
+    fn write(value: &TwoValue, ty: &Type) -> Result<BytesMut, Box<dyn Error + Sync + Send>> {
+        let mut out = BytesMut::new();
+        value.to_sql_checked(ty, &mut out)?;
+        Ok(out)
+    }
+
+    /// Write the value into `ty`, then read it back as a column of `ty`
+    fn write_and_read(value: &TwoValue, ty: &Type) -> TwoValue {
+        TwoValue::from_sql(ty, &write(value, ty).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn widens_numbers_into_the_target_type() {
+        assert_eq!(write_and_read(&TwoValue::Int4(7), &Type::INT8), TwoValue::Int8(7));
+        assert_eq!(write_and_read(&TwoValue::Int2(-7), &Type::INT4), TwoValue::Int4(-7));
+        assert_eq!(write_and_read(&TwoValue::Int8(42), &Type::NUMERIC), TwoValue::Numeric(String::from("42")));
+        assert_eq!(write_and_read(&TwoValue::Int4(3), &Type::FLOAT8), TwoValue::Float8(3.0));
+        assert_eq!(write_and_read(&TwoValue::Float4(0.1), &Type::NUMERIC), TwoValue::Numeric(String::from("0.1")));
+        assert_eq!(write_and_read(&TwoValue::Float8(-2.5), &Type::NUMERIC), TwoValue::Numeric(String::from("-2.5")));
+        assert_eq!(write_and_read(&TwoValue::Float8(f64::NAN), &Type::NUMERIC), TwoValue::Numeric(String::from("NaN")));
+        assert_eq!(write_and_read(&TwoValue::Numeric(String::from("12.50")), &Type::FLOAT8), TwoValue::Float8(12.5));
+        assert_eq!(write_and_read(&TwoValue::Numeric(String::from("12.00")), &Type::INT4), TwoValue::Int4(12));
+    }
+
+    #[test]
+    fn narrows_numbers_only_without_loss() {
+        assert_eq!(write_and_read(&TwoValue::Int8(32767), &Type::INT2), TwoValue::Int2(32767));
+        assert!(write(&TwoValue::Int8(32768), &Type::INT2).is_err());
+        assert!(write(&TwoValue::Int8(i64::MAX), &Type::INT4).is_err());
+        assert!(write(&TwoValue::Float8(1e300), &Type::FLOAT4).is_err());
+        assert!(write(&TwoValue::Numeric(String::from("12.5")), &Type::INT4).is_err());
+        assert!(write(&TwoValue::Int4(1), &Type::TEXT).is_err());
+    }
+
+    #[test]
+    fn writes_text_and_timestamps_into_the_target_type() {
+        let text = TwoValue::Text(String::from("{\"a\": 1}"));
+        assert_eq!(write_and_read(&text, &Type::VARCHAR), text);
+        assert_eq!(write_and_read(&text, &Type::JSONB), TwoValue::Json(String::from("{\"a\": 1}")));
+
+        let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let midnight = date.and_time(NaiveTime::MIN);
+        assert_eq!(write_and_read(&TwoValue::Date(date), &Type::TIMESTAMP), TwoValue::Timestamp(midnight));
+        assert_eq!(write_and_read(&TwoValue::Timestamp(midnight), &Type::TIMESTAMPTZ), TwoValue::TimestampTz(midnight.and_utc()));
+        assert_eq!(write_and_read(&TwoValue::TimestampTz(midnight.and_utc()), &Type::TIMESTAMP), TwoValue::Timestamp(midnight));
+    }
+
+    #[test]
+    fn round_trips_text_as_is() {
+        // Once the marker of NULL values, now an ordinary text
+        for text in ["twodb_NULL", "O'Brien", "it''s", "C:\\temp\\new", "\\x00", "\"quoted\"", "NULL", ""] {
+            let value = TwoValue::Text(String::from(text));
+            assert_eq!(write_and_read(&value, &Type::TEXT), value);
+            assert_eq!(write_and_read(&value, &Type::VARCHAR), value);
+        }
+        let array = TwoValue::Array(vec![
+            TwoValue::Text(String::from("twodb_NULL")),
+            TwoValue::Null,
+            TwoValue::Text(String::from("a'b\\c\"d,{e}")),
+        ]);
+        assert_eq!(write_and_read(&array, &Type::TEXT_ARRAY), array);
+        assert_eq!(write(&TwoValue::Null, &Type::TEXT).unwrap().len(), 0);
+    }
+}
@@ -4,13 +4,18 @@ pub fn query_get_self_references_tables() -> &'static str {
         SELECT
             conname AS constraint_name,
             conrelid::regclass::varchar AS table_name,
-            a.attname AS column_name
+            a.attname AS column_name,
+            af.attname AS referenced_column_name
         FROM
             pg_constraint AS c
         JOIN
             pg_attribute AS a
         ON
             a.attnum = ANY(c.conkey) AND a.attrelid = c.conrelid
+        JOIN
+            pg_attribute AS af
+        ON
+            af.attnum = ANY(c.confkey) AND af.attrelid = c.confrelid
         WHERE
             c.confrelid = c.conrelid
             AND c.contype = 'f'
@@ -24,4 +29,261 @@ pub fn query_get_self_references_by_table() -> String {
     let condition = " AND conrelid::regclass::varchar = $1";
     let query = query_get_self_references_tables();
     query.to_owned() + condition
-}
\ No newline at end of file
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Columns of the primary key of the table named by $1
+pub fn query_get_primary_key_columns_by_table() -> &'static str {
+    "
+        SELECT a.attname::varchar AS column_name
+        FROM pg_index AS i
+        JOIN pg_attribute AS a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+        WHERE i.indisprimary
+        AND i.indrelid = $1::text::regclass
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Triggers created by users on the table named by $1, `enabled` is `pg_trigger.tgenabled`:
+/// O fires in origin mode, D is disabled, R fires in replica mode only, A always fires
+pub fn query_get_user_triggers_by_table() -> &'static str {
+    "
+        SELECT t.tgname::varchar AS trigger_name, t.tgenabled::text AS enabled
+        FROM pg_trigger AS t
+        WHERE t.tgrelid = $1::text::regclass
+        AND NOT t.tgisinternal
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Sequences owned by columns of the table named by $1, serial and identity columns alike
+pub fn query_get_owned_sequences_by_table() -> &'static str {
+    "
+        SELECT
+            a.attname::varchar AS column_name,
+            pg_get_serial_sequence($1, a.attname) AS sequence_name
+        FROM pg_depend AS d
+        JOIN pg_class AS s ON s.oid = d.objid AND s.relkind = 'S'
+        JOIN pg_attribute AS a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid
+        WHERE d.classid = 'pg_class'::regclass
+        AND d.refclassid = 'pg_class'::regclass
+        AND d.refobjid = $1::text::regclass
+        AND d.deptype IN ('a', 'i')
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Tables of the public schema that the planner estimates as empty
+pub fn query_get_empty_tables() -> &'static str {
+    "
+        SELECT n.nspname,
+            c.relname AS table_name,
+            c.reltuples
+        FROM pg_class c
+        INNER JOIN pg_namespace n ON (n.oid = c.relnamespace)
+        WHERE c.reltuples = 0 AND c.relkind = 'r'
+        and n.nspname = 'public';
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Tables without foreign keys
+pub fn query_get_clean_tables() -> &'static str {
+    "
+        SELECT table_name
+        FROM information_schema.tables
+        WHERE table_schema = 'public'
+        AND table_type = 'BASE TABLE'
+        AND table_name NOT IN (
+            SELECT DISTINCT table_name
+            FROM information_schema.table_constraints
+            WHERE constraint_type = 'FOREIGN KEY'
+            AND table_schema = 'public'
+        );
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Columns of the table named by $1
+pub fn query_get_columns_by_table() -> &'static str {
+    "
+        SELECT
+            column_name,
+            data_type,
+            character_maximum_length::integer AS character_maximum_length,
+            numeric_precision,
+            numeric_scale,
+            is_nullable,
+            identity_generation
+        FROM
+            information_schema.columns
+        WHERE
+            table_name = $1
+        ORDER BY ordinal_position
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Whether the table named by $1 exists in the public schema
+pub fn query_table_exists() -> &'static str {
+    "
+        SELECT EXISTS (
+            SELECT 1
+            FROM pg_tables
+            WHERE schemaname = 'public'
+            AND tablename = $1
+        )
+    "
+}
+
+/// SQL dialect: PostgreSQL
+pub fn query_get_base_tables() -> &'static str {
+    "
+        SELECT table_name, table_type
+        FROM information_schema.tables
+        WHERE table_schema = 'public'
+        AND table_type = 'BASE TABLE'
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// One row per foreign key: the table that owns it and the table it references
+pub fn query_get_foreign_keys() -> &'static str {
+    "
+        SELECT
+            c.conname AS constraint_name,
+            child.relname::varchar AS table_name,
+            parent.relname::varchar AS referenced_table_name
+        FROM
+            pg_constraint AS c
+        JOIN pg_class AS child ON child.oid = c.conrelid
+        JOIN pg_class AS parent ON parent.oid = c.confrelid
+        JOIN pg_namespace AS n ON n.oid = child.relnamespace
+        WHERE
+            c.contype = 'f'
+            AND n.nspname = 'public'
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// One row per foreign key, with its columns and the columns they reference, in key order
+pub fn query_get_foreign_key_columns() -> &'static str {
+    "
+        SELECT
+            child.relname::varchar AS table_name,
+            parent.relname::varchar AS referenced_table_name,
+            ARRAY(
+                SELECT a.attname::varchar
+                FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, position)
+                JOIN pg_attribute AS a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+                ORDER BY k.position
+            ) AS column_names,
+            ARRAY(
+                SELECT a.attname::varchar
+                FROM unnest(c.confkey) WITH ORDINALITY AS k(attnum, position)
+                JOIN pg_attribute AS a ON a.attrelid = c.confrelid AND a.attnum = k.attnum
+                ORDER BY k.position
+            ) AS referenced_column_names
+        FROM
+            pg_constraint AS c
+        JOIN pg_class AS child ON child.oid = c.conrelid
+        JOIN pg_class AS parent ON parent.oid = c.confrelid
+        JOIN pg_namespace AS n ON n.oid = child.relnamespace
+        WHERE
+            c.contype = 'f'
+            AND n.nspname = 'public'
+        ORDER BY child.relname, c.conname
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Columns of every table of the public schema, with their type as declared
+pub fn query_get_schema_columns() -> &'static str {
+    "
+        SELECT
+            c.relname::varchar AS table_name,
+            a.attname::varchar AS column_name,
+            format_type(a.atttypid, a.atttypmod) AS data_type,
+            NOT a.attnotnull AS is_nullable,
+            pg_get_expr(d.adbin, d.adrelid) AS column_default,
+            CASE a.attidentity WHEN 'a' THEN 'ALWAYS' WHEN 'd' THEN 'BY DEFAULT' END AS identity_generation
+        FROM pg_attribute AS a
+        JOIN pg_class AS c ON c.oid = a.attrelid
+        JOIN pg_namespace AS n ON n.oid = c.relnamespace
+        LEFT JOIN pg_attrdef AS d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+        WHERE n.nspname = 'public'
+        AND c.relkind IN ('r', 'p')
+        AND a.attnum > 0
+        AND NOT a.attisdropped
+        ORDER BY c.relname, a.attnum
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Primary key (p), unique (u) and foreign key (f) constraints of every table of the public schema
+pub fn query_get_schema_constraints() -> &'static str {
+    "
+        SELECT
+            c.relname::varchar AS table_name,
+            con.conname::varchar AS constraint_name,
+            con.contype::text AS constraint_type,
+            pg_get_constraintdef(con.oid) AS definition
+        FROM pg_constraint AS con
+        JOIN pg_class AS c ON c.oid = con.conrelid
+        JOIN pg_namespace AS n ON n.oid = c.relnamespace
+        WHERE n.nspname = 'public'
+        AND con.contype IN ('p', 'u', 'f')
+        ORDER BY c.relname, con.conname
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Indexes of every table of the public schema, except the ones backing a constraint
+pub fn query_get_schema_indexes() -> &'static str {
+    "
+        SELECT
+            t.relname::varchar AS table_name,
+            i.relname::varchar AS index_name,
+            pg_get_indexdef(i.oid) AS definition
+        FROM pg_index AS x
+        JOIN pg_class AS i ON i.oid = x.indexrelid
+        JOIN pg_class AS t ON t.oid = x.indrelid
+        JOIN pg_namespace AS n ON n.oid = t.relnamespace
+        WHERE n.nspname = 'public'
+        AND NOT EXISTS (
+            SELECT 1
+            FROM pg_constraint AS con
+            WHERE con.conindid = x.indexrelid
+            AND con.contype IN ('p', 'u', 'x')
+        )
+        ORDER BY t.relname, i.relname
+    "
+}
+
+/// SQL dialect: PostgreSQL
+///
+/// Enum types of the public schema, with their labels in order
+pub fn query_get_enum_types() -> &'static str {
+    "
+        SELECT
+            format_type(t.oid, NULL) AS type_name,
+            array_agg(e.enumlabel::text ORDER BY e.enumsortorder) AS labels
+        FROM pg_type AS t
+        JOIN pg_enum AS e ON e.enumtypid = t.oid
+        JOIN pg_namespace AS n ON n.oid = t.typnamespace
+        WHERE n.nspname = 'public'
+        GROUP BY t.oid
+    "
+}
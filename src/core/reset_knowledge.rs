@@ -1,10 +1,18 @@
-use rusqlite::{Connection, params};
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::domain::two_db_error::TwoDbError;
 
-pub fn reset_database() {
-    let sqlite_conn = Connection::open(crate::core::SQLITE_DATABASE_PATH).unwrap();
+pub fn reset_database() -> Result<(), TwoDbError> {
+    let gateway = SqliteKnowledgeGateway::default();
+    gateway.delete_tables()?;
 
-    sqlite_conn.execute(
-        "DELETE FROM tables",
-        params![],
-    ).unwrap();
-}
\ No newline at end of file
+    // Checkpoints are only valid together with the is_exported flags
+    gateway.delete_migration_runs()?;
+
+    gateway.delete_schema_differences()?;
+
+    // Watermarks go with the rows moved, change columns are set by the user
+    gateway.clear_watermarks()?;
+
+    // Mapping rules, transform rules and row filters are set by the user, they are kept
+    Ok(())
+}
@@ -0,0 +1,280 @@
+/*! This file contains the row filters, which rows of the source tables are moved.
+
+Filters are set by the user per source table and kept in the `row_filters` table of the
+SQLite database. A FILTER keeps the rows of its table matching its condition, the parents
+of the rows left out are the user's concern. SEED filters make a subset instead: the rows
+they match, and every row these reference through foreign keys, parents of parents included,
+are moved, and no other row of any table. A table without seed is then only moved for the
+rows the others reference, e.g. a lookup table gets the values in use.
+
+The subset is walked once into the temporary table `twodb_subset_rows` of a source connection,
+the `ctid` of its rows keyed by table. During a migration run the table is kept on the pooled
+connection and reused by the next tables of the run, see `SourceRows::open`.
+*/
+
+use log::{error, info};
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::core::action::checkpoint::quote_literal;
+use crate::core::database::{pg_connect, PgClient};
+use crate::core::postgresql_queries::query_get_foreign_key_columns;
+use crate::domain::row_filter::RowFilter;
+use crate::domain::two_db_error::TwoDbError;
+
+/// Foreign key followed from the rows of a subset to the rows they reference
+#[derive(Debug)]
+struct ForeignKey {
+    table_name: String,
+    referenced_table_name: String,
+    column_names: Vec<String>,
+    referenced_column_names: Vec<String>,
+}
+
+/// Filters of every table of the source database
+pub fn get_row_filters(database_name: &str) -> Result<Vec<RowFilter>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_row_filters_of_database(database_name)
+}
+
+/// Save a filter once its condition is valid SQL on its table
+pub fn add_row_filter(filter: &RowFilter) -> Result<(), TwoDbError> {
+    if filter.database.is_empty() || filter.table_name.is_empty() || filter.condition.trim().is_empty() {
+        return Err(TwoDbError::Configuration(format!("A {} needs a source table and a condition", filter.kind_name())));
+    }
+    let mut client = pg_connect(&filter.database)?;
+    let query = format!("EXPLAIN SELECT * FROM {} WHERE {}", filter.table_name, filter.condition);
+    if let Err(err) = client.execute(&query, &[]) {
+        let message = format!("Invalid condition of table {}: {}", filter.table_name, err.as_db_error().map_or(err.to_string(), |e| e.message().to_string()));
+        return Err(TwoDbError::Configuration(message));
+    }
+    SqliteKnowledgeGateway::default().insert_row_filter(filter)
+}
+
+pub fn delete_row_filter(id: i64) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().delete_row_filter(id)
+}
+
+const SUBSET_ROWS: &str = "twodb_subset_rows";
+
+/// Rows of a source table to move, read on one connection of the source
+///
+/// Every read of a table in a subset joins `twodb_subset_rows` of this connection.
+/// The temporary tables of the connection are dropped with the SourceRows, see `drop_with`.
+pub struct SourceRows {
+    pub database_name: String,
+    pub table_name: String,
+    /// The table itself, or a parenthesized SELECT of its filtered rows.
+    /// Give it an alias, e.g. `SELECT * FROM {relation} AS source_rows`
+    pub relation: String,
+    pub client: PgClient,
+    temp_tables: Vec<&'static str>,
+}
+
+impl SourceRows {
+    /// Filter the rows of `table_name`, walking the subset if the connection has none for `run_id`
+    ///
+    /// Without a run, the subset is walked for this table alone and dropped with the SourceRows.
+    pub fn open(database_name: &str, table_name: &str, run_id: Option<i64>) -> Result<SourceRows, TwoDbError> {
+        let filters = get_row_filters(database_name)?;
+        let mut conditions = filters.iter()
+            .filter(|filter| !filter.is_seed && filter.table_name == table_name)
+            .map(|filter| format!("({})", filter.condition))
+            .collect::<Vec<_>>();
+
+        let seeds = filters.iter().filter(|filter| filter.is_seed).collect::<Vec<_>>();
+        let has_subset = !seeds.is_empty();
+        // Read before the client is borrowed, the move holds a single connection of each database
+        let foreign_keys = if has_subset { get_foreign_keys(database_name)? } else { Vec::new() };
+        let mut client = pg_connect(database_name)?;
+        let mut temp_tables = Vec::new();
+        if has_subset {
+            let subset_rows = query_subset_rows(&seeds, &foreign_keys);
+            // The walk of another run, or of other filters, is not reused
+            let subset_key = format!("run {:?}: {}", run_id, subset_rows);
+            let row = client.query_one(&format!("SELECT obj_description(to_regclass('pg_temp.{}'), 'pg_class')", SUBSET_ROWS), &[])
+                .map_err(|err| TwoDbError::transfer(table_name, &err))?;
+            if run_id.is_none() || row.get::<_, Option<String>>(0).as_ref() != Some(&subset_key) {
+                info!("Table: {} walks the subset of {} seeds", table_name, seeds.len());
+                client.batch_execute(&query_create_subset_rows(&subset_rows, &subset_key))
+                    .map_err(|err| TwoDbError::transfer(table_name, &err))?;
+            }
+            if run_id.is_none() {
+                temp_tables.push(SUBSET_ROWS);
+            }
+            conditions.push(query_subset_condition(table_name));
+        }
+        let relation = if conditions.is_empty() {
+            table_name.to_string()
+        } else {
+            info!("Table: {} rows are filtered, {} seeds", table_name, seeds.len());
+            format!("(SELECT * FROM {} WHERE {})", table_name, conditions.join(" AND "))
+        };
+        Ok(SourceRows {
+            database_name: database_name.to_string(),
+            table_name: table_name.to_string(),
+            relation,
+            client,
+            temp_tables,
+        })
+    }
+
+    /// Drop the temporary table `table_name` of the connection with the SourceRows, even on error
+    ///
+    /// The connection goes back to the pool, a table left behind would outlive the move.
+    pub fn drop_with(&mut self, table_name: &'static str) {
+        if !self.temp_tables.contains(&table_name) {
+            self.temp_tables.push(table_name);
+        }
+    }
+}
+
+impl Drop for SourceRows {
+    fn drop(&mut self) {
+        if self.temp_tables.is_empty() {
+            return;
+        }
+        let query = format!("DROP TABLE IF EXISTS {}", self.temp_tables.join(", "));
+        if let Err(err) = self.client.batch_execute(&query) {
+            error!("Table: {} temporary tables cannot be dropped: {}", self.table_name, err);
+        }
+    }
+}
+
+fn get_foreign_keys(database_name: &str) -> Result<Vec<ForeignKey>, TwoDbError> {
+    let mut client = pg_connect(database_name)?;
+    let rows = client.query(query_get_foreign_key_columns(), &[])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    Ok(rows.iter().map(|row| ForeignKey {
+        table_name: row.get("table_name"),
+        referenced_table_name: row.get("referenced_table_name"),
+        column_names: row.get("column_names"),
+        referenced_column_names: row.get("referenced_column_names"),
+    }).collect())
+}
+
+/// SELECT of the table name and `ctid` of every row in the subset, as `table_name` and `row_id`
+///
+/// The subset is walked in one recursive query, rows are told apart by table name and `ctid`,
+/// so it is meant for a source nobody writes to during the move.
+/// The `ctid` is walked as text, a recursive UNION needs hashable columns and `tid` is only
+/// hashable from PostgreSQL 14. Partitions of a partitioned table are not told apart.
+fn query_subset_rows(seeds: &[&RowFilter], foreign_keys: &[ForeignKey]) -> String {
+    let seed_rows = seeds.iter()
+        .map(|seed| format!(
+            "SELECT {}::text, ctid::text FROM {} WHERE ({})",
+            quote_literal(&seed.table_name), seed.table_name, seed.condition
+        ))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    let parent_rows = foreign_keys.iter()
+        .map(|foreign_key| {
+            let join = foreign_key.column_names.iter().zip(&foreign_key.referenced_column_names)
+                .map(|(column, referenced_column)| format!("p.{} = c.{}", referenced_column, column))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            format!(
+                "SELECT {}::text, p.ctid::text FROM {} AS c JOIN {} AS p ON {} WHERE s.table_name = {} AND c.ctid = s.row_id::tid",
+                quote_literal(&foreign_key.referenced_table_name),
+                foreign_key.table_name,
+                foreign_key.referenced_table_name,
+                join,
+                quote_literal(&foreign_key.table_name),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let subset = if parent_rows.is_empty() {
+        seed_rows
+    } else {
+        format!(
+            "{} UNION SELECT parent_rows.table_name, parent_rows.row_id \
+            FROM twodb_subset AS s \
+            CROSS JOIN LATERAL ({}) AS parent_rows(table_name, row_id)",
+            seed_rows,
+            parent_rows.join(" UNION ALL "),
+        )
+    };
+    format!(
+        "WITH RECURSIVE twodb_subset(table_name, row_id) AS ({}) SELECT DISTINCT table_name, row_id::tid AS row_id FROM twodb_subset",
+        subset,
+    )
+}
+
+/// Replace `twodb_subset_rows` by the rows of `subset_rows`, `subset_key` tells which walk it holds
+fn query_create_subset_rows(subset_rows: &str, subset_key: &str) -> String {
+    format!(
+        "DROP TABLE IF EXISTS {table}; \
+        CREATE TEMP TABLE {table} AS {subset_rows}; \
+        ALTER TABLE {table} ADD PRIMARY KEY (table_name, row_id); \
+        ANALYZE {table}; \
+        COMMENT ON TABLE {table} IS {key}",
+        table = SUBSET_ROWS,
+        key = quote_literal(subset_key),
+    )
+}
+
+/// Condition on the rows of `table_name` keeping those in the subset
+fn query_subset_condition(table_name: &str) -> String {
+    format!("ctid IN (SELECT row_id FROM {} WHERE table_name = {})", SUBSET_ROWS, quote_literal(table_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(table_name: &str, condition: &str) -> RowFilter {
+        RowFilter {
+            id: 1,
+            database: String::from("erp"),
+            table_name: table_name.to_string(),
+            condition: condition.to_string(),
+            is_seed: true,
+        }
+    }
+
+    #[test]
+    fn walks_the_seed_rows_alone_without_foreign_keys() {
+        let seed = seed("orders", "tenant_id = 42");
+        assert_eq!(
+            query_subset_rows(&[&seed], &[]),
+            "WITH RECURSIVE twodb_subset(table_name, row_id) AS (SELECT 'orders'::text, ctid::text FROM orders WHERE (tenant_id = 42)) \
+            SELECT DISTINCT table_name, row_id::tid AS row_id FROM twodb_subset",
+        );
+    }
+
+    #[test]
+    fn walks_the_parents_of_the_rows_as_text() {
+        let seeds = [seed("orders", "id < 10"), seed("invoice", "paid")];
+        let foreign_key = ForeignKey {
+            table_name: String::from("orders"),
+            referenced_table_name: String::from("customer"),
+            column_names: vec![String::from("customer_id"), String::from("shop_id")],
+            referenced_column_names: vec![String::from("id"), String::from("shop_id")],
+        };
+        let query = query_subset_rows(&seeds.iter().collect::<Vec<_>>(), &[foreign_key]);
+        assert_eq!(
+            query,
+            "WITH RECURSIVE twodb_subset(table_name, row_id) AS (\
+            SELECT 'orders'::text, ctid::text FROM orders WHERE (id < 10) \
+            UNION ALL SELECT 'invoice'::text, ctid::text FROM invoice WHERE (paid) \
+            UNION SELECT parent_rows.table_name, parent_rows.row_id FROM twodb_subset AS s \
+            CROSS JOIN LATERAL (SELECT 'customer'::text, p.ctid::text FROM orders AS c JOIN customer AS p \
+            ON p.id = c.customer_id AND p.shop_id = c.shop_id WHERE s.table_name = 'orders' AND c.ctid = s.row_id::tid) \
+            AS parent_rows(table_name, row_id)) \
+            SELECT DISTINCT table_name, row_id::tid AS row_id FROM twodb_subset",
+        );
+    }
+
+    #[test]
+    fn keeps_the_walk_keyed_by_table_and_ctid() {
+        let query = query_create_subset_rows("SELECT 1", "run Some(3): it's");
+        assert_eq!(
+            query,
+            "DROP TABLE IF EXISTS twodb_subset_rows; CREATE TEMP TABLE twodb_subset_rows AS SELECT 1; \
+            ALTER TABLE twodb_subset_rows ADD PRIMARY KEY (table_name, row_id); ANALYZE twodb_subset_rows; \
+            COMMENT ON TABLE twodb_subset_rows IS 'run Some(3): it''s'",
+        );
+        assert_eq!(
+            query_subset_condition("o'rders"),
+            "ctid IN (SELECT row_id FROM twodb_subset_rows WHERE table_name = 'o''rders')",
+        );
+    }
+}
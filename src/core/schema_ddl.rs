@@ -0,0 +1,504 @@
+/*! This file contains the generation of the DDL script that brings the target schema in line with the source.
+
+The script makes the target accept the rows of the source. Tables, columns, constraints and
+indexes missing in the target are created, and the types, nullability and defaults of the
+columns follow the source. Unique constraints, unique indexes and foreign keys of the target
+only may reject source rows, their drops are destructive: they are written commented out and
+never run unless the script allows it. Tables and columns of the target only are kept, the
+script never drops data.
+
+Foreign keys are added once every table exists, so the statements run in a single transaction
+whatever the order of the tables. Enum types and sequences used by the new columns are created first.
+
+A table missing in the target can also be created alone, right before its rows are moved,
+see `build_create_table_scripts`.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use log::info;
+use postgres::Client;
+use crate::core::database::pg_connect;
+use crate::core::postgresql_queries::query_get_enum_types;
+use crate::core::schema_diff::get_schema;
+use crate::domain::ddl_script::{DdlScript, DdlStatement};
+use crate::domain::schema::{ColumnSchema, ConstraintSchema, TableSchema};
+use crate::domain::two_db_error::TwoDbError;
+
+/// Statements run phase by phase, each phase may need the objects of the previous ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    CreateType,
+    CreateSequence,
+    DropForeignKey,
+    DropConstraint,
+    CreateTable,
+    AlterColumn,
+    OwnSequence,
+    AddKey,
+    AddForeignKey,
+    CreateIndex,
+}
+
+/// Labels of each enum type, by type name as printed by `format_type`
+pub type EnumTypes = BTreeMap<String, Vec<String>>;
+
+#[derive(Default)]
+struct ScriptBuilder {
+    statements: Vec<(Phase, DdlStatement)>,
+    sequences: BTreeSet<String>,
+    /// Enum types of the source missing in the target, removed once created
+    enum_types: EnumTypes,
+    notes: Vec<String>,
+}
+
+impl ScriptBuilder {
+    fn new(enum_types: EnumTypes) -> Self {
+        Self {
+            enum_types,
+            ..Default::default()
+        }
+    }
+
+    fn push(&mut self, phase: Phase, table_name: &str, sql: String) {
+        self.push_statement(phase, table_name, sql, false);
+    }
+
+    /// Drop a constraint or an index of the target only, see `DdlScript::allows_destructive`
+    fn push_destructive(&mut self, phase: Phase, table_name: &str, sql: String) {
+        self.push_statement(phase, table_name, sql, true);
+    }
+
+    fn push_statement(&mut self, phase: Phase, table_name: &str, sql: String, is_destructive: bool) {
+        self.statements.push((phase, DdlStatement { table_name: table_name.to_string(), sql, is_destructive }));
+    }
+
+    /// Create the sequence of a `nextval` default, owned by its column like a serial
+    fn use_sequence(&mut self, table_name: &str, column: &ColumnSchema) {
+        let Some(sequence) = column.default.as_deref().and_then(default_sequence) else {
+            return;
+        };
+        if self.sequences.insert(sequence.to_string()) {
+            self.push(Phase::CreateSequence, table_name, format!("CREATE SEQUENCE IF NOT EXISTS {}", sequence));
+        }
+        self.push(
+            Phase::OwnSequence,
+            table_name,
+            format!("ALTER SEQUENCE {} OWNED BY {}.{}", sequence, ident(table_name), ident(&column.name)),
+        );
+    }
+
+    /// Create the enum type of the column, or of its elements for an array
+    fn use_enum_type(&mut self, table_name: &str, column: &ColumnSchema) {
+        let type_name = column.data_type.trim_end_matches("[]");
+        let Some(labels) = self.enum_types.remove(type_name) else {
+            return;
+        };
+        let labels = labels.iter().map(|label| literal(label)).collect::<Vec<_>>();
+        self.push(
+            Phase::CreateType,
+            table_name,
+            format!("CREATE TYPE {} AS ENUM ({})", type_name, labels.join(", ")),
+        );
+    }
+
+    fn build(mut self, script: &mut DdlScript) {
+        // Stable, the order of the tables is kept within a phase
+        self.statements.sort_by_key(|(phase, _)| *phase);
+        script.statements.extend(self.statements.into_iter().map(|(_, statement)| statement));
+        script.notes = self.notes;
+        let left_out = script.statements_left_out().count();
+        if left_out > 0 {
+            script.notes.push(format!(
+                "{} drops of unique constraints, unique indexes or foreign keys of the target only are left out, they may reject source rows",
+                left_out
+            ));
+        }
+    }
+}
+
+/// Read both schemas and generate the script bringing the target in line with the source
+///
+/// The destructive statements are only run if `allows_destructive`
+pub fn generate_ddl_script(source_database_name: &str, target_database_name: &str, allows_destructive: bool) -> Result<DdlScript, TwoDbError> {
+    let source = get_schema(source_database_name)?;
+    let target = get_schema(target_database_name)?;
+    let enum_types = get_missing_enum_types(source_database_name, target_database_name)?;
+    let mut script = DdlScript {
+        source_database: source_database_name.to_string(),
+        target_database: target_database_name.to_string(),
+        allows_destructive,
+        ..Default::default()
+    };
+    build_ddl_script(&source, &target, enum_types, &mut script);
+    info!("DDL script for {}: {} statements", target_database_name, script.statements.len());
+    Ok(script)
+}
+
+/// Fill `script` with the statements bringing `target` in line with `source`
+///
+/// `enum_types` are the enum types of the source missing in the target
+pub fn build_ddl_script(
+    source: &BTreeMap<String, TableSchema>,
+    target: &BTreeMap<String, TableSchema>,
+    enum_types: EnumTypes,
+    script: &mut DdlScript,
+) {
+    let mut builder = ScriptBuilder::new(enum_types);
+    for (table_name, source_table) in source {
+        match target.get(table_name) {
+            Some(target_table) => alter_table(&mut builder, source_table, target_table),
+            None => create_table(&mut builder, source_table),
+        }
+    }
+    for table_name in target.keys().filter(|name| !source.contains_key(*name)) {
+        builder.notes.push(format!("Table {} is only in the target, it is kept", table_name));
+    }
+    builder.build(script);
+}
+
+/// Fill the scripts creating `table` in a target without it
+///
+/// `before_load` creates its enum types, sequences, columns and primary key,
+/// `after_load` adds its unique constraints, foreign keys and indexes, faster once the rows are in.
+pub fn build_create_table_scripts(table: &TableSchema, enum_types: EnumTypes, before_load: &mut DdlScript, after_load: &mut DdlScript) {
+    let mut builder = ScriptBuilder::new(enum_types);
+    create_table(&mut builder, table);
+
+    let (after, before) = builder.statements.into_iter().partition(|(phase, _)| *phase >= Phase::AddKey);
+    ScriptBuilder { statements: before, ..Default::default() }.build(before_load);
+    ScriptBuilder { statements: after, ..Default::default() }.build(after_load);
+}
+
+fn create_table(builder: &mut ScriptBuilder, table: &TableSchema) {
+    let mut definitions = Vec::new();
+    for column in &table.columns {
+        builder.use_enum_type(&table.name, column);
+        builder.use_sequence(&table.name, column);
+        definitions.push(column_definition(column));
+    }
+    if let Some(primary_key) = &table.primary_key {
+        definitions.push(format!("CONSTRAINT {} {}", ident(&primary_key.name), primary_key.definition));
+    }
+    builder.push(
+        Phase::CreateTable,
+        &table.name,
+        format!("CREATE TABLE {} (\n    {}\n)", ident(&table.name), definitions.join(",\n    ")),
+    );
+
+    for unique_constraint in &table.unique_constraints {
+        builder.push(Phase::AddKey, &table.name, add_constraint(&table.name, unique_constraint));
+    }
+    for foreign_key in &table.foreign_keys {
+        builder.push(Phase::AddForeignKey, &table.name, add_constraint(&table.name, foreign_key));
+    }
+    for index in &table.indexes {
+        builder.push(Phase::CreateIndex, &table.name, create_index(index));
+    }
+}
+
+fn alter_table(builder: &mut ScriptBuilder, source: &TableSchema, target: &TableSchema) {
+    let table_name = &source.name;
+    let alter_column = |column: &ColumnSchema, action: String| {
+        format!("ALTER TABLE {} ALTER COLUMN {} {}", ident(table_name), ident(&column.name), action)
+    };
+
+    for source_column in &source.columns {
+        let Some(target_column) = target.columns.iter().find(|c| c.name == source_column.name) else {
+            add_column(builder, table_name, source_column);
+            continue;
+        };
+        if source_column.data_type != target_column.data_type {
+            builder.use_enum_type(table_name, source_column);
+            let action = format!("TYPE {} USING {}::{}", source_column.data_type, ident(&source_column.name), source_column.data_type);
+            builder.push(Phase::AlterColumn, table_name, alter_column(source_column, action));
+        }
+        if source_column.is_nullable != target_column.is_nullable {
+            let action = if source_column.is_nullable { "DROP NOT NULL" } else { "SET NOT NULL" };
+            builder.push(Phase::AlterColumn, table_name, alter_column(source_column, action.to_string()));
+        }
+        if source_column.default != target_column.default {
+            let action = match &source_column.default {
+                Some(default) => format!("SET DEFAULT {}", default),
+                None => "DROP DEFAULT".to_string(),
+            };
+            builder.use_sequence(table_name, source_column);
+            builder.push(Phase::AlterColumn, table_name, alter_column(source_column, action));
+        }
+    }
+    for target_column in target.columns.iter().filter(|c| !source.columns.iter().any(|c2| c2.name == c.name)) {
+        // Rows of the source have no value for it
+        if !target_column.is_nullable && target_column.default.is_none() && target_column.identity_generation.is_none() {
+            builder.push(Phase::AlterColumn, table_name, alter_column(target_column, "DROP NOT NULL".to_string()));
+        }
+        builder.notes.push(format!("Column {}.{} is only in the target, it is kept", table_name, target_column.name));
+    }
+
+    match (&source.primary_key, &target.primary_key) {
+        (Some(source_key), Some(target_key)) if source_key.definition != target_key.definition => {
+            builder.push(Phase::DropConstraint, table_name, drop_constraint(table_name, target_key));
+            builder.push(Phase::AddKey, table_name, add_constraint(table_name, source_key));
+        }
+        (Some(source_key), None) => builder.push(Phase::AddKey, table_name, add_constraint(table_name, source_key)),
+        (None, Some(target_key)) => {
+            builder.notes.push(format!("Primary key {} of {} is only in the target, it is kept", target_key.name, table_name));
+        }
+        _ => {}
+    }
+
+    for (constraint, is_in_source) in by_definition(&source.unique_constraints, &target.unique_constraints) {
+        match is_in_source {
+            true => builder.push(Phase::AddKey, table_name, add_constraint(table_name, constraint)),
+            false => builder.push_destructive(Phase::DropConstraint, table_name, drop_constraint(table_name, constraint)),
+        }
+    }
+    for (foreign_key, is_in_source) in by_definition(&source.foreign_keys, &target.foreign_keys) {
+        match is_in_source {
+            true => builder.push(Phase::AddForeignKey, table_name, add_constraint(table_name, foreign_key)),
+            false => builder.push_destructive(Phase::DropForeignKey, table_name, drop_constraint(table_name, foreign_key)),
+        }
+    }
+    for (index, is_in_source) in by_definition(&source.indexes, &target.indexes) {
+        match is_in_source {
+            true => builder.push(Phase::CreateIndex, table_name, create_index(index)),
+            // Other indexes of the target do not reject rows
+            false if index.definition.starts_with("UNIQUE ") => {
+                builder.push_destructive(Phase::DropConstraint, table_name, format!("DROP INDEX {}", ident(&index.name)));
+            }
+            false => {}
+        }
+    }
+}
+
+/// A NOT NULL column without default cannot be added to a table with rows, it is added as nullable
+fn add_column(builder: &mut ScriptBuilder, table_name: &str, column: &ColumnSchema) {
+    builder.use_enum_type(table_name, column);
+    builder.use_sequence(table_name, column);
+    let mut column = column.clone();
+    if !column.is_nullable && column.default.is_none() && column.identity_generation.is_none() {
+        builder.notes.push(format!(
+            "Column {}.{} is NOT NULL in the source, it is added as nullable to the target",
+            table_name, column.name
+        ));
+        column.is_nullable = true;
+    }
+    builder.push(
+        Phase::AlterColumn,
+        table_name,
+        format!("ALTER TABLE {} ADD COLUMN {}", ident(table_name), column_definition(&column)),
+    );
+}
+
+/// Constraints of one side without one of the same definition on the other side,
+/// true for the ones of the source
+fn by_definition<'a>(source: &'a [ConstraintSchema], target: &'a [ConstraintSchema]) -> Vec<(&'a ConstraintSchema, bool)> {
+    let only_in = |side: &'a [ConstraintSchema], other: &'a [ConstraintSchema]| {
+        side.iter().filter(move |c| !other.iter().any(|c2| c2.definition == c.definition))
+    };
+    only_in(source, target).map(|c| (c, true))
+        .chain(only_in(target, source).map(|c| (c, false)))
+        .collect()
+}
+
+fn column_definition(column: &ColumnSchema) -> String {
+    let mut definition = format!("{} {}", ident(&column.name), column.data_type);
+    match (&column.identity_generation, &column.default) {
+        (Some(generation), _) => definition.push_str(&format!(" GENERATED {} AS IDENTITY", generation)),
+        (None, Some(default)) => definition.push_str(&format!(" DEFAULT {}", default)),
+        (None, None) => {}
+    }
+    if !column.is_nullable {
+        definition.push_str(" NOT NULL");
+    }
+    definition
+}
+
+fn add_constraint(table_name: &str, constraint: &ConstraintSchema) -> String {
+    format!("ALTER TABLE {} ADD CONSTRAINT {} {}", ident(table_name), ident(&constraint.name), constraint.definition)
+}
+
+fn drop_constraint(table_name: &str, constraint: &ConstraintSchema) -> String {
+    format!("ALTER TABLE {} DROP CONSTRAINT {}", ident(table_name), ident(&constraint.name))
+}
+
+/// The definition of an index is kept without its name, see `get_schema`
+fn create_index(index: &ConstraintSchema) -> String {
+    match index.definition.strip_prefix("UNIQUE ") {
+        Some(definition) => format!("CREATE UNIQUE INDEX {} {}", ident(&index.name), definition),
+        None => format!("CREATE INDEX {} {}", ident(&index.name), index.definition),
+    }
+}
+
+/// `nextval('document_id_seq'::regclass)` uses the sequence `document_id_seq`
+fn default_sequence(default: &str) -> Option<&str> {
+    let (sequence, _) = default.strip_prefix("nextval('")?.split_once("'::regclass)")?;
+    Some(sequence)
+}
+
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Names read from the catalog are quoted as is, they may be reserved words or mixed case
+fn ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Enum types of the public schema
+pub fn get_enum_types(database_name: &str) -> Result<EnumTypes, TwoDbError> {
+    let mut pg_client = pg_connect(database_name)?;
+    let rows = pg_client.query(query_get_enum_types(), &[])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    Ok(rows.iter().map(|row| (row.get("type_name"), row.get("labels"))).collect())
+}
+
+/// Enum types of the source without a type of the same name in the target
+///
+/// Types of the same name with other labels are not changed, `ALTER TYPE ... ADD VALUE`
+/// cannot be used in the transaction that adds it.
+pub fn get_missing_enum_types(source_database_name: &str, target_database_name: &str) -> Result<EnumTypes, TwoDbError> {
+    let target = get_enum_types(target_database_name)?;
+    let mut source = get_enum_types(source_database_name)?;
+    source.retain(|type_name, _| !target.contains_key(type_name));
+    Ok(source)
+}
+
+/// Write the script to a `.sql` file, to be reviewed before it is applied
+pub fn write_ddl_script(script: &DdlScript, path: &str) -> Result<(), TwoDbError> {
+    fs::write(path, script.to_sql())
+        .map_err(|err| TwoDbError::Export { path: path.to_string(), message: err.to_string() })
+}
+
+/// Run the script on the target in one transaction, nothing is changed if a statement fails
+pub fn apply_ddl_script(script: &DdlScript) -> Result<(), TwoDbError> {
+    let mut pg_client = pg_connect(&script.target_database)?;
+    apply_ddl_script_with(&mut pg_client, script)
+}
+
+/// Run the script with a client already borrowed from the pool of the target
+pub fn apply_ddl_script_with(pg_client: &mut Client, script: &DdlScript) -> Result<(), TwoDbError> {
+    let database_name = &script.target_database;
+    let mut transaction = pg_client.transaction()
+        .map_err(|err| TwoDbError::connection(database_name, err))?;
+    for statement in script.statements_left_out() {
+        info!("Table: {} destructive DDL left out: {}", statement.table_name, statement.sql);
+    }
+    for statement in script.statements_to_run() {
+        info!("Table: {} DDL: {}", statement.table_name, statement.sql);
+        transaction.batch_execute(&statement.sql)
+            .map_err(|err| TwoDbError::schema_change(database_name, &statement.sql, err))?;
+    }
+    transaction.commit()
+        .map_err(|err| TwoDbError::schema_change(database_name, "COMMIT", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, is_nullable: bool) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable,
+            default: None,
+            identity_generation: None,
+        }
+    }
+
+    fn constraint(name: &str, definition: &str) -> ConstraintSchema {
+        ConstraintSchema { name: name.to_string(), definition: definition.to_string() }
+    }
+
+    fn schema(tables: Vec<TableSchema>) -> BTreeMap<String, TableSchema> {
+        tables.into_iter().map(|table| (table.name.clone(), table)).collect()
+    }
+
+    fn build(source: Vec<TableSchema>, target: Vec<TableSchema>, allows_destructive: bool) -> DdlScript {
+        let mut script = DdlScript { allows_destructive, ..Default::default() };
+        build_ddl_script(&schema(source), &schema(target), EnumTypes::new(), &mut script);
+        script
+    }
+
+    fn sqls(script: &DdlScript) -> Vec<&str> {
+        script.statements.iter().map(|statement| statement.sql.as_str()).collect()
+    }
+
+    #[test]
+    fn missing_table_is_created_and_its_foreign_key_added_after_the_tables() {
+        let mut document = TableSchema::new("document".to_string());
+        document.columns = vec![column("id", "integer", false), column("author_id", "integer", true)];
+        document.primary_key = Some(constraint("document_pkey", "PRIMARY KEY (id)"));
+        document.foreign_keys = vec![constraint("document_author_fk", "FOREIGN KEY (author_id) REFERENCES author(id)")];
+        let mut author = TableSchema::new("author".to_string());
+        author.columns = vec![column("id", "integer", false)];
+        author.primary_key = Some(constraint("author_pkey", "PRIMARY KEY (id)"));
+
+        let script = build(vec![document, author.clone()], vec![author], false);
+
+        assert_eq!(sqls(&script), vec![
+            "CREATE TABLE \"document\" (\n    \"id\" integer NOT NULL,\n    \"author_id\" integer,\n    CONSTRAINT \"document_pkey\" PRIMARY KEY (id)\n)",
+            "ALTER TABLE \"document\" ADD CONSTRAINT \"document_author_fk\" FOREIGN KEY (author_id) REFERENCES author(id)",
+        ]);
+        assert!(script.statements.iter().all(|statement| !statement.is_destructive));
+    }
+
+    #[test]
+    fn columns_follow_the_source_and_new_not_null_columns_are_nullable() {
+        let mut source = TableSchema::new("item".to_string());
+        source.columns = vec![
+            column("id", "bigint", false),
+            ColumnSchema { default: Some("0".to_string()), ..column("quantity", "integer", false) },
+            column("label", "text", false),
+        ];
+        let mut target = TableSchema::new("item".to_string());
+        target.columns = vec![column("id", "integer", false), column("quantity", "integer", true), column("legacy", "text", false)];
+
+        let script = build(vec![source], vec![target], false);
+
+        assert_eq!(sqls(&script), vec![
+            "ALTER TABLE \"item\" ALTER COLUMN \"id\" TYPE bigint USING \"id\"::bigint",
+            "ALTER TABLE \"item\" ALTER COLUMN \"quantity\" SET NOT NULL",
+            "ALTER TABLE \"item\" ALTER COLUMN \"quantity\" SET DEFAULT 0",
+            "ALTER TABLE \"item\" ADD COLUMN \"label\" text",
+            "ALTER TABLE \"item\" ALTER COLUMN \"legacy\" DROP NOT NULL",
+        ]);
+        assert_eq!(script.notes, vec![
+            "Column item.label is NOT NULL in the source, it is added as nullable to the target",
+            "Column item.legacy is only in the target, it is kept",
+        ]);
+    }
+
+    #[test]
+    fn target_only_constraints_are_dropped_only_if_destructive() {
+        let mut source = TableSchema::new("account".to_string());
+        source.columns = vec![column("email", "text", true)];
+        source.indexes = vec![constraint("account_email_idx", "USING btree (email)")];
+        let mut target = source.clone();
+        target.unique_constraints = vec![constraint("account_email_key", "UNIQUE (email)")];
+        target.foreign_keys = vec![constraint("account_owner_fk", "FOREIGN KEY (email) REFERENCES owner(email)")];
+        target.indexes = vec![constraint("account_email_lower_idx", "UNIQUE USING btree (lower(email))")];
+        let mut other_target = TableSchema::new("archive".to_string());
+        other_target.columns = vec![column("id", "integer", false)];
+
+        let script = build(vec![source.clone()], vec![target.clone(), other_target], false);
+
+        assert_eq!(sqls(&script), vec![
+            "ALTER TABLE \"account\" DROP CONSTRAINT \"account_owner_fk\"",
+            "ALTER TABLE \"account\" DROP CONSTRAINT \"account_email_key\"",
+            "DROP INDEX \"account_email_lower_idx\"",
+            "CREATE INDEX \"account_email_idx\" USING btree (email)",
+        ]);
+        assert_eq!(script.statements_to_run().count(), 1);
+        assert_eq!(script.statements_left_out().count(), 3);
+        assert!(script.notes.contains(&"Table archive is only in the target, it is kept".to_string()));
+        let sql = script.to_sql();
+        assert!(sql.contains("-- ALTER TABLE \"account\" DROP CONSTRAINT \"account_owner_fk\"\n"));
+        assert!(sql.contains("-- DROP INDEX \"account_email_lower_idx\"\n"));
+        assert!(sql.contains("\nCREATE INDEX \"account_email_idx\" USING btree (email);\n"));
+
+        let script = build(vec![source], vec![target], true);
+        assert_eq!(script.statements_to_run().count(), 4);
+        assert!(script.to_sql().contains("\nDROP INDEX \"account_email_lower_idx\";\n"));
+    }
+}
@@ -0,0 +1,331 @@
+/*! This file contains the comparison of the schemas of the source and target databases.
+
+The schemas are read from the catalog of both databases, then compared table by table.
+Constraints and indexes are matched by definition, so the same foreign key named
+differently on both sides is not a difference. The last comparison of each pair of
+databases is kept in the `schema_differences` table of the SQLite database.
+*/
+
+use std::collections::BTreeMap;
+use std::fs;
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::core::database::pg_connect;
+use crate::core::postgresql_queries::{query_get_schema_columns, query_get_schema_constraints, query_get_schema_indexes};
+use crate::domain::schema::{ColumnSchema, ConstraintSchema, TableSchema};
+use crate::domain::schema_diff::{DifferenceKind, SchemaDiff, SchemaDifference, SchemaObject};
+use crate::domain::two_db_error::TwoDbError;
+
+/// Read the schema of every table of the public schema, by table name
+pub fn get_schema(database_name: &str) -> Result<BTreeMap<String, TableSchema>, TwoDbError> {
+    let mut client = pg_connect(database_name)?;
+    let mut tables: BTreeMap<String, TableSchema> = BTreeMap::new();
+
+    let rows = client.query(query_get_schema_columns(), &[])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    for row in rows {
+        let table_name: String = row.get("table_name");
+        tables.entry(table_name.clone()).or_insert_with(|| TableSchema::new(table_name))
+            .columns.push(ColumnSchema {
+                name: row.get("column_name"),
+                data_type: row.get("data_type"),
+                is_nullable: row.get("is_nullable"),
+                default: row.get("column_default"),
+                identity_generation: row.get("identity_generation"),
+            });
+    }
+
+    let rows = client.query(query_get_schema_constraints(), &[])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    for row in rows {
+        let table_name: String = row.get("table_name");
+        let table = tables.entry(table_name.clone()).or_insert_with(|| TableSchema::new(table_name));
+        let constraint = ConstraintSchema {
+            name: row.get("constraint_name"),
+            definition: row.get("definition"),
+        };
+        match row.get::<_, String>("constraint_type").as_str() {
+            "p" => table.primary_key = Some(constraint),
+            "u" => table.unique_constraints.push(constraint),
+            _ => table.foreign_keys.push(constraint),
+        }
+    }
+
+    let rows = client.query(query_get_schema_indexes(), &[])
+        .map_err(|err| TwoDbError::catalog(database_name, err))?;
+    for row in rows {
+        let table_name: String = row.get("table_name");
+        tables.entry(table_name.clone()).or_insert_with(|| TableSchema::new(table_name))
+            .indexes.push(ConstraintSchema {
+                name: row.get("index_name"),
+                definition: index_definition(&row.get::<_, String>("definition")),
+            });
+    }
+
+    Ok(tables)
+}
+
+/// `CREATE UNIQUE INDEX name ON public.t USING btree (a)` becomes `UNIQUE ON public.t USING btree (a)`
+fn index_definition(definition: &str) -> String {
+    let unique = if definition.starts_with("CREATE UNIQUE") { "UNIQUE " } else { "" };
+    match definition.split_once(" ON ") {
+        Some((_, rest)) => format!("{}ON {}", unique, rest),
+        None => definition.to_string(),
+    }
+}
+
+/// Differences between two schemas, grouped by table in table name order
+pub fn diff_schemas(source: &BTreeMap<String, TableSchema>, target: &BTreeMap<String, TableSchema>) -> Vec<SchemaDifference> {
+    let mut table_names = source.keys().chain(target.keys()).collect::<Vec<_>>();
+    table_names.sort();
+    table_names.dedup();
+
+    let mut differences = Vec::new();
+    for table_name in table_names {
+        match (source.get(table_name), target.get(table_name)) {
+            (Some(source_table), Some(target_table)) => diff_tables(source_table, target_table, &mut differences),
+            (source_table, _) => {
+                let kind = if source_table.is_some() { DifferenceKind::OnlyInSource } else { DifferenceKind::OnlyInTarget };
+                differences.push(only_in(table_name, SchemaObject::Table, table_name, kind, table_name));
+            }
+        }
+    }
+    differences
+}
+
+fn diff_tables(source: &TableSchema, target: &TableSchema, differences: &mut Vec<SchemaDifference>) {
+    let table_name = &source.name;
+
+    for source_column in &source.columns {
+        let Some(target_column) = target.columns.iter().find(|c| c.name == source_column.name) else {
+            differences.push(only_in(table_name, SchemaObject::Column, &source_column.name, DifferenceKind::OnlyInSource, &source_column.data_type));
+            continue;
+        };
+        let column_difference = |kind, source_value: Option<String>, target_value: Option<String>| SchemaDifference {
+            table_name: table_name.clone(),
+            object: SchemaObject::Column,
+            object_name: source_column.name.clone(),
+            kind,
+            source_value,
+            target_value,
+        };
+        if source_column.data_type != target_column.data_type {
+            differences.push(column_difference(
+                DifferenceKind::DataType,
+                Some(source_column.data_type.clone()),
+                Some(target_column.data_type.clone()),
+            ));
+        }
+        if source_column.is_nullable != target_column.is_nullable {
+            differences.push(column_difference(
+                DifferenceKind::Nullability,
+                Some(nullability(source_column).to_string()),
+                Some(nullability(target_column).to_string()),
+            ));
+        }
+        if source_column.default != target_column.default {
+            differences.push(column_difference(
+                DifferenceKind::Default,
+                source_column.default.clone(),
+                target_column.default.clone(),
+            ));
+        }
+    }
+    for target_column in &target.columns {
+        if !source.columns.iter().any(|c| c.name == target_column.name) {
+            differences.push(only_in(table_name, SchemaObject::Column, &target_column.name, DifferenceKind::OnlyInTarget, &target_column.data_type));
+        }
+    }
+
+    match (&source.primary_key, &target.primary_key) {
+        (Some(source_key), Some(target_key)) if source_key.definition != target_key.definition => {
+            differences.push(SchemaDifference {
+                table_name: table_name.clone(),
+                object: SchemaObject::PrimaryKey,
+                object_name: target_key.name.clone(),
+                kind: DifferenceKind::Definition,
+                source_value: Some(source_key.definition.clone()),
+                target_value: Some(target_key.definition.clone()),
+            });
+        }
+        (Some(source_key), None) => {
+            differences.push(only_in(table_name, SchemaObject::PrimaryKey, &source_key.name, DifferenceKind::OnlyInSource, &source_key.definition));
+        }
+        (None, Some(target_key)) => {
+            differences.push(only_in(table_name, SchemaObject::PrimaryKey, &target_key.name, DifferenceKind::OnlyInTarget, &target_key.definition));
+        }
+        _ => {}
+    }
+
+    diff_by_definition(table_name, SchemaObject::UniqueConstraint, &source.unique_constraints, &target.unique_constraints, differences);
+    diff_by_definition(table_name, SchemaObject::ForeignKey, &source.foreign_keys, &target.foreign_keys, differences);
+    diff_by_definition(table_name, SchemaObject::Index, &source.indexes, &target.indexes, differences);
+}
+
+/// Constraints or indexes of one side without one of the same definition on the other side
+fn diff_by_definition(
+    table_name: &str,
+    object: SchemaObject,
+    source: &[ConstraintSchema],
+    target: &[ConstraintSchema],
+    differences: &mut Vec<SchemaDifference>,
+) {
+    for constraint in source {
+        if !target.iter().any(|c| c.definition == constraint.definition) {
+            differences.push(only_in(table_name, object, &constraint.name, DifferenceKind::OnlyInSource, &constraint.definition));
+        }
+    }
+    for constraint in target {
+        if !source.iter().any(|c| c.definition == constraint.definition) {
+            differences.push(only_in(table_name, object, &constraint.name, DifferenceKind::OnlyInTarget, &constraint.definition));
+        }
+    }
+}
+
+/// An object of one side only, `value` describes it on that side
+fn only_in(table_name: &str, object: SchemaObject, object_name: &str, kind: DifferenceKind, value: &str) -> SchemaDifference {
+    let (source_value, target_value) = match kind {
+        DifferenceKind::OnlyInTarget => (None, Some(value.to_string())),
+        _ => (Some(value.to_string()), None),
+    };
+    SchemaDifference {
+        table_name: table_name.to_string(),
+        object,
+        object_name: object_name.to_string(),
+        kind,
+        source_value,
+        target_value,
+    }
+}
+
+fn nullability(column: &ColumnSchema) -> &'static str {
+    if column.is_nullable { "NULL" } else { "NOT NULL" }
+}
+
+/// Compare the schemas of both databases and keep the result in SQLite
+pub fn compare_schemas(source_database_name: &str, target_database_name: &str) -> Result<SchemaDiff, TwoDbError> {
+    let source = get_schema(source_database_name)?;
+    let target = get_schema(target_database_name)?;
+    let schema_diff = SchemaDiff {
+        source_database: source_database_name.to_string(),
+        target_database: target_database_name.to_string(),
+        differences: diff_schemas(&source, &target),
+    };
+    save_schema_diff(&schema_diff)?;
+    Ok(schema_diff)
+}
+
+/// Replace the last comparison of these databases
+pub fn save_schema_diff(schema_diff: &SchemaDiff) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().save_schema_diff(schema_diff)
+}
+
+/// Last comparison of these databases, empty if they were never compared
+pub fn get_schema_diff(source_database_name: &str, target_database_name: &str) -> Result<SchemaDiff, TwoDbError> {
+    Ok(SchemaDiff {
+        source_database: source_database_name.to_string(),
+        target_database: target_database_name.to_string(),
+        differences: SqliteKnowledgeGateway::default().get_schema_differences(source_database_name, target_database_name)?,
+    })
+}
+
+/// Write the comparison as JSON
+pub fn export_schema_diff(schema_diff: &SchemaDiff, path: &str) -> Result<(), TwoDbError> {
+    let export_error = |message: String| TwoDbError::Export { path: path.to_string(), message };
+    let json = serde_json::to_string_pretty(schema_diff).map_err(|err| export_error(err.to_string()))?;
+    fs::write(path, json).map_err(|err| export_error(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, is_nullable: bool, default: Option<&str>) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable,
+            default: default.map(String::from),
+            identity_generation: None,
+        }
+    }
+
+    fn table(name: &str, columns: Vec<ColumnSchema>) -> TableSchema {
+        TableSchema { columns, ..TableSchema::new(name.to_string()) }
+    }
+
+    fn schema(tables: Vec<TableSchema>) -> BTreeMap<String, TableSchema> {
+        tables.into_iter().map(|table| (table.name.clone(), table)).collect()
+    }
+
+    fn difference(table_name: &str, object: SchemaObject, object_name: &str, kind: DifferenceKind, values: (Option<&str>, Option<&str>)) -> SchemaDifference {
+        SchemaDifference {
+            table_name: table_name.to_string(),
+            object,
+            object_name: object_name.to_string(),
+            kind,
+            source_value: values.0.map(String::from),
+            target_value: values.1.map(String::from),
+        }
+    }
+
+    #[test]
+    fn same_schemas_have_no_differences() {
+        let tables = schema(vec![table("item", vec![column("id", "integer", false, None)])]);
+        assert_eq!(diff_schemas(&tables, &tables.clone()), vec![]);
+    }
+
+    #[test]
+    fn tables_of_one_side_are_found_in_table_name_order() {
+        let source = schema(vec![table("b_only_source", vec![]), table("shared", vec![])]);
+        let target = schema(vec![table("a_only_target", vec![]), table("shared", vec![])]);
+        assert_eq!(diff_schemas(&source, &target), vec![
+            difference("a_only_target", SchemaObject::Table, "a_only_target", DifferenceKind::OnlyInTarget, (None, Some("a_only_target"))),
+            difference("b_only_source", SchemaObject::Table, "b_only_source", DifferenceKind::OnlyInSource, (Some("b_only_source"), None)),
+        ]);
+    }
+
+    #[test]
+    fn columns_of_one_side_are_found_with_their_type() {
+        let source = schema(vec![table("item", vec![column("id", "integer", false, None), column("legacy", "text", true, None)])]);
+        let target = schema(vec![table("item", vec![column("id", "integer", false, None), column("added", "jsonb", true, None)])]);
+        assert_eq!(diff_schemas(&source, &target), vec![
+            difference("item", SchemaObject::Column, "legacy", DifferenceKind::OnlyInSource, (Some("text"), None)),
+            difference("item", SchemaObject::Column, "added", DifferenceKind::OnlyInTarget, (None, Some("jsonb"))),
+        ]);
+    }
+
+    #[test]
+    fn changed_type_nullability_and_default_are_found() {
+        let source = schema(vec![table("item", vec![
+            column("price", "numeric(10,2)", true, None),
+            column("status", "text", false, Some("'new'::text")),
+        ])]);
+        let target = schema(vec![table("item", vec![
+            column("price", "double precision", false, None),
+            column("status", "text", false, None),
+        ])]);
+        assert_eq!(diff_schemas(&source, &target), vec![
+            difference("item", SchemaObject::Column, "price", DifferenceKind::DataType, (Some("numeric(10,2)"), Some("double precision"))),
+            difference("item", SchemaObject::Column, "price", DifferenceKind::Nullability, (Some("NULL"), Some("NOT NULL"))),
+            difference("item", SchemaObject::Column, "status", DifferenceKind::Default, (Some("'new'::text"), None)),
+        ]);
+    }
+
+    #[test]
+    fn constraints_are_matched_by_definition_not_name() {
+        let foreign_key = |name: &str, definition: &str| ConstraintSchema { name: name.to_string(), definition: definition.to_string() };
+        let mut source = table("child", vec![]);
+        source.foreign_keys = vec![
+            foreign_key("child_parent_id_fkey", "FOREIGN KEY (parent_id) REFERENCES parent(id)"),
+            foreign_key("child_owner_id_fkey", "FOREIGN KEY (owner_id) REFERENCES owner(id)"),
+        ];
+        let mut target = table("child", vec![]);
+        target.foreign_keys = vec![foreign_key("fk_other_name", "FOREIGN KEY (parent_id) REFERENCES parent(id)")];
+        assert_eq!(diff_schemas(&schema(vec![source]), &schema(vec![target])), vec![
+            difference(
+                "child", SchemaObject::ForeignKey, "child_owner_id_fkey", DifferenceKind::OnlyInSource,
+                (Some("FOREIGN KEY (owner_id) REFERENCES owner(id)"), None),
+            ),
+        ]);
+    }
+}
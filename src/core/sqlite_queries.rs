@@ -3,6 +3,41 @@ pub fn query_update_row_count() -> &'static str {
     "
         UPDATE tables
         SET row_count = ?1
-        WHERE name = ?2
+        WHERE name = ?2 AND database = ?3
     "
-}
\ No newline at end of file
+}
+
+/// SQL dialect: SQLite
+pub fn query_update_export_order() -> &'static str {
+    "
+        UPDATE tables
+        SET export_order = ?1
+        WHERE name = ?2 AND database = ?3
+    "
+}
+
+/// SQL dialect: SQLite
+pub fn query_get_running_migration_run() -> &'static str {
+    "
+        SELECT id
+        FROM migration_runs
+        WHERE source_database = ?1 AND target_database = ?2 AND status = ?3
+        ORDER BY id DESC
+        LIMIT 1
+    "
+}
+
+/// SQL dialect: SQLite
+///
+/// Keep one checkpoint per table, the latest wins
+pub fn query_upsert_table_checkpoint() -> &'static str {
+    "
+        INSERT INTO table_checkpoints (run_id, table_name, database, primary_key_column, last_primary_key)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT (table_name, database) DO UPDATE SET
+            run_id = excluded.run_id,
+            primary_key_column = excluded.primary_key_column,
+            last_primary_key = excluded.last_primary_key,
+            updated_at = CURRENT_TIMESTAMP
+    "
+}
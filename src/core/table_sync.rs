@@ -0,0 +1,171 @@
+/*! This file contains the incremental sync of tables already moved, only the rows changed since are copied.
+
+A table with a change column, e.g. `updated_at`, is synced by watermark: the rows whose change
+column went past the highest value synced so far. The watermark of its first sync is the highest
+value in the target. Rows with a NULL change column are never synced.
+Any other table is synced by primary key: the rows of the source missing from the target, or
+whose values differ from the target row with the same key, found with an anti-join in the target
+on the key and a hash of the row. Only the columns moved as they are go into the hash,
+transformed and filled columns are not compared. A row whose values print differently on both
+sides, e.g. a `timestamptz` in two time zones, is updated again at every sync, which is harmless.
+Rows deleted from the source stay in the target in both modes.
+*/
+
+use std::error::Error;
+use postgres::{Client, GenericClient};
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::core::action::checkpoint::quote_literal;
+use crate::core::action::copy::{copy_one_table, CopyFormat};
+use crate::core::get_knowledge::get_columns;
+use crate::core::row_filter::SourceRows;
+use crate::domain::table_sync::TableSync;
+use crate::domain::two_column::TwoColumn;
+use crate::domain::two_db_error::TwoDbError;
+
+/// Syncs of the tables of the source database
+pub fn get_table_syncs(database_name: &str) -> Result<Vec<TableSync>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_table_syncs_of_database(database_name)
+}
+
+/// Sync of a table, none if it has no change column and has never been synced
+pub fn get_table_sync(database_name: &str, table_name: &str) -> Result<Option<TableSync>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_table_sync(database_name, table_name)
+}
+
+/// Sync a table by watermark on `change_column` from now on, once the column is known in the source
+pub fn set_change_column(sync: &TableSync) -> Result<(), TwoDbError> {
+    if sync.database.is_empty() || sync.table_name.is_empty() || sync.change_column.is_empty() {
+        return Err(TwoDbError::Configuration(String::from("A change column needs a source table and a column")));
+    }
+    let columns = get_columns(&sync.database, &sync.table_name)?;
+    if !columns.iter().any(|c| c.name == sync.change_column) {
+        return Err(TwoDbError::Configuration(format!(
+            "Column {}.{} does not exist in {}, run update first if it was added",
+            sync.table_name, sync.change_column, sync.database
+        )));
+    }
+    SqliteKnowledgeGateway::default().upsert_change_column(sync)
+}
+
+/// Forget the change column and the watermark of a table, it is synced by primary key again
+pub fn delete_table_sync(id: i64) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().delete_table_sync(id)
+}
+
+/// Record a sync of a table, `watermark` is empty when it is synced by primary key
+pub fn save_watermark(database_name: &str, table_name: &str, watermark: &str) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().save_watermark(database_name, table_name, watermark)
+}
+
+/// Highest value of `column_name` in `relation`, as text, none if the relation is empty
+pub fn get_max_value(client: &mut impl GenericClient, relation: &str, column_name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let query = format!("SELECT MAX({column_name})::text FROM {relation} AS max_rows");
+    Ok(client.query_one(&query, &[])?.get(0))
+}
+
+/// Condition on `change_column` of the rows changed after `last` up to `next`, none if there are none
+///
+/// A first sync without `last` takes every row up to `next`.
+pub fn query_watermark_condition(change_column: &str, last: Option<&str>, next: Option<&str>) -> Option<String> {
+    match (last, next) {
+        (_, None) => None,
+        (Some(last), Some(next)) if last == next => None,
+        (Some(last), Some(next)) => Some(format!(
+            "{change_column} > {} AND {change_column} <= {}", quote_literal(last), quote_literal(next)
+        )),
+        (None, Some(next)) => Some(format!("{} <= {}", change_column, quote_literal(next))),
+    }
+}
+
+/// Hash of the values of `columns` of a row, the same on both sides for the same values
+pub fn query_row_hash(columns: &[&str]) -> String {
+    format!("md5(ROW({})::text)", columns.join(", "))
+}
+
+/// Copy the primary keys of the rows of the source missing from the target table, or changed,
+/// into the temporary table `twodb_changed_keys` of the source, return how many there are
+///
+/// `source_query` returns the keys, named like `primary_key`, and the hash of each row, named
+/// `twodb_row_hash`. They are copied into a temporary table of the target, which is then
+/// anti-joined with the target table on the key and `target_row_hash`, see `query_row_hash`.
+/// The temporary table of the target only lives in the transaction. The one of the source is
+/// read by the rows to sync, e.g. `WHERE id IN (SELECT id FROM twodb_changed_keys)`, and
+/// dropped with the SourceRows.
+pub fn copy_changed_keys(
+    source: &mut SourceRows,
+    target_client: &mut Client,
+    source_query: &str,
+    source_key: &str,
+    target_table_name: &str,
+    primary_key: &TwoColumn,
+    target_row_hash: &str,
+) -> Result<u64, Box<dyn Error>> {
+    let key = &primary_key.name;
+    let row_hash = TwoColumn {
+        name: String::from("twodb_row_hash"),
+        data_type: String::from("text"),
+        is_identity_always: false,
+        max_length: None,
+    };
+    let mut transaction = target_client.transaction()?;
+    transaction.batch_execute(&format!(
+        "CREATE TEMP TABLE twodb_source_keys ON COMMIT DROP AS
+        SELECT {key}, ''::text AS twodb_row_hash FROM {target_table_name} WITH NO DATA"
+    ))?;
+    copy_one_table(&mut *source.client, &mut transaction, "twodb_source_keys", source_query, &[primary_key, &row_hash], CopyFormat::Text)?;
+
+    // Typed like the key of the source, a table left by a failed drop is dropped before
+    source.drop_with("twodb_changed_keys");
+    source.client.batch_execute(&format!(
+        "DROP TABLE IF EXISTS twodb_changed_keys;
+        CREATE TEMP TABLE twodb_changed_keys AS SELECT {source_key} AS {key} FROM {} WITH NO DATA",
+        source.table_name
+    ))?;
+    let changed_query = format!(
+        "SELECT k.{key}
+        FROM twodb_source_keys AS k
+        WHERE NOT EXISTS (
+            SELECT 1 FROM {target_table_name} AS t WHERE t.{key} = k.{key} AND {target_row_hash} = k.twodb_row_hash
+        )"
+    );
+    let changed_count = copy_one_table(
+        &mut transaction, &mut *source.client, "twodb_changed_keys", &changed_query, &[primary_key], CopyFormat::Text,
+    )?;
+    transaction.commit()?;
+    Ok(changed_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermark_condition_takes_the_rows_after_the_last_sync() {
+        assert_eq!(
+            query_watermark_condition("updated_at", Some("2024-01-01 10:00:00"), Some("2024-02-01 08:30:00")).as_deref(),
+            Some("updated_at > '2024-01-01 10:00:00' AND updated_at <= '2024-02-01 08:30:00'"),
+        );
+        assert_eq!(query_watermark_condition("version", None, Some("42")).as_deref(), Some("version <= '42'"));
+    }
+
+    #[test]
+    fn watermark_condition_is_none_when_nothing_changed() {
+        assert_eq!(query_watermark_condition("version", Some("42"), Some("42")), None);
+        assert_eq!(query_watermark_condition("version", Some("42"), None), None);
+        assert_eq!(query_watermark_condition("version", None, None), None);
+    }
+
+    #[test]
+    fn watermark_condition_quotes_the_values() {
+        assert_eq!(
+            query_watermark_condition("code", Some("o'brien"), Some("zed")).as_deref(),
+            Some("code > 'o''brien' AND code <= 'zed'"),
+        );
+    }
+
+    #[test]
+    fn row_hash_covers_the_columns_in_order() {
+        assert_eq!(query_row_hash(&["id", "name"]), "md5(ROW(id, name)::text)");
+        assert_eq!(query_row_hash(&[]), "md5(ROW()::text)");
+    }
+}
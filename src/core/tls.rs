@@ -0,0 +1,188 @@
+/*! This file contains the TLS settings of a connection profile.
+
+The modes follow libpq:
+- `disable`: plain connection
+- `prefer`: TLS if the server supports it, the certificate is not checked, a plain connection
+  if the server does not offer TLS or the handshake fails
+- `require`: TLS, the certificate is only checked against `sslrootcert` when there is one
+- `verify-ca`: TLS, the certificate must be signed by a trusted root
+- `verify-full`: as `verify-ca`, and the certificate must match the host
+
+A self-signed server certificate is its own root, give it as `sslrootcert` with `verify-ca`.
+The client certificate `sslcert` and its key `sslkey` are PEM files, the key in PKCS#8
+(`openssl pkcs8 -topk8 -nocrypt -in client.key -out client.pk8`).
+*/
+
+use std::fs;
+use log::info;
+use native_tls::{Certificate, Identity, TlsConnector};
+use postgres::config::SslMode;
+use postgres::{Config, NoTls};
+use postgres_native_tls::MakeTlsConnector;
+use crate::core::config::{expand_home, ConnectionProfile};
+use crate::domain::two_db_error::TwoDbError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TlsMode {
+    Disable,
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl TlsMode {
+    pub fn name(&self) -> &str {
+        match self {
+            TlsMode::Disable => "disable",
+            TlsMode::Prefer => "prefer",
+            TlsMode::Require => "require",
+            TlsMode::VerifyCa => "verify-ca",
+            TlsMode::VerifyFull => "verify-full",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<TlsMode, TwoDbError> {
+        match name {
+            "disable" => Ok(TlsMode::Disable),
+            "prefer" => Ok(TlsMode::Prefer),
+            "require" => Ok(TlsMode::Require),
+            "verify-ca" => Ok(TlsMode::VerifyCa),
+            "verify-full" => Ok(TlsMode::VerifyFull),
+            other => Err(TwoDbError::Configuration(format!("Unknown sslmode: {}", other))),
+        }
+    }
+
+    /// What is negotiated with the server, the verification is done by the connector
+    pub fn ssl_mode(&self) -> SslMode {
+        match self {
+            TlsMode::Disable => SslMode::Disable,
+            TlsMode::Prefer => SslMode::Prefer,
+            TlsMode::Require | TlsMode::VerifyCa | TlsMode::VerifyFull => SslMode::Require,
+        }
+    }
+}
+
+/// Build the connector checking the server certificate as the profile asks
+pub fn make_tls_connector(profile: &ConnectionProfile) -> Result<MakeTlsConnector, TwoDbError> {
+    let mode = profile.tls_mode()?;
+    let mut builder = TlsConnector::builder();
+    let verify_certificate = match mode {
+        TlsMode::Disable | TlsMode::Prefer => false,
+        TlsMode::Require => profile.sslrootcert.is_some(),
+        TlsMode::VerifyCa | TlsMode::VerifyFull => true,
+    };
+
+    // Not read when the certificate is not checked, it cannot stop a `prefer` connection
+    if let Some(root_cert) = profile.sslrootcert.as_ref().filter(|_| verify_certificate) {
+        let pem = read_pem(root_cert)?;
+        let certificate = Certificate::from_pem(&pem)
+            .map_err(|err| TwoDbError::Configuration(format!("Invalid sslrootcert {}: {}", root_cert, err)))?;
+        builder.add_root_certificate(certificate);
+    }
+
+    match (&profile.sslcert, &profile.sslkey) {
+        (Some(cert), Some(key)) => {
+            let identity = Identity::from_pkcs8(&read_pem(cert)?, &read_pem(key)?)
+                .map_err(|err| TwoDbError::Configuration(format!("Invalid sslcert {} or sslkey {}: {}", cert, key, err)))?;
+            builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => return Err(TwoDbError::Configuration(String::from("sslcert and sslkey must be given together"))),
+    }
+
+    builder.danger_accept_invalid_certs(!verify_certificate);
+    builder.danger_accept_invalid_hostnames(mode != TlsMode::VerifyFull);
+
+    let connector = builder.build()
+        .map_err(|err| TwoDbError::Configuration(format!("Cannot build the TLS connector: {}", err)))?;
+    Ok(MakeTlsConnector::new(connector))
+}
+
+/// Connect without TLS in `prefer` mode when the TLS handshake fails, as libpq does
+///
+/// The driver only falls back when the server does not offer TLS. One connection is tried with
+/// TLS and, if it fails, one without, when the pool of the profile is built.
+pub fn fall_back_to_plaintext(mode: TlsMode, pg_config: &mut Config, tls: &MakeTlsConnector, database_name: &str) {
+    if mode != TlsMode::Prefer || pg_config.connect(tls.clone()).is_ok() {
+        return;
+    }
+    let mut plain_config = pg_config.clone();
+    plain_config.ssl_mode(SslMode::Disable);
+    if plain_config.connect(NoTls).is_ok() {
+        info!("Database {} is reached without TLS, the TLS connection failed", database_name);
+        pg_config.ssl_mode(SslMode::Disable);
+    }
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>, TwoDbError> {
+    let path = expand_home(path);
+    fs::read(&path)
+        .map_err(|err| TwoDbError::Configuration(format!("Cannot read {}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(settings: &str) -> ConnectionProfile {
+        toml::from_str(&format!("dbname = \"erp\"\n{}", settings)).unwrap()
+    }
+
+    fn connector_error(settings: &str) -> String {
+        match make_tls_connector(&profile(settings)) {
+            Err(TwoDbError::Configuration(message)) => message,
+            Err(err) => panic!("Not a configuration error: {}", err),
+            Ok(_) => panic!("The connector of {} is built", settings),
+        }
+    }
+
+    #[test]
+    fn parses_the_libpq_modes() {
+        for mode in [TlsMode::Disable, TlsMode::Prefer, TlsMode::Require, TlsMode::VerifyCa, TlsMode::VerifyFull] {
+            assert_eq!(TlsMode::from_name(mode.name()).unwrap(), mode);
+        }
+        assert!(matches!(TlsMode::from_name("allow"), Err(TwoDbError::Configuration(_))));
+        assert!(matches!(TlsMode::from_name("Require"), Err(TwoDbError::Configuration(_))));
+        assert_eq!(profile("").tls_mode().unwrap(), TlsMode::Prefer);
+        assert_eq!(TlsMode::Prefer.ssl_mode(), SslMode::Prefer);
+        assert_eq!(TlsMode::VerifyFull.ssl_mode(), SslMode::Require);
+    }
+
+    #[test]
+    fn rejects_a_missing_or_invalid_root_certificate() {
+        let missing = connector_error("sslmode = \"verify-ca\"\nsslrootcert = \"/nonexistent/root.crt\"");
+        assert!(missing.starts_with("Cannot read /nonexistent/root.crt"), "{}", missing);
+
+        let path = std::env::temp_dir().join(format!("twodb_invalid_root_{}.crt", std::process::id()));
+        fs::write(&path, "not a certificate").unwrap();
+        let invalid = connector_error(&format!("sslmode = \"verify-full\"\nsslrootcert = {:?}", path.display().to_string()));
+        fs::remove_file(&path).unwrap();
+        assert!(invalid.starts_with("Invalid sslrootcert"), "{}", invalid);
+    }
+
+    #[test]
+    fn prefer_ignores_the_root_certificate() {
+        assert!(make_tls_connector(&profile("sslmode = \"prefer\"\nsslrootcert = \"/nonexistent/root.crt\"")).is_ok());
+        assert!(make_tls_connector(&profile("sslmode = \"disable\"")).is_ok());
+    }
+
+    #[test]
+    fn needs_both_client_certificate_and_key() {
+        let message = connector_error("sslcert = \"client.crt\"");
+        assert_eq!(message, "sslcert and sslkey must be given together");
+    }
+
+    #[test]
+    fn only_prefer_falls_back_to_plaintext() {
+        // Nothing listens there, no mode other than prefer tries a connection
+        let tls = make_tls_connector(&profile("")).unwrap();
+        let mut pg_config = profile("host = \"127.0.0.1\"\nport = 1\nsslmode = \"require\"").pg_config().unwrap();
+        fall_back_to_plaintext(TlsMode::Require, &mut pg_config, &tls, "erp");
+        assert_eq!(pg_config.get_ssl_mode(), SslMode::Require);
+        // Neither connection works, prefer is kept
+        let mut pg_config = profile("host = \"127.0.0.1\"\nport = 1").pg_config().unwrap();
+        fall_back_to_plaintext(TlsMode::Prefer, &mut pg_config, &tls, "erp");
+        assert_eq!(pg_config.get_ssl_mode(), SslMode::Prefer);
+    }
+}
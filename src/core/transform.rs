@@ -0,0 +1,481 @@
+/*! This file contains the transformation of the values of a row between the source and the target.
+
+Rules are set by the user per source column and kept in the `transform_rules` table of the
+SQLite database, e.g. to mask emails and names when production data is copied to staging.
+FAKE and TOKENIZE rules are keyed with `TWODB_TRANSFORM_KEY`: the same source value gets the
+same result in every table and every run with the same key, and cannot be guessed without it.
+*/
+
+use std::env::var;
+use std::fmt::Write;
+use bytes::BytesMut;
+use hmac::{Hmac, Mac};
+use log::warn;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use crate::adapters::gateways::sqlite_knowledge_gateway::SqliteKnowledgeGateway;
+use crate::core::pg_types::numeric_to_sql;
+use crate::domain::transform_rule::{TransformKind, TransformRule, FAKE_CATEGORIES};
+use crate::domain::two_column::TwoColumn;
+use crate::domain::two_db_error::TwoDbError;
+use crate::domain::two_value::TwoValue;
+
+const FIRST_NAMES: [&str; 24] = [
+    "Alex", "Bella", "Carlos", "Diana", "Elias", "Fatima", "George", "Hana", "Ivan", "Julia", "Kenji", "Lena",
+    "Marco", "Nora", "Oscar", "Priya", "Quentin", "Rosa", "Samuel", "Tara", "Umar", "Vera", "Walter", "Yara",
+];
+
+const LAST_NAMES: [&str; 24] = [
+    "Anderson", "Baker", "Costa", "Dubois", "Evans", "Fischer", "Garcia", "Hansen", "Ito", "Jensen", "Kowalski", "Larsen",
+    "Moreau", "Nakamura", "Olsen", "Petrov", "Quinn", "Rossi", "Silva", "Tanaka", "Ueda", "Varga", "Weber", "Young",
+];
+
+const WORDS: [&str; 24] = [
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do", "eiusmod", "tempor",
+    "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim", "ad", "minim", "veniam", "quis",
+];
+
+/// Longest made up text, in words
+const MAX_FAKE_WORDS: usize = 50;
+
+const TEXT_TYPES: [&str; 3] = ["text", "character varying", "character"];
+
+/// Read the key of FAKE and TOKENIZE rules from `TWODB_TRANSFORM_KEY`
+fn get_transform_key() -> Option<String> {
+    var("TWODB_TRANSFORM_KEY").ok().filter(|key| !key.is_empty())
+}
+
+/// Rules of every table of the source database
+pub fn get_transform_rules(database_name: &str) -> Result<Vec<TransformRule>, TwoDbError> {
+    SqliteKnowledgeGateway::default().get_transform_rules_of_database(database_name)
+}
+
+/// Save a rule once the values it needs are set and valid
+pub fn add_transform_rule(rule: &TransformRule) -> Result<(), TwoDbError> {
+    let invalid = if rule.database.is_empty() || rule.table_name.is_empty() || rule.column_name.is_empty() {
+        Some(String::from("needs a source table and column"))
+    } else {
+        match rule.kind {
+            TransformKind::Fake if !FAKE_CATEGORIES.contains(&rule.value.as_str()) => {
+                Some(format!("needs a category among {}", FAKE_CATEGORIES.join(", ")))
+            }
+            TransformKind::Replace => Regex::new(&rule.value).err().map(|err| format!("has an invalid regular expression: {}", err)),
+            _ => None,
+        }
+    };
+    if let Some(invalid) = invalid {
+        return Err(TwoDbError::Configuration(format!("A {} rule {}", rule.kind.name(), invalid)));
+    }
+    SqliteKnowledgeGateway::default().insert_transform_rule(rule)
+}
+
+pub fn delete_transform_rule(id: i64) -> Result<(), TwoDbError> {
+    SqliteKnowledgeGateway::default().delete_transform_rule(id)
+}
+
+/// A rule ready to be applied to the values of one column
+#[derive(Debug)]
+enum ColumnTransform {
+    Hash,
+    Fake(String),
+    Null,
+    Fixed(TwoValue),
+    Replace(Regex, String),
+    Tokenize,
+}
+
+/// Target column of the rows and its transformation, if any
+#[derive(Debug)]
+struct TransformedColumn {
+    name: String,
+    transform: Option<ColumnTransform>,
+    /// Text made up for a `varchar(n)` column is cut to its length
+    max_length: Option<usize>,
+}
+
+/// Transformation of the rows of a table, one entry per column of the rows, in the same order
+#[derive(Debug)]
+pub struct RowTransform {
+    table_name: String,
+    columns: Vec<TransformedColumn>,
+    key: Option<Hmac<Sha256>>,
+}
+
+impl RowTransform {
+    /// True when every value is moved as it is
+    pub fn is_empty(&self) -> bool {
+        self.columns.iter().all(|column| column.transform.is_none())
+    }
+
+    /// True when the values of the target column `column_name` are changed
+    pub fn is_transformed(&self, column_name: &str) -> bool {
+        self.columns.iter().any(|column| column.name == column_name && column.transform.is_some())
+    }
+
+    /// Transform the values of a row, in the order of the columns
+    pub fn apply(&self, values: Vec<TwoValue>) -> Result<Vec<TwoValue>, TwoDbError> {
+        values.into_iter().zip(&self.columns).map(|(value, column)| match &column.transform {
+            Some(transform) => match self.transform_value(transform, value) {
+                Ok(TwoValue::Text(text)) => Ok(TwoValue::Text(truncate(text, column.max_length))),
+                Ok(value) => Ok(value),
+                Err(message) => Err(TwoDbError::Transfer {
+                    table: self.table_name.clone(),
+                    message: format!("Cannot transform column: {} \n Error: {}", column.name, message),
+                }),
+            },
+            None => Ok(value),
+        }).collect()
+    }
+
+    fn transform_value(&self, transform: &ColumnTransform, value: TwoValue) -> Result<TwoValue, String> {
+        if let ColumnTransform::Fixed(fixed) = transform {
+            return Ok(fixed.clone());
+        }
+        if value.is_null() || matches!(transform, ColumnTransform::Null) {
+            return Ok(TwoValue::Null);
+        }
+        match transform {
+            ColumnTransform::Hash => {
+                let digest = Sha256::digest(value.to_string().as_bytes());
+                Ok(TwoValue::Text(to_hex(&digest)))
+            }
+            ColumnTransform::Fake(category) => Ok(TwoValue::Text(self.fake(category, &value.to_string()))),
+            ColumnTransform::Replace(regex, replacement) => {
+                let text = value.to_string();
+                Ok(TwoValue::Text(regex.replace_all(&text, replacement.as_str()).into_owned()))
+            }
+            ColumnTransform::Tokenize => self.tokenize(value),
+            ColumnTransform::Null | ColumnTransform::Fixed(_) => unreachable!(),
+        }
+    }
+
+    /// HMAC-SHA256 of `parts` with the key, checked when the transform was built
+    fn keyed_hash(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut mac = self.key.clone().expect("transform key");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    fn fake(&self, category: &str, text: &str) -> String {
+        let seed = self.keyed_hash(&[b"fake", category.as_bytes(), text.as_bytes()]);
+        let pick = |i: usize, words: &[&'static str]| words[usize::from(u16::from_be_bytes([seed[2 * i], seed[2 * i + 1]])) % words.len()];
+        let number = u32::from_be_bytes([seed[28], seed[29], seed[30], seed[31]]);
+        match category {
+            "first_name" => pick(0, &FIRST_NAMES).to_string(),
+            "last_name" => pick(1, &LAST_NAMES).to_string(),
+            // 40 more bits of the hash, so a unique column of millions of emails stays unique
+            "email" => format!(
+                "{}.{}.{}@example.com",
+                pick(0, &FIRST_NAMES).to_lowercase(), pick(1, &LAST_NAMES).to_lowercase(), to_hex(&seed[20..25])
+            ),
+            "phone" => format!("+1 555 {:03} {:04}", number / 10_000 % 1_000, number % 10_000),
+            // As many words as the source text, the words repeat after 14
+            "text" => {
+                let count = text.split_whitespace().count().clamp(1, MAX_FAKE_WORDS);
+                (0..count).map(|i| pick(i % 14, &WORDS)).collect::<Vec<_>>().join(" ")
+            }
+            _ => format!("{} {}", pick(0, &FIRST_NAMES), pick(1, &LAST_NAMES)),
+        }
+    }
+
+    /// A token of the same type, integers are permuted within their range so keys stay unique
+    fn tokenize(&self, value: TwoValue) -> Result<TwoValue, String> {
+        match value {
+            TwoValue::Int2(value) => Ok(TwoValue::Int2(self.tokenize_integer(i64::from(value)) as i16)),
+            TwoValue::Int4(value) => Ok(TwoValue::Int4(self.tokenize_integer(i64::from(value)) as i32)),
+            TwoValue::Int8(value) => Ok(TwoValue::Int8(self.tokenize_integer(value))),
+            TwoValue::Uuid(value) => {
+                let hash = self.keyed_hash(&[b"uuid", &value]);
+                let mut token = [0u8; 16];
+                token.copy_from_slice(&hash[..16]);
+                // Version 4, variant RFC 4122
+                token[6] = (token[6] & 0x0f) | 0x40;
+                token[8] = (token[8] & 0x3f) | 0x80;
+                Ok(TwoValue::Uuid(token))
+            }
+            TwoValue::Text(value) => Ok(TwoValue::Text(format!("tok_{}", to_hex(&self.keyed_hash(&[b"text", value.as_bytes()])[..12])))),
+            value => Err(format!("TOKENIZE is not supported for {:?}", value)),
+        }
+    }
+
+    /// Keyed permutation of the integers, the same whatever the type of the column
+    ///
+    /// Integers keep their sign and their band: below 2^15, below 2^31, or above,
+    /// so an int2 or int4 key gets a token of its type, equal to the token of an int8 foreign key.
+    /// A Feistel network permutes every pattern of the bits of the band, it is applied again
+    /// until the result is in the band, the cycle of `value` always comes back to it.
+    fn tokenize_integer(&self, value: i64) -> i64 {
+        if value < 0 {
+            return !self.tokenize_integer(!value);
+        }
+        let value = value as u64;
+        let (bits, lower) = match value {
+            0..=0x7fff => (16, 0),
+            0x8000..=0x7fff_ffff => (32, 0x8000),
+            _ => (64, 0x8000_0000),
+        };
+        let limit = 1u64 << (bits - 1);
+        let mut token = value;
+        loop {
+            token = self.permute(token, bits);
+            if (lower..limit).contains(&token) {
+                return token as i64;
+            }
+        }
+    }
+
+    fn permute(&self, value: u64, bits: u32) -> u64 {
+        let half = bits / 2;
+        let mask = (1u64 << half) - 1;
+        let (mut left, mut right) = ((value >> half) & mask, value & mask);
+        for round in 0..4u8 {
+            let hash = self.keyed_hash(&[b"feistel", &[bits as u8, round], &right.to_be_bytes()]);
+            let f = u64::from_be_bytes(hash[..8].try_into().expect("8 bytes")) & mask;
+            (left, right) = (right, left ^ f);
+        }
+        (left << half) | right
+    }
+}
+
+/// The first `max_length` characters of `text`
+fn truncate(mut text: String, max_length: Option<usize>) -> String {
+    if let Some((end, _)) = max_length.and_then(|max_length| text.char_indices().nth(max_length)) {
+        text.truncate(end);
+    }
+    text
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// The value of a FIXED rule in the type of its target column
+fn parse_fixed_value(value: &str, column: &TwoColumn) -> Option<TwoValue> {
+    match column.data_type.as_str() {
+        "smallint" => value.parse().ok().map(TwoValue::Int2),
+        "integer" => value.parse().ok().map(TwoValue::Int4),
+        "bigint" => value.parse().ok().map(TwoValue::Int8),
+        "boolean" => value.parse().ok().map(TwoValue::Bool),
+        // Validated like it is sent, e.g. 1e5 or inf are not numerics
+        "numeric" => numeric_to_sql(value, &mut BytesMut::new()).ok().map(|_| TwoValue::Numeric(value.to_string())),
+        "json" | "jsonb" => Some(TwoValue::Json(value.to_string())),
+        "USER-DEFINED" => Some(TwoValue::Enum(value.to_string())),
+        data_type if TEXT_TYPES.contains(&data_type) => Some(TwoValue::Text(value.to_string())),
+        _ => None,
+    }
+}
+
+/// Check that the rule can write into `column` and get it ready
+fn build_column_transform(rule: &TransformRule, column: &TwoColumn) -> Result<ColumnTransform, String> {
+    let data_type = column.data_type.as_str();
+    let needs_text = matches!(rule.kind, TransformKind::Hash | TransformKind::Fake | TransformKind::Replace);
+    if needs_text && !TEXT_TYPES.contains(&data_type) {
+        return Err(format!("{} needs a text column", rule.kind.name()));
+    }
+    let can_tokenize = ["smallint", "integer", "bigint", "uuid"].contains(&data_type) || TEXT_TYPES.contains(&data_type);
+    if rule.kind == TransformKind::Tokenize && !can_tokenize {
+        return Err(String::from("TOKENIZE needs an integer, uuid or text column"));
+    }
+    match rule.kind {
+        TransformKind::Hash => Ok(ColumnTransform::Hash),
+        TransformKind::Fake => Ok(ColumnTransform::Fake(rule.value.clone())),
+        TransformKind::Null => Ok(ColumnTransform::Null),
+        TransformKind::Fixed => {
+            let fixed = parse_fixed_value(&rule.value, column)
+                .ok_or_else(|| format!("{} is not a valid value of type {}", rule.value, data_type))?;
+            match (&fixed, column.max_length) {
+                (TwoValue::Text(text), Some(max_length)) if text.chars().count() > max_length as usize => {
+                    Err(format!("{} is longer than the {} characters of the column", text, max_length))
+                }
+                _ => Ok(ColumnTransform::Fixed(fixed)),
+            }
+        }
+        TransformKind::Replace => Regex::new(&rule.value)
+            .map(|regex| ColumnTransform::Replace(regex, rule.replacement.clone()))
+            .map_err(|err| err.to_string()),
+        TransformKind::Tokenize => Ok(ColumnTransform::Tokenize),
+    }
+}
+
+/// Transformation of the rows of `table_name` of the source database
+///
+/// `columns` are the columns of the rows: the source column the values are read from,
+/// and the target column they are written to. The last rule of a column wins.
+pub fn get_row_transform(database_name: &str, table_name: &str, columns: &[(&str, &TwoColumn)]) -> Result<RowTransform, TwoDbError> {
+    let rules = SqliteKnowledgeGateway::default().get_transform_rules(database_name, table_name)?;
+    for rule in &rules {
+        if !columns.iter().any(|(source_column, _)| *source_column == rule.column_name) {
+            warn!("Column {}.{} is not moved, its {} rule is not used", table_name, rule.column_name, rule.kind.name());
+        }
+    }
+
+    let mut transforms = Vec::new();
+    for (source_column, target_column) in columns {
+        let transform = match rules.iter().rev().find(|rule| rule.column_name == *source_column) {
+            Some(rule) => Some(build_column_transform(rule, target_column).map_err(|message| {
+                TwoDbError::Configuration(format!("Column {}.{}: {}", table_name, source_column, message))
+            })?),
+            None => None,
+        };
+        transforms.push(TransformedColumn {
+            name: target_column.name.clone(),
+            transform,
+            max_length: target_column.max_length.map(|max_length| max_length as usize),
+        });
+    }
+
+    let needs_key = transforms.iter().any(|column| matches!(column.transform, Some(ColumnTransform::Fake(_) | ColumnTransform::Tokenize)));
+    let key = match get_transform_key() {
+        Some(key) => Some(Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any size")),
+        None if needs_key => {
+            let message = format!("Table {} has FAKE or TOKENIZE rules, set TWODB_TRANSFORM_KEY", table_name);
+            return Err(TwoDbError::Configuration(message));
+        }
+        None => None,
+    };
+    Ok(RowTransform {
+        table_name: table_name.to_string(),
+        columns: transforms,
+        key,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::*;
+
+    fn transformer(key: &str, transform: ColumnTransform, max_length: Option<usize>) -> RowTransform {
+        RowTransform {
+            table_name: String::from("tokens"),
+            columns: vec![TransformedColumn { name: String::from("id"), transform: Some(transform), max_length }],
+            key: Some(Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap()),
+        }
+    }
+
+    fn tokenizer(key: &str) -> RowTransform {
+        transformer(key, ColumnTransform::Tokenize, None)
+    }
+
+    fn column(data_type: &str, max_length: Option<i32>) -> TwoColumn {
+        TwoColumn { name: String::from("value"), data_type: data_type.to_string(), is_identity_always: false, max_length }
+    }
+
+    #[test]
+    fn tokenize_permutes_every_smallint_within_its_sign() {
+        let transform = tokenizer("test key");
+        let mut tokens = HashSet::new();
+        for value in i16::MIN..=i16::MAX {
+            let token = transform.tokenize_integer(i64::from(value));
+            assert!((i64::from(i16::MIN)..=i64::from(i16::MAX)).contains(&token), "{} -> {} is out of range", value, token);
+            assert_eq!(token < 0, value < 0, "{} -> {} changed sign", value, token);
+            assert!(tokens.insert(token), "{} -> {} collides", value, token);
+        }
+    }
+
+    #[test]
+    fn tokenize_keeps_integers_in_range_without_collisions() {
+        let transform = tokenizer("test key");
+        let borders = [i64::from(i16::MIN), i64::from(i16::MAX), i64::from(i32::MIN), i64::from(i32::MAX), i64::MIN, i64::MAX];
+        let values = (-5_000..5_000)
+            .chain(borders.iter().flat_map(|&border| [border.saturating_sub(1), border, border.saturating_add(1)]))
+            .collect::<HashSet<i64>>();
+        let mut tokens = HashSet::new();
+        for &value in &values {
+            let token = transform.tokenize_integer(value);
+            assert_eq!(i16::try_from(token).is_ok(), i16::try_from(value).is_ok(), "{} -> {} left the int2 range", value, token);
+            assert_eq!(i32::try_from(token).is_ok(), i32::try_from(value).is_ok(), "{} -> {} left the int4 range", value, token);
+            assert_eq!(token < 0, value < 0, "{} -> {} changed sign", value, token);
+            assert!(tokens.insert(token), "{} -> {} collides", value, token);
+        }
+    }
+
+    #[test]
+    fn tokenize_gives_the_same_token_to_every_integer_type() {
+        let transform = tokenizer("test key");
+        for value in [7i16, -42, 12_345] {
+            let TwoValue::Int8(token) = transform.tokenize(TwoValue::Int8(i64::from(value))).unwrap() else { panic!() };
+            assert_eq!(transform.tokenize(TwoValue::Int2(value)).unwrap(), TwoValue::Int2(token as i16));
+            assert_eq!(transform.tokenize(TwoValue::Int4(i32::from(value))).unwrap(), TwoValue::Int4(token as i32));
+        }
+        for value in [100_000, -3_000_000] {
+            let TwoValue::Int8(token) = transform.tokenize(TwoValue::Int8(i64::from(value))).unwrap() else { panic!() };
+            assert_eq!(transform.tokenize(TwoValue::Int4(value)).unwrap(), TwoValue::Int4(token as i32));
+        }
+    }
+
+    #[test]
+    fn tokenize_is_deterministic_for_a_key() {
+        let first = tokenizer("test key");
+        let second = tokenizer("test key");
+        let other = tokenizer("other key");
+        let values = [TwoValue::Int2(7), TwoValue::Int4(-42), TwoValue::Int8(1 << 40), TwoValue::Text(String::from("abc"))];
+        for value in values {
+            let token = first.tokenize(value.clone()).unwrap();
+            assert_eq!(token, second.tokenize(value.clone()).unwrap());
+            assert_ne!(token, other.tokenize(value).unwrap());
+        }
+    }
+
+    #[test]
+    fn fake_emails_stay_unique_for_a_million_rows() {
+        let transform = transformer("test key", ColumnTransform::Fake(String::from("email")), None);
+        let mut emails = HashSet::new();
+        for i in 0..1_000_000 {
+            let email = transform.fake("email", &format!("user{}@corp.example", i));
+            assert!(emails.insert(email.clone()), "user{} gets {} again", i, email);
+        }
+    }
+
+    #[test]
+    fn made_up_text_is_cut_to_the_column_length() {
+        for transform in [ColumnTransform::Fake(String::from("email")), ColumnTransform::Tokenize, ColumnTransform::Hash] {
+            let transform = transformer("test key", transform, Some(10));
+            let values = transform.apply(vec![TwoValue::Text(String::from("jane.doe@corp.example"))]).unwrap();
+            let [TwoValue::Text(text)] = values.as_slice() else { panic!("{:?}", values) };
+            assert_eq!(text.chars().count(), 10, "{}", text);
+        }
+        assert_eq!(truncate(String::from("héllo wörld"), Some(7)), "héllo w");
+        assert_eq!(truncate(String::from("short"), Some(10)), "short");
+        assert_eq!(truncate(String::from("no limit"), None), "no limit");
+    }
+
+    #[test]
+    fn parse_fixed_value_checks_the_type_of_the_column() {
+        assert_eq!(parse_fixed_value("12", &column("smallint", None)), Some(TwoValue::Int2(12)));
+        assert_eq!(parse_fixed_value("40000", &column("smallint", None)), None);
+        assert_eq!(parse_fixed_value("-7", &column("integer", None)), Some(TwoValue::Int4(-7)));
+        assert_eq!(parse_fixed_value("1.5", &column("integer", None)), None);
+        assert_eq!(parse_fixed_value("9000000000", &column("bigint", None)), Some(TwoValue::Int8(9_000_000_000)));
+        assert_eq!(parse_fixed_value("true", &column("boolean", None)), Some(TwoValue::Bool(true)));
+        assert_eq!(parse_fixed_value("yes", &column("boolean", None)), None);
+        assert_eq!(parse_fixed_value("12.50", &column("numeric", None)), Some(TwoValue::Numeric(String::from("12.50"))));
+        assert_eq!(parse_fixed_value("1e5", &column("numeric", None)), None);
+        assert_eq!(parse_fixed_value("abc", &column("numeric", None)), None);
+        assert_eq!(parse_fixed_value("{\"a\": 1}", &column("jsonb", None)), Some(TwoValue::Json(String::from("{\"a\": 1}"))));
+        assert_eq!(parse_fixed_value("redacted", &column("character varying", Some(20))), Some(TwoValue::Text(String::from("redacted"))));
+        assert_eq!(parse_fixed_value("2024-01-01", &column("date", None)), None);
+    }
+
+    #[test]
+    fn fixed_text_longer_than_the_column_is_rejected() {
+        let rule = TransformRule { kind: TransformKind::Fixed, value: String::from("redacted"), ..Default::default() };
+        assert!(build_column_transform(&rule, &column("character varying", Some(8))).is_ok());
+        assert!(build_column_transform(&rule, &column("character varying", Some(5))).is_err());
+        assert!(build_column_transform(&rule, &column("text", None)).is_ok());
+    }
+
+    #[test]
+    fn transform_kind_names_round_trip() {
+        for kind in TransformKind::ALL {
+            assert_eq!(TransformKind::from_name(kind.name()), Some(kind));
+        }
+        assert_eq!(TransformKind::from_name("MASK"), None);
+        assert_eq!(TransformKind::from_name("hash"), None);
+    }
+}
@@ -0,0 +1,73 @@
+/*! This file contains the DdlScript entity, the statements that bring the target schema in line with the source. */
+
+use std::collections::BTreeSet;
+
+/// One statement, without its trailing semicolon
+#[derive(Debug, Clone, PartialEq)]
+pub struct DdlStatement {
+    /// Table changed by the statement
+    pub table_name: String,
+    pub sql: String,
+    /// Drops a constraint or an index of the target only, see `DdlScript::allows_destructive`
+    pub is_destructive: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DdlScript {
+    pub source_database: String,
+    pub target_database: String,
+    /// In the order they must run, e.g. tables before the foreign keys referencing them
+    pub statements: Vec<DdlStatement>,
+    /// What the script deliberately leaves alone, e.g. tables only in the target
+    pub notes: Vec<String>,
+    /// Destructive statements are run, otherwise they are written commented out and never run
+    pub allows_destructive: bool,
+}
+
+impl DdlScript {
+    /// Statements run on the target, in order
+    pub fn statements_to_run(&self) -> impl Iterator<Item = &DdlStatement> {
+        self.statements.iter().filter(|statement| self.allows_destructive || !statement.is_destructive)
+    }
+
+    /// Destructive statements left out, written commented out
+    pub fn statements_left_out(&self) -> impl Iterator<Item = &DdlStatement> {
+        self.statements.iter().filter(|statement| !self.allows_destructive && statement.is_destructive)
+    }
+
+    /// Names of the tables changed by the script
+    pub fn table_names(&self) -> BTreeSet<&str> {
+        self.statements.iter().map(|s| s.table_name.as_str()).collect()
+    }
+
+    /// The script as a `.sql` file, run by `psql -f` in a single transaction
+    pub fn to_sql(&self) -> String {
+        let mut sql = format!(
+            "-- Bring the schema of {} in line with the schema of {}\n-- Generated by twodb, review it before running it\n",
+            self.target_database, self.source_database,
+        );
+        for note in &self.notes {
+            sql.push_str(&format!("-- Note: {}\n", note));
+        }
+        if self.statements.is_empty() {
+            sql.push_str("-- Nothing to change\n");
+            return sql;
+        }
+
+        sql.push_str("\nBEGIN;\n\n");
+        for statement in &self.statements {
+            if !self.allows_destructive && statement.is_destructive {
+                sql.push_str("-- Destructive, left out, uncomment it or generate the script with destructive statements\n");
+                for line in statement.sql.lines() {
+                    sql.push_str(&format!("-- {}\n", line));
+                }
+                sql.push_str("-- ;\n\n");
+                continue;
+            }
+            sql.push_str(&statement.sql);
+            sql.push_str(";\n\n");
+        }
+        sql.push_str("COMMIT;\n");
+        sql
+    }
+}
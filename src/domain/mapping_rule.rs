@@ -0,0 +1,267 @@
+/*! This file contains the MappingRule entity, how a source table and its columns map to the target. */
+
+use crate::domain::two_column::TwoColumn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MappingRuleKind {
+    /// Rows of the source table go to the target table `target_name`
+    #[default]
+    RenameTable,
+    /// Values of the source column `column_name` go to the target column `target_name`
+    RenameColumn,
+    /// The target column `target_name` gets `value` in every row
+    Constant,
+    /// The target column `target_name` gets the SQL expression `value`, e.g. `now()`,
+    /// evaluated by the target like a column default
+    Default,
+    /// The source column `column_name` is not moved
+    Exclude,
+}
+
+impl MappingRuleKind {
+    pub const ALL: [MappingRuleKind; 5] = [
+        MappingRuleKind::RenameTable,
+        MappingRuleKind::RenameColumn,
+        MappingRuleKind::Constant,
+        MappingRuleKind::Default,
+        MappingRuleKind::Exclude,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            MappingRuleKind::RenameTable => "RENAME TABLE",
+            MappingRuleKind::RenameColumn => "RENAME COLUMN",
+            MappingRuleKind::Constant => "CONSTANT",
+            MappingRuleKind::Default => "DEFAULT",
+            MappingRuleKind::Exclude => "EXCLUDE",
+        }
+    }
+
+    pub fn from_name(name: &str) -> MappingRuleKind {
+        match name {
+            "RENAME COLUMN" => MappingRuleKind::RenameColumn,
+            "CONSTANT" => MappingRuleKind::Constant,
+            "DEFAULT" => MappingRuleKind::Default,
+            "EXCLUDE" => MappingRuleKind::Exclude,
+            _ => MappingRuleKind::RenameTable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MappingRule {
+    pub id: i64,
+    /// Source database
+    pub database: String,
+    /// Source table
+    pub table_name: String,
+    pub kind: MappingRuleKind,
+    /// Source column, for RenameColumn and Exclude
+    pub column_name: String,
+    /// Target table for RenameTable, target column otherwise
+    pub target_name: String,
+    /// For Constant and Default
+    pub value: String,
+}
+
+/// Where the values of a target column come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSource {
+    /// A column of the source table
+    Column(String),
+    /// The same value in every row
+    Constant(String),
+    /// A SQL expression evaluated by the target
+    Default(String),
+}
+
+/// A column of the target table filled by the move
+#[derive(Debug)]
+pub struct MappedColumn {
+    pub source: ColumnSource,
+    pub target: TwoColumn,
+}
+
+/// The rules of one source table, a table without rules maps to itself
+///
+/// When rules overlap, the last one added wins, like transform rules
+#[derive(Debug, Clone, Default)]
+pub struct TableMapping {
+    pub table_name: String,
+    pub rules: Vec<MappingRule>,
+}
+
+impl TableMapping {
+    pub fn new(table_name: &str, rules: Vec<MappingRule>) -> Self {
+        Self {
+            table_name: table_name.to_string(),
+            rules,
+        }
+    }
+
+    fn rules_of(&self, kind: MappingRuleKind) -> impl Iterator<Item = &MappingRule> {
+        self.rules.iter().filter(move |rule| rule.kind == kind)
+    }
+
+    /// Name of the table in the target
+    pub fn target_table(&self) -> &str {
+        self.rules_of(MappingRuleKind::RenameTable)
+            .last()
+            .map_or(self.table_name.as_str(), |rule| rule.target_name.as_str())
+    }
+
+    /// Name in the target of a source column, none if it is excluded
+    pub fn target_column<'a>(&'a self, column_name: &'a str) -> Option<&'a str> {
+        if self.rules_of(MappingRuleKind::Exclude).any(|rule| rule.column_name == column_name) {
+            return None;
+        }
+        let renamed = self.rules_of(MappingRuleKind::RenameColumn).filter(|rule| rule.column_name == column_name).last();
+        Some(renamed.map_or(column_name, |rule| rule.target_name.as_str()))
+    }
+
+    /// Source of a target column, a column of the same name unless a rule says otherwise
+    ///
+    /// The source column may not exist, see `map_columns`
+    pub fn column_source(&self, target_column: &str) -> Option<ColumnSource> {
+        let fill = self.rules.iter().rev().find(|rule| {
+            matches!(rule.kind, MappingRuleKind::Constant | MappingRuleKind::Default) && rule.target_name == target_column
+        });
+        match fill {
+            Some(rule) if rule.kind == MappingRuleKind::Constant => return Some(ColumnSource::Constant(rule.value.clone())),
+            Some(rule) => return Some(ColumnSource::Default(rule.value.clone())),
+            None => {}
+        }
+        let renamed = self.rules_of(MappingRuleKind::RenameColumn).filter(|rule| rule.target_name == target_column).last();
+        if let Some(rule) = renamed {
+            // The source column may have been renamed again to another target
+            return (self.target_column(&rule.column_name) == Some(target_column)).then(|| ColumnSource::Column(rule.column_name.clone()));
+        }
+        // A source column of the same name renamed or excluded does not go there
+        (self.target_column(target_column) == Some(target_column)).then(|| ColumnSource::Column(target_column.to_string()))
+    }
+
+    /// A rule `rule` cannot be added next to: a second rename of the table or of a source column,
+    /// or another rule filling the same target column
+    pub fn conflicting_rule(&self, rule: &MappingRule) -> Option<&MappingRule> {
+        let fills_column = |kind: MappingRuleKind| {
+            matches!(kind, MappingRuleKind::RenameColumn | MappingRuleKind::Constant | MappingRuleKind::Default)
+        };
+        self.rules.iter().find(|other| match rule.kind {
+            MappingRuleKind::RenameTable => other.kind == MappingRuleKind::RenameTable,
+            MappingRuleKind::Exclude => false,
+            _ => {
+                (rule.kind == MappingRuleKind::RenameColumn && other.kind == rule.kind && other.column_name == rule.column_name)
+                    || (fills_column(other.kind) && other.target_name == rule.target_name)
+            }
+        })
+    }
+
+    /// Target columns filled by the move, in target order
+    pub fn map_columns(&self, source_columns: &[TwoColumn], target_columns: Vec<TwoColumn>) -> Vec<MappedColumn> {
+        target_columns.into_iter().filter_map(|target| {
+            let source = self.column_source(&target.name)?;
+            if let ColumnSource::Column(name) = &source {
+                if !source_columns.iter().any(|c| &c.name == name) {
+                    return None;
+                }
+            }
+            Some(MappedColumn { source, target })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: MappingRuleKind, column_name: &str, target_name: &str) -> MappingRule {
+        MappingRule { kind, column_name: column_name.to_string(), target_name: target_name.to_string(), ..Default::default() }
+    }
+
+    fn columns(names: &[&str]) -> Vec<TwoColumn> {
+        names.iter().map(|name| TwoColumn {
+            name: name.to_string(),
+            data_type: String::from("text"),
+            is_identity_always: false,
+            max_length: None,
+        }).collect()
+    }
+
+    /// Target column and where its values come from, for the target columns filled
+    fn mapped(mapping: &TableMapping, source: &[&str], target: &[&str]) -> Vec<(String, ColumnSource)> {
+        mapping.map_columns(&columns(source), columns(target))
+            .into_iter()
+            .map(|column| (column.target.name, column.source))
+            .collect()
+    }
+
+    fn from_column(target: &str, source: &str) -> (String, ColumnSource) {
+        (target.to_string(), ColumnSource::Column(source.to_string()))
+    }
+
+    #[test]
+    fn columns_without_rules_map_to_themselves() {
+        let mapping = TableMapping::new("item", Vec::new());
+        assert_eq!(mapping.target_table(), "item");
+        assert_eq!(mapped(&mapping, &["id", "name"], &["id", "name", "extra"]), vec![from_column("id", "id"), from_column("name", "name")]);
+    }
+
+    #[test]
+    fn follows_rename_chains() {
+        // a goes to b while b goes to c, as a swap would
+        let mapping = TableMapping::new("item", vec![
+            rule(MappingRuleKind::RenameColumn, "a", "b"),
+            rule(MappingRuleKind::RenameColumn, "b", "c"),
+        ]);
+        assert_eq!(mapping.target_column("a"), Some("b"));
+        assert_eq!(mapping.target_column("b"), Some("c"));
+        assert_eq!(mapped(&mapping, &["a", "b"], &["a", "b", "c"]), vec![from_column("b", "a"), from_column("c", "b")]);
+
+        // Renamed again, only the last rename counts
+        let mapping = TableMapping::new("item", vec![
+            rule(MappingRuleKind::RenameColumn, "a", "b"),
+            rule(MappingRuleKind::RenameColumn, "a", "c"),
+        ]);
+        assert_eq!(mapping.column_source("b"), None);
+        assert_eq!(mapping.column_source("c"), Some(ColumnSource::Column(String::from("a"))));
+    }
+
+    #[test]
+    fn dropped_columns_are_not_moved() {
+        let mapping = TableMapping::new("item", vec![rule(MappingRuleKind::Exclude, "secret", "")]);
+        assert_eq!(mapping.target_column("secret"), None);
+        assert_eq!(mapped(&mapping, &["id", "secret"], &["id", "secret"]), vec![from_column("id", "id")]);
+
+        // A column of the source that is gone, the target column is left to its default
+        let mapping = TableMapping::new("item", vec![rule(MappingRuleKind::RenameColumn, "old", "new")]);
+        assert_eq!(mapped(&mapping, &["id"], &["id", "new"]), vec![from_column("id", "id")]);
+    }
+
+    #[test]
+    fn rules_win_over_columns_of_the_same_name() {
+        let mut constant = rule(MappingRuleKind::Constant, "", "status");
+        constant.value = String::from("active");
+        let mapping = TableMapping::new("item", vec![rule(MappingRuleKind::RenameColumn, "label", "name"), constant]);
+        assert_eq!(mapped(&mapping, &["label", "name", "status"], &["label", "name", "status"]), vec![
+            from_column("name", "label"),
+            (String::from("status"), ColumnSource::Constant(String::from("active"))),
+        ]);
+    }
+
+    #[test]
+    fn rejects_rules_filling_a_target_column_twice() {
+        let mapping = TableMapping::new("item", vec![
+            rule(MappingRuleKind::RenameTable, "", "items"),
+            rule(MappingRuleKind::RenameColumn, "a", "b"),
+            rule(MappingRuleKind::Default, "", "created_at"),
+        ]);
+        let conflicts = |rule: MappingRule| mapping.conflicting_rule(&rule).map(|other| other.target_name.clone());
+        assert_eq!(conflicts(rule(MappingRuleKind::RenameTable, "", "things")).as_deref(), Some("items"));
+        assert_eq!(conflicts(rule(MappingRuleKind::RenameColumn, "a", "c")).as_deref(), Some("b"));
+        assert_eq!(conflicts(rule(MappingRuleKind::RenameColumn, "x", "b")).as_deref(), Some("b"));
+        assert_eq!(conflicts(rule(MappingRuleKind::Constant, "", "b")).as_deref(), Some("b"));
+        assert_eq!(conflicts(rule(MappingRuleKind::RenameColumn, "x", "created_at")).as_deref(), Some("created_at"));
+        assert_eq!(conflicts(rule(MappingRuleKind::RenameColumn, "b", "c")), None);
+        assert_eq!(conflicts(rule(MappingRuleKind::Exclude, "a", "")), None);
+    }
+}
@@ -0,0 +1,43 @@
+/*! This file contains the MigrationRun entity and its status. */
+
+/// Status of one "Move All Tables" run from a source to a target database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationRunStatus {
+    /// Started and not finished yet, e.g. the app was closed halfway
+    Running,
+    Finished,
+}
+
+impl MigrationRunStatus {
+    pub fn name(&self) -> &str {
+        match self {
+            MigrationRunStatus::Running => "RUNNING",
+            MigrationRunStatus::Finished => "FINISHED",
+        }
+    }
+
+    pub fn from_name(name: &str) -> MigrationRunStatus {
+        match name {
+            "FINISHED" => MigrationRunStatus::Finished,
+            _ => MigrationRunStatus::Running,
+        }
+    }
+}
+
+/// One "Move All Tables" run, as recorded in SQLite
+#[derive(Debug, Clone)]
+pub struct MigrationRun {
+    pub id: i64,
+    pub status: MigrationRunStatus,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// Last primary key committed to the target for a table left halfway
+#[derive(Debug, Clone)]
+pub struct TableCheckpoint {
+    pub table_name: String,
+    pub primary_key_column: String,
+    pub last_primary_key: String,
+    pub updated_at: String,
+}
@@ -1,4 +1,14 @@
 /*! This file contains the domain entities of the application. */
 
+pub mod ddl_script;
+pub mod mapping_rule;
+pub mod migration_run;
+pub mod row_filter;
+pub mod schema;
+pub mod schema_diff;
 pub mod table;
-pub mod two_column;
\ No newline at end of file
+pub mod table_sync;
+pub mod transform_rule;
+pub mod two_column;
+pub mod two_db_error;
+pub mod two_value;
\ No newline at end of file
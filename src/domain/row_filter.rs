@@ -0,0 +1,25 @@
+/*! This file contains the RowFilter entity, which rows of a source table are moved. */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RowFilter {
+    pub id: i64,
+    /// Source database
+    pub database: String,
+    /// Source table
+    pub table_name: String,
+    /// SQL condition on the columns of the table, e.g. `tenant_id = 42`
+    pub condition: String,
+    /// The rows matching `condition` start a subset: only they, and the rows they
+    /// reference through foreign keys, are moved from every table
+    pub is_seed: bool,
+}
+
+impl RowFilter {
+    pub fn kind_name(&self) -> &str {
+        if self.is_seed {
+            "SEED"
+        } else {
+            "FILTER"
+        }
+    }
+}
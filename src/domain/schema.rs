@@ -0,0 +1,42 @@
+/*! This file contains the schema of a table as read from the catalog. */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// As printed by `format_type`, e.g. `character varying(255)` or `numeric(10,2)`
+    pub data_type: String,
+    pub is_nullable: bool,
+    /// Default expression, e.g. `nextval('document_id_seq'::regclass)`
+    pub default: Option<String>,
+    /// `ALWAYS` or `BY DEFAULT` for identity columns, as in `information_schema.columns`
+    pub identity_generation: Option<String>,
+}
+
+/// A constraint or an index
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintSchema {
+    pub name: String,
+    /// Definition without the name, two constraints with the same definition are the same
+    pub definition: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableSchema {
+    pub name: String,
+    /// In the order of the table
+    pub columns: Vec<ColumnSchema>,
+    pub primary_key: Option<ConstraintSchema>,
+    pub unique_constraints: Vec<ConstraintSchema>,
+    pub foreign_keys: Vec<ConstraintSchema>,
+    /// Indexes that do not back a constraint
+    pub indexes: Vec<ConstraintSchema>,
+}
+
+impl TableSchema {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+}
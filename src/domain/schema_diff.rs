@@ -0,0 +1,110 @@
+/*! This file contains the SchemaDiff entity, the differences between the schemas of the source and target databases. */
+
+use serde::Serialize;
+
+/// What a difference is about
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaObject {
+    Table,
+    Column,
+    PrimaryKey,
+    UniqueConstraint,
+    ForeignKey,
+    Index,
+}
+
+impl SchemaObject {
+    pub fn name(&self) -> &str {
+        match self {
+            SchemaObject::Table => "TABLE",
+            SchemaObject::Column => "COLUMN",
+            SchemaObject::PrimaryKey => "PRIMARY KEY",
+            SchemaObject::UniqueConstraint => "UNIQUE",
+            SchemaObject::ForeignKey => "FOREIGN KEY",
+            SchemaObject::Index => "INDEX",
+        }
+    }
+
+    pub fn from_name(name: &str) -> SchemaObject {
+        match name {
+            "COLUMN" => SchemaObject::Column,
+            "PRIMARY KEY" => SchemaObject::PrimaryKey,
+            "UNIQUE" => SchemaObject::UniqueConstraint,
+            "FOREIGN KEY" => SchemaObject::ForeignKey,
+            "INDEX" => SchemaObject::Index,
+            _ => SchemaObject::Table,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DifferenceKind {
+    /// Missing in the target, e.g. a column whose data is not moved
+    OnlyInSource,
+    /// Missing in the source, e.g. a column left to its default
+    OnlyInTarget,
+    DataType,
+    Nullability,
+    Default,
+    /// Same object with another definition, e.g. a primary key on other columns
+    Definition,
+}
+
+impl DifferenceKind {
+    pub fn name(&self) -> &str {
+        match self {
+            DifferenceKind::OnlyInSource => "ONLY IN SOURCE",
+            DifferenceKind::OnlyInTarget => "ONLY IN TARGET",
+            DifferenceKind::DataType => "DATA TYPE",
+            DifferenceKind::Nullability => "NULLABILITY",
+            DifferenceKind::Default => "DEFAULT",
+            DifferenceKind::Definition => "DEFINITION",
+        }
+    }
+
+    pub fn from_name(name: &str) -> DifferenceKind {
+        match name {
+            "ONLY IN SOURCE" => DifferenceKind::OnlyInSource,
+            "ONLY IN TARGET" => DifferenceKind::OnlyInTarget,
+            "DATA TYPE" => DifferenceKind::DataType,
+            "NULLABILITY" => DifferenceKind::Nullability,
+            "DEFAULT" => DifferenceKind::Default,
+            _ => DifferenceKind::Definition,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaDifference {
+    pub table_name: String,
+    pub object: SchemaObject,
+    /// Name of the column, constraint or index, the table name for a table
+    pub object_name: String,
+    pub kind: DifferenceKind,
+    /// Value in the source, e.g. the data type, none when the object is only in the target
+    pub source_value: Option<String>,
+    pub target_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDiff {
+    pub source_database: String,
+    pub target_database: String,
+    /// Grouped by table, in table name order
+    pub differences: Vec<SchemaDifference>,
+}
+
+impl SchemaDiff {
+    /// Names of the tables with differences, in order
+    pub fn table_names(&self) -> Vec<&str> {
+        let mut table_names = self.differences.iter().map(|d| d.table_name.as_str()).collect::<Vec<_>>();
+        table_names.dedup();
+        table_names
+    }
+
+    pub fn differences_of<'a>(&'a self, table_name: &'a str) -> impl Iterator<Item = &'a SchemaDifference> {
+        self.differences.iter().filter(move |d| d.table_name == table_name)
+    }
+}
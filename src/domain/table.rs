@@ -10,6 +10,7 @@ pub struct Table {
     pub export_order: i64,
     pub is_self_referencing: bool,
     pub self_referencing_column: String,
+    pub self_referencing_strategy: SelfReferencingStrategy,
     pub row_count: i64,
     pub is_exported: bool,
 }
@@ -25,6 +26,7 @@ impl Default for Table {
             export_order: 0,
             is_self_referencing: false,
             self_referencing_column: String::from(""),
+            self_referencing_strategy: SelfReferencingStrategy::Unknown,
             row_count: 0,
             is_exported: false,
         }
@@ -56,6 +58,13 @@ impl TableType {
             TableType::VIEW => "VIEW",
         }
     }
+
+    pub fn from_name(name: &str) -> TableType {
+        match name {
+            "VIEW" => TableType::VIEW,
+            _ => TableType::BaseTable,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -71,4 +80,40 @@ impl ExportComplexityType {
             ExportComplexityType::COMPLEX => "COMPLEX",
         }
     }
+
+    pub fn from_name(name: &str) -> ExportComplexityType {
+        match name {
+            "COMPLEX" => ExportComplexityType::COMPLEX,
+            _ => ExportComplexityType::SIMPLE,
+        }
+    }
+}
+
+/// How rows of a self-referencing table are loaded so that parents exist before their children
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelfReferencingStrategy {
+    /// Not decided yet, or the table is not self-referencing
+    Unknown,
+    /// Rows are read in a recursive walk over the self-referencing column, parents first
+    ParentFirst,
+    /// Rows are loaded with the self-referencing column NULL, then the column is filled in a second pass
+    Backfill,
+}
+
+impl SelfReferencingStrategy {
+    pub fn name(&self) -> &str {
+        match self {
+            SelfReferencingStrategy::Unknown => "",
+            SelfReferencingStrategy::ParentFirst => "PARENT_FIRST",
+            SelfReferencingStrategy::Backfill => "BACKFILL",
+        }
+    }
+
+    pub fn from_name(name: &str) -> SelfReferencingStrategy {
+        match name {
+            "PARENT_FIRST" => SelfReferencingStrategy::ParentFirst,
+            "BACKFILL" => SelfReferencingStrategy::Backfill,
+            _ => SelfReferencingStrategy::Unknown,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,26 @@
+/*! This file contains the TableSync entity, how the rows changed since the last sync of a source table are found. */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableSync {
+    pub id: i64,
+    /// Source database
+    pub database: String,
+    /// Source table
+    pub table_name: String,
+    /// Column set to a higher value whenever a row is inserted or changed, e.g. `updated_at`.
+    /// Empty when the rows missing from the target are found by primary key
+    pub change_column: String,
+    /// Highest value of `change_column` synced, as text, empty before the first sync
+    pub watermark: String,
+    pub synced_at: Option<String>,
+}
+
+impl TableSync {
+    pub fn mode_name(&self) -> &str {
+        if self.change_column.is_empty() {
+            "PRIMARY KEY"
+        } else {
+            "WATERMARK"
+        }
+    }
+}
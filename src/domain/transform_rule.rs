@@ -0,0 +1,66 @@
+/*! This file contains the TransformRule entity, how the values of a source column are changed on their way to the target. */
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TransformKind {
+    /// SHA-256 of the value, in hex
+    #[default]
+    Hash,
+    /// A made up value of the category `value`, e.g. `email`, the same for the same source value
+    Fake,
+    /// NULL in every row
+    Null,
+    /// `value` in every row
+    Fixed,
+    /// Every match of the regular expression `value` replaced by `replacement`
+    Replace,
+    /// A keyed token of the same type, the same for the same source value in every table,
+    /// so foreign keys still join when the columns on both sides are tokenized.
+    /// The sequence of a tokenized serial column moves past the highest token.
+    Tokenize,
+}
+
+impl TransformKind {
+    pub const ALL: [TransformKind; 6] = [
+        TransformKind::Hash,
+        TransformKind::Fake,
+        TransformKind::Null,
+        TransformKind::Fixed,
+        TransformKind::Replace,
+        TransformKind::Tokenize,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            TransformKind::Hash => "HASH",
+            TransformKind::Fake => "FAKE",
+            TransformKind::Null => "NULL",
+            TransformKind::Fixed => "FIXED",
+            TransformKind::Replace => "REGEX REPLACE",
+            TransformKind::Tokenize => "TOKENIZE",
+        }
+    }
+
+    /// None for a name of no kind, a rule is never applied as another kind
+    pub fn from_name(name: &str) -> Option<TransformKind> {
+        TransformKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Categories of made up values of a FAKE rule
+pub const FAKE_CATEGORIES: [&str; 6] = ["name", "first_name", "last_name", "email", "phone", "text"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransformRule {
+    pub id: i64,
+    /// Source database
+    pub database: String,
+    /// Source table
+    pub table_name: String,
+    /// Source column
+    pub column_name: String,
+    pub kind: TransformKind,
+    /// Category for Fake, value for Fixed, regular expression for Replace
+    pub value: String,
+    /// For Replace, `$1` is the first group of the match
+    pub replacement: String,
+}
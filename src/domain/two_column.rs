@@ -4,4 +4,8 @@
 pub struct TwoColumn {
     pub name: String,
     pub data_type: String,
+    /// `GENERATED ALWAYS AS IDENTITY`, explicit values need `OVERRIDING SYSTEM VALUE`
+    pub is_identity_always: bool,
+    /// Length of a `varchar(n)` or `character(n)` column, in characters
+    pub max_length: Option<i32>,
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+/*! This file contains the TwoDbError entity. */
+
+use std::error::Error;
+use std::fmt;
+
+/// Error returned by the repositories, the use cases and the core actions
+#[derive(Debug, Clone, PartialEq)]
+pub enum TwoDbError {
+    /// The server cannot be reached, or it refused the credentials
+    Connection {
+        database: String,
+        message: String,
+    },
+    /// A query on `information_schema` or `pg_catalog` failed
+    Catalog {
+        database: String,
+        message: String,
+    },
+    /// Rows of a table cannot be read from the source or written to the target
+    Transfer {
+        table: String,
+        message: String,
+    },
+    /// The target rejected a row, e.g. a foreign key or a unique constraint
+    ConstraintViolation {
+        table: String,
+        constraint: String,
+        /// As reported by PostgreSQL, e.g. `(document_id)=(55)`
+        key: String,
+        /// Table missing the parent row, only for foreign key violations
+        referenced_table: Option<String>,
+        message: String,
+    },
+    /// A statement of a DDL script failed, the whole script was rolled back
+    SchemaChange {
+        database: String,
+        statement: String,
+        message: String,
+    },
+    /// The SQLite knowledge store cannot be read or written
+    Knowledge(String),
+    /// A file exported for the user, e.g. a report or a DDL script, cannot be written
+    Export {
+        path: String,
+        message: String,
+    },
+    /// A setting is missing or invalid
+    Configuration(String),
+}
+
+impl fmt::Display for TwoDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TwoDbError::Connection { database, message } => {
+                write!(f, "Cannot connect to database {}: {}", database, message)
+            }
+            TwoDbError::Catalog { database, message } => {
+                write!(f, "Cannot read the catalog of database {}: {}", database, message)
+            }
+            TwoDbError::Transfer { table, message } => {
+                write!(f, "Cannot move table {}: {}", table, message)
+            }
+            TwoDbError::ConstraintViolation { table, constraint, key, message, .. } => {
+                write!(f, "Table {} violates constraint {} for key {}: {}", table, constraint, key, message)
+            }
+            TwoDbError::SchemaChange { database, statement, message } => {
+                write!(f, "Cannot change the schema of database {} with `{}`: {}", database, statement, message)
+            }
+            TwoDbError::Knowledge(message) => write!(f, "Knowledge store error: {}", message),
+            TwoDbError::Export { path, message } => write!(f, "Cannot write {}: {}", path, message),
+            TwoDbError::Configuration(message) => write!(f, "Configuration error: {}", message),
+        }
+    }
+}
+
+impl Error for TwoDbError {}
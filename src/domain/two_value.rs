@@ -0,0 +1,157 @@
+/*! This file contains the TwoValue entity. */
+
+use std::fmt;
+use std::net::IpAddr;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+/// One cell of a row, carried with its type from the source to the target database
+#[derive(Debug, Clone, PartialEq)]
+pub enum TwoValue {
+    Null,
+    Bool(bool),
+    Int2(i16),
+    Int4(i32),
+    Int8(i64),
+    Float4(f32),
+    Float8(f64),
+    /// text, varchar, bpchar and name
+    Text(String),
+    /// Kept as its exact decimal text, e.g. `-12.3400`
+    Numeric(String),
+    /// Amount in the smallest currency unit, e.g. cents
+    Money(i64),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    /// `zone` is in seconds west of UTC, as PostgreSQL sends it
+    TimeTz { time: NaiveTime, zone: i32 },
+    Timestamp(NaiveDateTime),
+    TimestampTz(DateTime<Utc>),
+    Interval { months: i32, days: i32, microseconds: i64 },
+    Uuid([u8; 16]),
+    /// json and jsonb, kept as JSON text
+    Json(String),
+    Bytea(Vec<u8>),
+    /// inet and cidr
+    Inet { addr: IpAddr, netmask: u8 },
+    MacAddr([u8; 6]),
+    /// Label of a user-defined enum
+    Enum(String),
+    /// One-dimensional array of any of the other values
+    Array(Vec<TwoValue>),
+}
+
+impl TwoValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, TwoValue::Null)
+    }
+}
+
+impl fmt::Display for TwoValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TwoValue::Null => write!(f, "NULL"),
+            TwoValue::Bool(value) => write!(f, "{}", value),
+            TwoValue::Int2(value) => write!(f, "{}", value),
+            TwoValue::Int4(value) => write!(f, "{}", value),
+            TwoValue::Int8(value) => write!(f, "{}", value),
+            TwoValue::Float4(value) => write!(f, "{}", value),
+            TwoValue::Float8(value) => write!(f, "{}", value),
+            TwoValue::Text(value) => write!(f, "{}", value),
+            TwoValue::Numeric(value) => write!(f, "{}", value),
+            TwoValue::Money(value) => {
+                let sign = if *value < 0 { "-" } else { "" };
+                write!(f, "{}{}.{:02}", sign, (value / 100).abs(), (value % 100).abs())
+            }
+            TwoValue::Date(value) => write!(f, "{}", value),
+            TwoValue::Time(value) => write!(f, "{}", value),
+            TwoValue::TimeTz { time, zone } => {
+                let offset = -zone;
+                let sign = if offset < 0 { '-' } else { '+' };
+                write!(f, "{}{}{:02}:{:02}", time, sign, offset.abs() / 3600, offset.abs() % 3600 / 60)
+            }
+            TwoValue::Timestamp(value) => write!(f, "{}", value),
+            TwoValue::TimestampTz(value) => write!(f, "{}", value.to_rfc3339()),
+            TwoValue::Interval { months, days, microseconds } => write_interval(f, *months, *days, *microseconds),
+            TwoValue::Uuid(value) => {
+                for (i, part) in [&value[0..4], &value[4..6], &value[6..8], &value[8..10], &value[10..16]].iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "-")?;
+                    }
+                    write_hex(f, part)?;
+                }
+                Ok(())
+            }
+            TwoValue::Json(value) => write!(f, "{}", value),
+            TwoValue::Bytea(value) => {
+                write!(f, "\\x")?;
+                write_hex(f, value)
+            }
+            TwoValue::Inet { addr, netmask } => write!(f, "{}/{}", addr, netmask),
+            TwoValue::MacAddr(value) => {
+                write!(f, "{}", value.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":"))
+            }
+            TwoValue::Enum(value) => write!(f, "{}", value),
+            TwoValue::Array(values) => {
+                write!(f, "{{{}}}", values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(","))
+            }
+        }
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+}
+
+/// As PostgreSQL prints it with `IntervalStyle = postgres`, e.g. `1 year 2 mons -3 days +04:05:06.5`
+///
+/// A positive part after a negative one is signed, the time is printed if nonzero or alone.
+fn write_interval(f: &mut fmt::Formatter<'_>, months: i32, days: i32, microseconds: i64) -> fmt::Result {
+    let mut is_zero = true;
+    let mut is_before = false;
+    for (value, unit) in [(months / 12, "year"), (months % 12, "mon"), (days, "day")] {
+        if value == 0 {
+            continue;
+        }
+        let separator = if is_zero { "" } else { " " };
+        let sign = if is_before && value > 0 { "+" } else { "" };
+        let plural = if value != 1 { "s" } else { "" };
+        write!(f, "{}{}{} {}{}", separator, sign, value, unit, plural)?;
+        is_before = value < 0;
+        is_zero = false;
+    }
+    if !is_zero && microseconds == 0 {
+        return Ok(());
+    }
+
+    let sign = if microseconds < 0 { "-" } else if is_before { "+" } else { "" };
+    let total = microseconds.unsigned_abs();
+    let seconds = total / 1_000_000;
+    write!(
+        f, "{}{}{:02}:{:02}:{:02}",
+        if is_zero { "" } else { " " }, sign, seconds / 3600, seconds / 60 % 60, seconds % 60,
+    )?;
+    match total % 1_000_000 {
+        0 => Ok(()),
+        fraction => write!(f, ".{}", format!("{:06}", fraction).trim_end_matches('0')),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(months: i32, days: i32, microseconds: i64) -> String {
+        TwoValue::Interval { months, days, microseconds }.to_string()
+    }
+
+    #[test]
+    fn prints_intervals_as_postgresql() {
+        assert_eq!(interval(0, 0, 0), "00:00:00");
+        assert_eq!(interval(14, 3, 14_706_500_000), "1 year 2 mons 3 days 04:05:06.5");
+        assert_eq!(interval(1, 1, 0), "1 mon 1 day");
+        assert_eq!(interval(-12, 0, 0), "-1 years");
+        assert_eq!(interval(0, -1, 3_600_000_000), "-1 days +01:00:00");
+        assert_eq!(interval(0, 2, -90_000_001), "2 days -00:01:30.000001");
+        assert_eq!(interval(0, 0, 100 * 3_600_000_000), "100:00:00");
+    }
+}
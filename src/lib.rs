@@ -9,3 +9,4 @@ mod state;
 mod twoui;
 pub mod application;
 pub mod adapters;
+pub mod cli;
@@ -4,4 +4,19 @@ pub struct WindowsState {
     pub window_move_one_table_open: bool,
     pub window_move_all_tables_open: bool,
     pub window_reset_open: bool,
+    #[serde(default)]
+    pub window_schema_diff_open: bool,
+    #[serde(default)]
+    pub window_apply_ddl_open: bool,
+    /// Apply DDL also drops the constraints and indexes of the target only
+    #[serde(default)]
+    pub apply_ddl_destructive: bool,
+    #[serde(default)]
+    pub window_mapping_open: bool,
+    #[serde(default)]
+    pub window_transform_open: bool,
+    #[serde(default)]
+    pub window_filter_open: bool,
+    #[serde(default)]
+    pub window_watermark_open: bool,
 }
\ No newline at end of file
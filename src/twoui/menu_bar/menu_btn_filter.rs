@@ -0,0 +1,101 @@
+use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
+use crate::core::row_filter::{add_row_filter, delete_row_filter, get_row_filters};
+use crate::domain::two_db_error::TwoDbError;
+
+impl TwoDBApp {
+    pub fn menu_btn_filter_render(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.menu_button("Filter", |ui| {
+            if ui.button("Row Filters").clicked() {
+                ui.close_menu();
+                self.windows_state.window_filter_open = true;
+                self.reload_row_filters();
+            }
+        });
+
+        // Window Row Filters
+        if self.windows_state.window_filter_open {
+            let mut open = true;
+            let mut deleted_filter = None;
+            let mut add_clicked = false;
+
+            egui::Window::new("Row Filters")
+                .open(&mut open)
+                .default_size([720.0, 420.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Filters of the tables of {}", self.row_filter_form.database));
+                    ui.label("With a SEED, only the rows it matches and the rows they reference are moved");
+                    egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+                        egui::Grid::new("row_filters").striped(true).show(ui, |ui| {
+                            ui.strong("Table");
+                            ui.strong("Kind");
+                            ui.strong("Condition");
+                            ui.end_row();
+
+                            for filter in &self.row_filters {
+                                ui.label(&filter.table_name);
+                                ui.label(filter.kind_name());
+                                ui.label(&filter.condition);
+                                if ui.button("Delete").clicked() {
+                                    deleted_filter = Some(filter.id);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    // New filter
+                    let form = &mut self.row_filter_form;
+                    egui::Grid::new("row_filter_form").show(ui, |ui| {
+                        ui.label("Source table");
+                        ui.text_edit_singleline(&mut form.table_name);
+                        ui.end_row();
+
+                        ui.label("Condition");
+                        ui.text_edit_singleline(&mut form.condition);
+                        ui.end_row();
+
+                        ui.label("Seed");
+                        ui.checkbox(&mut form.is_seed, "Move the matching rows and the rows they reference");
+                        ui.end_row();
+                    });
+                    add_clicked = ui.button("Add").clicked();
+                });
+            self.windows_state.window_filter_open = open;
+
+            if let Some(id) = deleted_filter {
+                self.row_filter_event(delete_row_filter(id));
+            }
+            if add_clicked {
+                let result = add_row_filter(&self.row_filter_form);
+                if result.is_ok() {
+                    self.row_filter_form.condition.clear();
+                }
+                self.row_filter_event(result);
+            }
+        }
+    }
+
+    fn row_filter_event(&mut self, result: Result<(), TwoDbError>) {
+        match result {
+            Ok(_) => self.reload_row_filters(),
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+
+    /// Filters of the source database, read again from SQLite
+    fn reload_row_filters(&mut self) {
+        let result = ConnectionConfig::load().and_then(|config| {
+            let filters = get_row_filters(&config.source)?;
+            Ok((config.source, filters))
+        });
+        match result {
+            Ok((source_database_name, filters)) => {
+                self.row_filter_form.database = source_database_name;
+                self.row_filters = filters;
+            }
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+}
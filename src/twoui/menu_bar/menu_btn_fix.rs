@@ -5,7 +5,9 @@ impl TwoDBApp {
     pub fn menu_btn_fix_render(&mut self, _: &egui::Context, ui: &mut egui::Ui) {
         ui.menu_button("Fix", |ui| {
             if ui.button("Fix Numeric Data").clicked() {
-                fix_numeric();
+                if let Err(err) = fix_numeric() {
+                    TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone());
+                }
             }
         });
     }
@@ -0,0 +1,137 @@
+use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
+use crate::core::mapping::{add_mapping_rule, delete_mapping_rule, get_mapping_rules};
+use crate::domain::mapping_rule::MappingRuleKind;
+use crate::domain::two_db_error::TwoDbError;
+
+impl TwoDBApp {
+    pub fn menu_btn_mapping_render(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.menu_button("Mapping", |ui| {
+            if ui.button("Mapping Rules").clicked() {
+                ui.close_menu();
+                self.windows_state.window_mapping_open = true;
+                self.reload_mapping_rules();
+            }
+        });
+
+        // Window Mapping Rules
+        if self.windows_state.window_mapping_open {
+            let mut open = true;
+            let mut deleted_rule = None;
+            let mut add_clicked = false;
+
+            egui::Window::new("Mapping Rules")
+                .open(&mut open)
+                .default_size([720.0, 420.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Rules of the tables of {}", self.mapping_rule_form.database));
+                    egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+                        egui::Grid::new("mapping_rules").striped(true).show(ui, |ui| {
+                            ui.strong("Table");
+                            ui.strong("Rule");
+                            ui.strong("Column");
+                            ui.strong("Target");
+                            ui.strong("Value");
+                            ui.end_row();
+
+                            for rule in &self.mapping_rules {
+                                ui.label(&rule.table_name);
+                                ui.label(rule.kind.name());
+                                ui.label(&rule.column_name);
+                                ui.label(&rule.target_name);
+                                ui.label(&rule.value);
+                                if ui.button("Delete").clicked() {
+                                    deleted_rule = Some(rule.id);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    // New rule, only the fields used by its kind are shown
+                    let form = &mut self.mapping_rule_form;
+                    egui::Grid::new("mapping_rule_form").show(ui, |ui| {
+                        ui.label("Rule");
+                        egui::ComboBox::from_id_source("mapping_rule_kind")
+                            .selected_text(form.kind.name())
+                            .show_ui(ui, |ui| {
+                                for kind in MappingRuleKind::ALL {
+                                    ui.selectable_value(&mut form.kind, kind, kind.name());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Source table");
+                        ui.text_edit_singleline(&mut form.table_name);
+                        ui.end_row();
+
+                        if matches!(form.kind, MappingRuleKind::RenameColumn | MappingRuleKind::Exclude) {
+                            ui.label("Source column");
+                            ui.text_edit_singleline(&mut form.column_name);
+                            ui.end_row();
+                        }
+                        if form.kind != MappingRuleKind::Exclude {
+                            ui.label(if form.kind == MappingRuleKind::RenameTable { "Target table" } else { "Target column" });
+                            ui.text_edit_singleline(&mut form.target_name);
+                            ui.end_row();
+                        }
+                        if matches!(form.kind, MappingRuleKind::Constant | MappingRuleKind::Default) {
+                            ui.label(if form.kind == MappingRuleKind::Constant { "Value" } else { "SQL expression" });
+                            ui.text_edit_singleline(&mut form.value);
+                            ui.end_row();
+                        }
+                    });
+                    add_clicked = ui.button("Add").clicked();
+                });
+            self.windows_state.window_mapping_open = open;
+
+            if let Some(id) = deleted_rule {
+                self.mapping_rule_event(delete_mapping_rule(id));
+            }
+            if add_clicked {
+                let form = &mut self.mapping_rule_form;
+                // Only the fields used by the kind are saved
+                if !matches!(form.kind, MappingRuleKind::RenameColumn | MappingRuleKind::Exclude) {
+                    form.column_name.clear();
+                }
+                if form.kind == MappingRuleKind::Exclude {
+                    form.target_name.clear();
+                }
+                if !matches!(form.kind, MappingRuleKind::Constant | MappingRuleKind::Default) {
+                    form.value.clear();
+                }
+                let result = add_mapping_rule(&self.mapping_rule_form);
+                if result.is_ok() {
+                    let form = &mut self.mapping_rule_form;
+                    form.column_name.clear();
+                    form.target_name.clear();
+                    form.value.clear();
+                }
+                self.mapping_rule_event(result);
+            }
+        }
+    }
+
+    fn mapping_rule_event(&mut self, result: Result<(), TwoDbError>) {
+        match result {
+            Ok(_) => self.reload_mapping_rules(),
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+
+    /// Rules of the source database, read again from SQLite
+    fn reload_mapping_rules(&mut self) {
+        let result = ConnectionConfig::load().and_then(|config| {
+            let rules = get_mapping_rules(&config.source)?;
+            Ok((config.source, rules))
+        });
+        match result {
+            Ok((source_database_name, rules)) => {
+                self.mapping_rule_form.database = source_database_name;
+                self.mapping_rules = rules;
+            }
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+}
@@ -25,7 +25,9 @@ impl TwoDBApp {
                         if ui.button("Yes").clicked() {
                             // Delete Database in SQLite
                             info!("Resetting database");
-                            reset_database();
+                            if let Err(err) = reset_database() {
+                                TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone());
+                            }
                         }
                         if ui.button("No").clicked() {
                             info!("Cancel Resetting database");
@@ -0,0 +1,186 @@
+use std::thread;
+use egui::Align2;
+use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
+use crate::core::schema_ddl::{apply_ddl_script, generate_ddl_script, write_ddl_script};
+use crate::core::schema_diff::{compare_schemas, export_schema_diff, get_schema_diff};
+
+const SCHEMA_DIFF_EXPORT_PATH: &str = "schema_diff.json";
+const DDL_SCRIPT_PATH: &str = "schema_migration.sql";
+
+impl TwoDBApp {
+    pub fn menu_btn_schema_render(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.menu_button("Schema", |ui| {
+            if ui.button("Compare Schemas").clicked() {
+                ui.close_menu();
+                self.windows_state.window_schema_diff_open = true;
+                self.button_compare_schemas_event();
+            }
+            if ui.button("Last Comparison").clicked() {
+                ui.close_menu();
+                self.windows_state.window_schema_diff_open = true;
+                self.button_last_comparison_event();
+            }
+            ui.separator();
+            if ui.button("Generate DDL Script").clicked() {
+                ui.close_menu();
+                self.button_ddl_script_event(false, false);
+            }
+            if ui.button("Apply DDL Script").clicked() {
+                ui.close_menu();
+                self.windows_state.window_apply_ddl_open = true;
+                self.windows_state.apply_ddl_destructive = false;
+            }
+        });
+
+        // Window Apply DDL, the script changes the target database
+        if self.windows_state.window_apply_ddl_open {
+            let mut confirmed = false;
+            let destructive = &mut self.windows_state.apply_ddl_destructive;
+            egui::Window::new("Apply DDL Script")
+                .open(&mut self.windows_state.window_apply_ddl_open)
+                .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+                .show(ctx, |ui| {
+                    ui.label(format!("The script is written to {} and run on the target database.", DDL_SCRIPT_PATH));
+                    ui.checkbox(destructive, "Also drop the unique constraints, unique indexes and foreign keys of the target only");
+                    ui.label("Are you sure you want to change the target schema?");
+                    if ui.button("Yes").clicked() {
+                        confirmed = true;
+                    }
+                });
+            if confirmed {
+                self.windows_state.window_apply_ddl_open = false;
+                self.button_ddl_script_event(true, self.windows_state.apply_ddl_destructive);
+            }
+        }
+
+        // Window Schema Diff
+        if self.windows_state.window_schema_diff_open {
+            egui::Window::new("Schema Diff")
+                .open(&mut self.windows_state.window_schema_diff_open)
+                .default_size([720.0, 420.0])
+                .show(ctx, |ui| {
+                    let schema_diff = self.schema_diff.lock().unwrap();
+                    let Some(schema_diff) = schema_diff.as_ref() else {
+                        ui.label("No comparison yet");
+                        return;
+                    };
+
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} → {}: {} differences in {} tables",
+                            schema_diff.source_database,
+                            schema_diff.target_database,
+                            schema_diff.differences.len(),
+                            schema_diff.table_names().len(),
+                        ));
+                        if ui.button("Export JSON").clicked() {
+                            match export_schema_diff(schema_diff, SCHEMA_DIFF_EXPORT_PATH) {
+                                Ok(_) => {
+                                    let text = format!("Schema diff exported to {}", SCHEMA_DIFF_EXPORT_PATH);
+                                    TwoDBApp::notify(text, self.is_busy.clone(), self.toast_text.clone());
+                                }
+                                Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+                            }
+                        }
+                    });
+                    ui.separator();
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for table_name in schema_diff.table_names() {
+                            egui::CollapsingHeader::new(table_name).show(ui, |ui| {
+                                egui::Grid::new(table_name).striped(true).show(ui, |ui| {
+                                    ui.strong("Object");
+                                    ui.strong("Name");
+                                    ui.strong("Difference");
+                                    ui.strong("Source");
+                                    ui.strong("Target");
+                                    ui.end_row();
+
+                                    for difference in schema_diff.differences_of(table_name) {
+                                        ui.label(difference.object.name());
+                                        ui.label(&difference.object_name);
+                                        ui.label(difference.kind.name());
+                                        ui.label(difference.source_value.as_deref().unwrap_or("-"));
+                                        ui.label(difference.target_value.as_deref().unwrap_or("-"));
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                        }
+                    });
+                });
+        }
+    }
+
+    fn button_compare_schemas_event(&mut self) {
+        let is_busy = self.is_busy.clone();
+        *is_busy.lock().unwrap() = true;
+        let toast_text = self.toast_text.clone();
+        let toast_error = self.toast_error.clone();
+        let schema_diff = self.schema_diff.clone();
+
+        thread::spawn(move || {
+            let config = match ConnectionConfig::load() {
+                Ok(config) => config,
+                Err(err) => return TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            };
+
+            match compare_schemas(&config.source, &config.target) {
+                Ok(result) => {
+                    let text = format!(
+                        "Done Compare Schemas for {} and {}, {} differences",
+                        config.source, config.target, result.differences.len()
+                    );
+                    *schema_diff.lock().unwrap() = Some(result);
+                    TwoDBApp::notify(text, is_busy, toast_text);
+                }
+                Err(err) => TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            }
+        });
+    }
+
+    /// Write the DDL script of the current schemas, and run it on the target if `apply`
+    ///
+    /// Destructive statements are only run if `destructive`, see `DdlScript::allows_destructive`
+    fn button_ddl_script_event(&mut self, apply: bool, destructive: bool) {
+        let is_busy = self.is_busy.clone();
+        *is_busy.lock().unwrap() = true;
+        let toast_text = self.toast_text.clone();
+        let toast_error = self.toast_error.clone();
+
+        thread::spawn(move || {
+            let result = ConnectionConfig::load()
+                .and_then(|config| generate_ddl_script(&config.source, &config.target, destructive))
+                .and_then(|script| {
+                    write_ddl_script(&script, DDL_SCRIPT_PATH)?;
+                    if apply {
+                        apply_ddl_script(&script)?;
+                    }
+                    Ok(script)
+                });
+
+            match result {
+                Ok(script) => {
+                    let action = if apply { "applied to" } else { "written for" };
+                    let text = format!(
+                        "DDL script {} {} {}, {} statements",
+                        DDL_SCRIPT_PATH, action, script.target_database, script.statements.len()
+                    );
+                    TwoDBApp::notify(text, is_busy, toast_text);
+                }
+                Err(err) => TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            }
+        });
+    }
+
+    /// Show the comparison kept in SQLite, without reading the catalogs again
+    fn button_last_comparison_event(&mut self) {
+        let result = ConnectionConfig::load()
+            .and_then(|config| get_schema_diff(&config.source, &config.target));
+        match result {
+            Ok(result) => *self.schema_diff.lock().unwrap() = Some(result),
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::thread;
+use crate::TwoDBApp;
+use crate::core::action::r#move::{sync_all_tables, sync_one_table};
+use crate::core::config::ConnectionConfig;
+use crate::core::table_sync::{delete_table_sync, get_table_syncs, set_change_column};
+use crate::domain::two_db_error::TwoDbError;
+
+impl TwoDBApp {
+    pub fn menu_btn_sync_render(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.menu_button("Sync", |ui| {
+            if ui.button("Sync All Tables").clicked() {
+                ui.close_menu();
+                let is_busy = self.is_busy.clone();
+                *is_busy.lock().unwrap() = true;
+                let toast_text = self.toast_text.clone();
+                let toast_error = self.toast_error.clone();
+
+                thread::spawn(move || {
+                    let source_database_name = match ConnectionConfig::load() {
+                        Ok(config) => config.source,
+                        Err(err) => return TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+                    };
+
+                    match sync_all_tables(&source_database_name) {
+                        Ok(failures) if failures.is_empty() => {
+                            let text = format!("Done Sync Tables for {}", source_database_name);
+                            TwoDBApp::notify(text, is_busy, toast_text);
+                        }
+                        Ok(failures) => {
+                            let text = format!(
+                                "{} tables not synced for {}, first error: {}",
+                                failures.len(), source_database_name, failures[0].1
+                            );
+                            TwoDBApp::notify_error(text, is_busy, toast_error);
+                        }
+                        Err(err) => TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+                    }
+                });
+            }
+
+            if ui.button("Watermarks").clicked() {
+                ui.close_menu();
+                self.windows_state.window_watermark_open = true;
+                self.reload_table_syncs();
+            }
+        });
+
+        // Window Watermarks
+        if self.windows_state.window_watermark_open {
+            let mut open = true;
+            let mut deleted_sync = None;
+            let mut set_clicked = false;
+            let mut sync_clicked = false;
+
+            egui::Window::new("Watermarks")
+                .open(&mut open)
+                .default_size([720.0, 420.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Syncs of the tables of {}", self.table_sync_form.database));
+                    ui.label("A table without change column is synced by primary key, the rows missing from the target or changed are copied, on the columns moved as they are");
+                    egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+                        egui::Grid::new("table_syncs").striped(true).show(ui, |ui| {
+                            ui.strong("Table");
+                            ui.strong("Mode");
+                            ui.strong("Change column");
+                            ui.strong("Watermark");
+                            ui.strong("Synced at");
+                            ui.end_row();
+
+                            for sync in &self.table_syncs {
+                                ui.label(&sync.table_name);
+                                ui.label(sync.mode_name());
+                                ui.label(&sync.change_column);
+                                ui.label(&sync.watermark);
+                                ui.label(sync.synced_at.as_deref().unwrap_or(""));
+                                if ui.button("Delete").clicked() {
+                                    deleted_sync = Some(sync.id);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    let form = &mut self.table_sync_form;
+                    egui::Grid::new("table_sync_form").show(ui, |ui| {
+                        ui.label("Source table");
+                        ui.text_edit_singleline(&mut form.table_name);
+                        ui.end_row();
+
+                        ui.label("Change column");
+                        ui.text_edit_singleline(&mut form.change_column);
+                        ui.end_row();
+                    });
+                    ui.horizontal(|ui| {
+                        set_clicked = ui.button("Set Change Column").clicked();
+                        sync_clicked = ui.button("Sync Table").clicked();
+                    });
+                });
+            self.windows_state.window_watermark_open = open;
+
+            if let Some(id) = deleted_sync {
+                self.table_sync_event(delete_table_sync(id));
+            }
+            if set_clicked {
+                let result = set_change_column(&self.table_sync_form);
+                if result.is_ok() {
+                    self.table_sync_form.change_column.clear();
+                }
+                self.table_sync_event(result);
+            }
+            if sync_clicked {
+                let result = sync_one_table(self.table_sync_form.table_name.clone());
+                self.table_sync_event(result);
+            }
+        }
+    }
+
+    fn table_sync_event(&mut self, result: Result<(), TwoDbError>) {
+        match result {
+            Ok(_) => self.reload_table_syncs(),
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+
+    /// Syncs of the source database, read again from SQLite
+    fn reload_table_syncs(&mut self) {
+        let result = ConnectionConfig::load().and_then(|config| {
+            let syncs = get_table_syncs(&config.source)?;
+            Ok((config.source, syncs))
+        });
+        match result {
+            Ok((source_database_name, syncs)) => {
+                self.table_sync_form.database = source_database_name;
+                self.table_syncs = syncs;
+            }
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+}
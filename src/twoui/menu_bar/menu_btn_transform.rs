@@ -0,0 +1,151 @@
+use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
+use crate::core::transform::{add_transform_rule, delete_transform_rule, get_transform_rules};
+use crate::domain::transform_rule::{TransformKind, FAKE_CATEGORIES};
+use crate::domain::two_db_error::TwoDbError;
+
+impl TwoDBApp {
+    pub fn menu_btn_transform_render(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.menu_button("Transform", |ui| {
+            if ui.button("Transform Rules").clicked() {
+                ui.close_menu();
+                self.windows_state.window_transform_open = true;
+                self.reload_transform_rules();
+            }
+        });
+
+        // Window Transform Rules
+        if self.windows_state.window_transform_open {
+            let mut open = true;
+            let mut deleted_rule = None;
+            let mut add_clicked = false;
+
+            egui::Window::new("Transform Rules")
+                .open(&mut open)
+                .default_size([720.0, 420.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Rules of the columns of {}", self.transform_rule_form.database));
+                    ui.label("FAKE and TOKENIZE rules need the TWODB_TRANSFORM_KEY environment variable");
+                    egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+                        egui::Grid::new("transform_rules").striped(true).show(ui, |ui| {
+                            ui.strong("Table");
+                            ui.strong("Column");
+                            ui.strong("Rule");
+                            ui.strong("Value");
+                            ui.strong("Replacement");
+                            ui.end_row();
+
+                            for rule in &self.transform_rules {
+                                ui.label(&rule.table_name);
+                                ui.label(&rule.column_name);
+                                ui.label(rule.kind.name());
+                                ui.label(&rule.value);
+                                ui.label(&rule.replacement);
+                                if ui.button("Delete").clicked() {
+                                    deleted_rule = Some(rule.id);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    // New rule, only the fields used by its kind are shown
+                    let form = &mut self.transform_rule_form;
+                    egui::Grid::new("transform_rule_form").show(ui, |ui| {
+                        ui.label("Rule");
+                        egui::ComboBox::from_id_source("transform_rule_kind")
+                            .selected_text(form.kind.name())
+                            .show_ui(ui, |ui| {
+                                for kind in TransformKind::ALL {
+                                    ui.selectable_value(&mut form.kind, kind, kind.name());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Source table");
+                        ui.text_edit_singleline(&mut form.table_name);
+                        ui.end_row();
+
+                        ui.label("Source column");
+                        ui.text_edit_singleline(&mut form.column_name);
+                        ui.end_row();
+
+                        match form.kind {
+                            TransformKind::Fake => {
+                                ui.label("Category");
+                                egui::ComboBox::from_id_source("transform_rule_category")
+                                    .selected_text(form.value.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for category in FAKE_CATEGORIES {
+                                            ui.selectable_value(&mut form.value, category.to_string(), category);
+                                        }
+                                    });
+                                ui.end_row();
+                            }
+                            TransformKind::Fixed => {
+                                ui.label("Value");
+                                ui.text_edit_singleline(&mut form.value);
+                                ui.end_row();
+                            }
+                            TransformKind::Replace => {
+                                ui.label("Regular expression");
+                                ui.text_edit_singleline(&mut form.value);
+                                ui.end_row();
+                                ui.label("Replacement");
+                                ui.text_edit_singleline(&mut form.replacement);
+                                ui.end_row();
+                            }
+                            TransformKind::Hash | TransformKind::Null | TransformKind::Tokenize => {}
+                        }
+                    });
+                    add_clicked = ui.button("Add").clicked();
+                });
+            self.windows_state.window_transform_open = open;
+
+            if let Some(id) = deleted_rule {
+                self.transform_rule_event(delete_transform_rule(id));
+            }
+            if add_clicked {
+                let form = &mut self.transform_rule_form;
+                // Only the fields used by the kind are saved
+                if !matches!(form.kind, TransformKind::Fake | TransformKind::Fixed | TransformKind::Replace) {
+                    form.value.clear();
+                }
+                if form.kind != TransformKind::Replace {
+                    form.replacement.clear();
+                }
+                let result = add_transform_rule(&self.transform_rule_form);
+                if result.is_ok() {
+                    let form = &mut self.transform_rule_form;
+                    form.column_name.clear();
+                    form.value.clear();
+                    form.replacement.clear();
+                }
+                self.transform_rule_event(result);
+            }
+        }
+    }
+
+    fn transform_rule_event(&mut self, result: Result<(), TwoDbError>) {
+        match result {
+            Ok(_) => self.reload_transform_rules(),
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+
+    /// Rules of the source database, read again from SQLite
+    fn reload_transform_rules(&mut self) {
+        let result = ConnectionConfig::load().and_then(|config| {
+            let rules = get_transform_rules(&config.source)?;
+            Ok((config.source, rules))
+        });
+        match result {
+            Ok((source_database_name, rules)) => {
+                self.transform_rule_form.database = source_database_name;
+                self.transform_rules = rules;
+            }
+            Err(err) => TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone()),
+        }
+    }
+}
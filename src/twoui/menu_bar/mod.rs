@@ -1,15 +1,19 @@
 mod menu_btn_reset;
 mod menu_btn_fix;
+mod menu_btn_schema;
+mod menu_btn_mapping;
+mod menu_btn_transform;
+mod menu_btn_filter;
+mod menu_btn_sync;
 
-use std::env::var;
 use std::thread;
 use egui::Align2;
 use log::info;
-use crate::core::action::r#move::{get_queries_one_table, move_one_table};
-use crate::core::get_knowledge::{get_tables_with_condition};
+use crate::core::action::r#move::{get_queries_one_table, move_all_tables, move_one_table};
 /// Render the menu bar
 
 use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
 
 impl TwoDBApp {
     pub fn menu_btn_migrate_data_render(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
@@ -22,20 +26,30 @@ impl TwoDBApp {
                 let is_busy = self.is_busy.clone();
                 *is_busy.lock().unwrap() = true;
                 let toast_text = self.toast_text.clone();
+                let toast_error = self.toast_error.clone();
 
                 thread::spawn(move || {
-                    let source_database_name = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
+                    let config = match ConnectionConfig::load() {
+                        Ok(config) => config,
+                        Err(err) => return TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+                    };
+                    let source_database_name = config.source;
+                    let target_database_name = config.target;
 
-                    let tables_from_sqlite = get_tables_with_condition(
-                    " WHERE is_exported = 0"
-                    );
-                    info!("Tables from sqlite: {:?}", tables_from_sqlite);
-                    for table in tables_from_sqlite {
-                        move_one_table(table.name);
+                    match move_all_tables(&source_database_name, &target_database_name) {
+                        Ok(failures) if failures.is_empty() => {
+                            let text = format!("Done Move Tables for {}", source_database_name);
+                            TwoDBApp::notify(text, is_busy, toast_text);
+                        }
+                        Ok(failures) => {
+                            let text = format!(
+                                "{} tables not moved for {}, first error: {}",
+                                failures.len(), source_database_name, failures[0].1
+                            );
+                            TwoDBApp::notify_error(text, is_busy, toast_error);
+                        }
+                        Err(err) => TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
                     }
-
-                    let text = format!("Done Move Tables for {}", source_database_name);
-                    TwoDBApp::notify(text, is_busy, toast_text);
                 });
             }
         });
@@ -56,11 +70,15 @@ impl TwoDBApp {
                     });
                     if ui.button("Move!").clicked() {
                         info!("Table name: {}", self.table_name);
-                        move_one_table(self.table_name.clone());
+                        if let Err(err) = move_one_table(self.table_name.clone()) {
+                            TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone());
+                        }
                     }
                     if ui.button("Build Query!").clicked() {
                         info!("Table name: {}", self.table_name);
-                        get_queries_one_table(&self.table_name);
+                        if let Err(err) = get_queries_one_table(&self.table_name) {
+                            TwoDBApp::notify_error(err.to_string(), self.is_busy.clone(), self.toast_error.clone());
+                        }
                     }
                 });
         }
@@ -0,0 +1,41 @@
+use std::thread;
+use egui::Ui;
+use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
+use crate::core::dependency_graph::update_export_order;
+
+impl TwoDBApp {
+    pub fn render_update_export_order_button(&mut self, ui: &mut Ui) {
+        if ui.button("Update Export Order").clicked() {
+            ui.close_menu();
+            self.button_update_export_order_event();
+        }
+    }
+
+    fn button_update_export_order_event(&mut self) {
+        let is_busy = self.is_busy.clone();
+        *is_busy.lock().unwrap() = true;
+        let toast_text = self.toast_text.clone();
+        let toast_error = self.toast_error.clone();
+
+        thread::spawn(move || {
+            let config = match ConnectionConfig::load() {
+                Ok(config) => config,
+                Err(err) => return TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            };
+            let database_name_source = config.source;
+            let cycles = match update_export_order(&database_name_source) {
+                Ok(cycles) => cycles,
+                Err(err) => return TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            };
+
+            let text = if cycles.is_empty() {
+                format!("Done Update Export Order for {}", database_name_source)
+            } else {
+                let cycles_str = cycles.iter().map(|c| format!("[{}]", c.join(", "))).collect::<Vec<_>>().join(" ");
+                format!("Done Update Export Order for {}, foreign key cycles: {}", database_name_source, cycles_str)
+            };
+            TwoDBApp::notify(text, is_busy, toast_text);
+        });
+    }
+}
@@ -1,6 +1,6 @@
-use std::env::var;
 use std::thread;
 use crate::TwoDBApp;
+use crate::core::config::ConnectionConfig;
 use crate::core::action::update::update_all_tables;
 
 impl TwoDBApp {
@@ -15,16 +15,25 @@ impl TwoDBApp {
         let is_busy = self.is_busy.clone();
         *is_busy.lock().unwrap() = true;
         let toast_text = self.toast_text.clone();
+        let toast_error = self.toast_error.clone();
 
         thread::spawn(move || {
-            let database_name_source = var("POSTGRES_DB_SOURCE").unwrap_or(String::from(""));
-            update_all_tables(&database_name_source);
+            let config = match ConnectionConfig::load() {
+                Ok(config) => config,
+                Err(err) => return TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            };
+            let database_name_source = config.source;
+            let database_name_target = config.target;
+            let result = update_all_tables(&database_name_source)
+                .and_then(|_| update_all_tables(&database_name_target));
 
-            let database_name_target = var("POSTGRES_DB_TARGET").unwrap_or(String::from(""));
-            update_all_tables(&database_name_target);
-
-            let text = format!("Done Get All Tables for {} and {}", database_name_source, database_name_target);
-            TwoDBApp::notify(text, is_busy, toast_text);
+            match result {
+                Ok(_) => {
+                    let text = format!("Done Get All Tables for {} and {}", database_name_source, database_name_target);
+                    TwoDBApp::notify(text, is_busy, toast_text);
+                }
+                Err(err) => TwoDBApp::notify_error(err.to_string(), is_busy, toast_error),
+            }
         });
     }
 }
\ No newline at end of file
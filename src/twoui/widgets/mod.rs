@@ -1,4 +1,5 @@
 mod btn_update_self_referencing_tables;
 mod btn_update_tables;
 mod btn_update_clean_tables;
-mod btn_update_empty_tables;
\ No newline at end of file
+mod btn_update_empty_tables;
+mod btn_update_export_order;